@@ -39,12 +39,13 @@ fn co2_for_mode(mode: &str, distance_km: f64) -> f64 {
         "Bicicleta" => TransportType::Bicicleta,
         "Caminata" => TransportType::Caminata,
         "Indriver" | "Uber" => TransportType::Uber,
+        "Taxi" => TransportType::Taxi,
         _ => TransportType::Bus,
     };
     transport.co2_per_km() * distance_km
 }
 
-fn build_result(mode: &str, grams: f64, distance_km: f64) -> CarbonResult {
+fn build_result(_mode: &str, grams: f64, distance_km: f64) -> CarbonResult {
     // Average car emits 150g CO2/km per passenger
     let car_grams = 150.0 * distance_km;
     let saved = (car_grams - grams).max(0.0);
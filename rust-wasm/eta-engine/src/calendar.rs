@@ -0,0 +1,89 @@
+//! Which day it is for traffic purposes.
+//!
+//! ETAs use `TrafficConditions::at` with the loaded traffic calendar (the
+//! default high seasons until one is loaded). The moment comes from the
+//! caller, or is today at the requested hour; "today" can be pinned with
+//! `set_date_override` so a test or demo sees a fixed calendar day.
+
+use serde::{Deserialize, Serialize};
+use shared_types::calendar::{DayType, TrafficCalendar};
+use shared_types::{clock, TrafficConditions};
+use std::sync::RwLock;
+use wasm_bindgen::prelude::*;
+
+static CALENDAR: RwLock<Option<TrafficCalendar>> = RwLock::new(None);
+static DATE_OVERRIDE: RwLock<Option<i64>> = RwLock::new(None);
+
+/// Calendar context behind an ETA.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrafficDay {
+    /// `YYYY-MM-DD` Cancún.
+    pub date: String,
+    pub day_type: DayType,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub season: Option<String>,
+}
+
+/// Now, or the override when one is set.
+pub fn now_s() -> i64 {
+    DATE_OVERRIDE.read().ok().and_then(|g| *g).unwrap_or_else(clock::now_unix_s)
+}
+
+/// The moment an ETA is for: `departure` (ISO 8601, Cancún when it has no
+/// offset) when given, else today at `hour`, else now.
+pub fn moment(departure: Option<&str>, hour: Option<u8>) -> Result<i64, String> {
+    match (departure, hour) {
+        (Some(d), _) => clock::parse_datetime(d),
+        (None, Some(h)) if h < 24 => Ok(clock::at_hour(now_s(), h)),
+        (None, Some(h)) => Err(format!("Invalid hour: {}", h)),
+        (None, None) => Ok(now_s()),
+    }
+}
+
+/// Traffic at `unix_s` under the loaded calendar.
+pub fn traffic_at(unix_s: i64) -> (TrafficConditions, TrafficDay) {
+    let guard = CALENDAR.read().ok();
+    let default = TrafficCalendar::default();
+    let calendar = guard.as_ref().and_then(|g| g.as_ref()).unwrap_or(&default);
+    let (y, m, d) = clock::cancun_date(unix_s);
+    let day = TrafficDay {
+        date: format!("{:04}-{:02}-{:02}", y, m, d),
+        day_type: calendar.day_type(unix_s),
+        season: calendar.season(unix_s).map(|s| s.name.clone()),
+    };
+    (TrafficConditions::at(unix_s, calendar), day)
+}
+
+// --- WASM ---
+
+pub fn load_traffic_calendar_core(json: &str) -> Result<usize, String> {
+    let calendar: TrafficCalendar = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    calendar.validate()?;
+    let n = calendar.high_season.len();
+    *CALENDAR.write().map_err(|_| "Lock failed".to_string())? = Some(calendar);
+    Ok(n)
+}
+
+pub fn set_date_override_core(date: &str) -> Result<(), String> {
+    let t = match date.trim() {
+        "" => None,
+        d => Some(clock::parse_datetime(d)?),
+    };
+    *DATE_OVERRIDE.write().map_err(|_| "Lock failed".to_string())? = t;
+    Ok(())
+}
+
+/// Replaces the traffic calendar: `high_season` (`name`, `start`/`end` as
+/// `MM-DD`, `factor`) and extra `holidays` (`YYYY-MM-DD`). Returns how many
+/// seasons it holds.
+#[wasm_bindgen]
+pub fn load_traffic_calendar(json: &str) -> Result<usize, JsValue> {
+    load_traffic_calendar_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Pins "now" to `date` (ISO 8601, Cancún when it has no offset); empty
+/// goes back to the device clock.
+#[wasm_bindgen]
+pub fn set_date_override(date: &str) -> Result<(), JsValue> {
+    set_date_override_core(date).map_err(|e| JsValue::from_str(&e))
+}
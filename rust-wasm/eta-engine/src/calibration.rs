@@ -0,0 +1,344 @@
+//! Per-route speed profiles fitted from observed trips.
+//!
+//! A trip record is one ride on a route, given as a distance or two stop
+//! coordinates, with its departure time and duration. Records are grouped
+//! by route and hour bucket and the median speed of each group becomes its
+//! profile. Observed durations already include traffic and dwell, so a
+//! calibrated ETA applies neither again (rain still applies). Speeds are per
+//! street km: a record given by coordinates gets the same circuity
+//! correction as a two-stop ETA. The table serializes to JSON for the PWA to
+//! keep offline.
+
+use serde::{Deserialize, Serialize};
+use shared_types::{clock, TransportType};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use wasm_bindgen::prelude::*;
+
+use crate::circuity;
+
+pub const PROFILE_VERSION: u32 = 1;
+/// Fewer records than this leave the bucket on the mode default.
+const MIN_SAMPLES: usize = 3;
+/// Records shorter than this are mostly dwell.
+const MIN_DISTANCE_M: f64 = 50.0;
+const MIN_SPEED_KMH: f64 = 2.0;
+const MAX_SPEED_KMH: f64 = 100.0;
+
+static PROFILES: RwLock<Option<SpeedProfiles>> = RwLock::new(None);
+
+/// Hour buckets following the shape of `TrafficConditions::from_hour`.
+pub fn hour_bucket(hour: u8) -> &'static str {
+    match hour {
+        0..=5 => "night",
+        6 => "early",
+        7..=9 => "am_peak",
+        10..=12 => "midday",
+        13..=14 => "lunch",
+        15..=16 => "afternoon",
+        17..=19 => "pm_peak",
+        _ => "evening",
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TripRecord {
+    pub route_id: String,
+    /// Stop coordinates `[lat, lng]`, used when `distance_m` is missing.
+    #[serde(default)]
+    pub from: Option<[f64; 2]>,
+    #[serde(default)]
+    pub to: Option<[f64; 2]>,
+    #[serde(default)]
+    pub distance_m: Option<f64>,
+    /// Mode label, for the street distance between `from` and `to`; a bus
+    /// when missing.
+    #[serde(default)]
+    pub transport_type: Option<String>,
+    /// ISO 8601, Cancún time when it has no offset.
+    pub departure: String,
+    pub duration_s: f64,
+}
+
+impl TripRecord {
+    /// (hour bucket, km/h), or why the record is unusable.
+    fn observation(&self) -> Result<(&'static str, f64), String> {
+        let t = clock::parse_datetime(&self.departure)?;
+        let distance_m = match (self.distance_m, self.from, self.to) {
+            (Some(d), _, _) => d,
+            (None, Some(a), Some(b)) => {
+                let mode = self.transport_type.as_deref().map_or(TransportType::Bus, TransportType::from_label);
+                circuity::road_distance_m(&mode, a, b)
+            }
+            _ => return Err("no distance_m nor from/to coordinates".to_string()),
+        };
+        if distance_m < MIN_DISTANCE_M { return Err(format!("distance {:.0} m too short", distance_m)); }
+        if self.duration_s <= 0.0 { return Err("duration_s must be positive".to_string()); }
+        let speed = distance_m / 1000.0 / (self.duration_s / 3600.0);
+        if !(MIN_SPEED_KMH..=MAX_SPEED_KMH).contains(&speed) {
+            return Err(format!("implausible speed {:.1} km/h", speed));
+        }
+        Ok((hour_bucket(clock::cancun_hour(t)), speed))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SpeedProfile {
+    /// Median observed km/h, traffic and dwell included.
+    pub speed_kmh: f64,
+    pub samples: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SpeedProfiles {
+    pub version: u32,
+    /// route id → hour bucket → profile.
+    pub profiles: BTreeMap<String, BTreeMap<String, SpeedProfile>>,
+}
+
+impl SpeedProfiles {
+    pub fn get(&self, route_id: &str, hour: u8) -> Option<&SpeedProfile> {
+        self.profiles.get(route_id)?.get(hour_bucket(hour))
+    }
+
+    pub fn len(&self) -> usize {
+        self.profiles.values().map(|b| b.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct CalibrationReport {
+    pub profiles: SpeedProfiles,
+    pub used: usize,
+    /// `(record index, reason)`.
+    pub rejected: Vec<(usize, String)>,
+}
+
+fn median(v: &mut [f64]) -> f64 {
+    v.sort_by(f64::total_cmp);
+    let n = v.len();
+    if n % 2 == 1 { v[n / 2] } else { (v[n / 2 - 1] + v[n / 2]) / 2.0 }
+}
+
+pub fn fit_profiles(records: &[TripRecord]) -> CalibrationReport {
+    let mut groups: BTreeMap<(&str, &str), Vec<f64>> = BTreeMap::new();
+    let mut rejected = Vec::new();
+    for (i, r) in records.iter().enumerate() {
+        match r.observation() {
+            Ok((bucket, speed)) => groups.entry((r.route_id.as_str(), bucket)).or_default().push(speed),
+            Err(e) => rejected.push((i, e)),
+        }
+    }
+
+    let mut profiles = SpeedProfiles { version: PROFILE_VERSION, ..Default::default() };
+    let mut used = 0;
+    for ((route, bucket), mut speeds) in groups {
+        if speeds.len() < MIN_SAMPLES { continue; }
+        used += speeds.len();
+        let profile = SpeedProfile {
+            speed_kmh: (median(&mut speeds) * 10.0).round() / 10.0,
+            samples: speeds.len() as u32,
+        };
+        profiles.profiles.entry(route.to_string()).or_default().insert(bucket.to_string(), profile);
+    }
+    CalibrationReport { profiles, used, rejected }
+}
+
+/// Calibrated profile for `route_id` at `hour`, if one is loaded.
+pub fn calibrated(route_id: &str, hour: u8) -> Option<SpeedProfile> {
+    PROFILES.read().ok()?.as_ref()?.get(route_id, hour).cloned()
+}
+
+// --- WASM ---
+
+pub fn load_speed_profiles_core(json: &str) -> Result<usize, String> {
+    let table: SpeedProfiles = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    if table.version != PROFILE_VERSION {
+        return Err(format!("Unsupported profile version {}", table.version));
+    }
+    let n = table.len();
+    *PROFILES.write().map_err(|_| "Lock failed".to_string())? = Some(table);
+    Ok(n)
+}
+
+/// Fits profiles from `TripRecord[]` JSON and loads them. Returns the
+/// report; `profiles` is the table to store for `load_speed_profiles`.
+#[wasm_bindgen]
+pub fn calibrate_speeds(records_json: &str) -> Result<JsValue, JsValue> {
+    let records: Vec<TripRecord> = serde_json::from_str(records_json).map_err(|e| JsValue::from_str(&e.to_string()))?;
+    let report = fit_profiles(&records);
+    *PROFILES.write().map_err(|_| JsValue::from_str("Lock failed"))? = Some(report.profiles.clone());
+    serde_wasm_bindgen::to_value(&report).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Loads a table saved from `calibrate_speeds`. Returns how many
+/// route/hour profiles it holds.
+#[wasm_bindgen]
+pub fn load_speed_profiles(json: &str) -> Result<usize, JsValue> {
+    load_speed_profiles_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// The loaded table as JSON, for offline storage.
+#[wasm_bindgen]
+pub fn export_speed_profiles() -> Result<String, JsValue> {
+    let guard = PROFILES.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    let table = guard.clone().unwrap_or(SpeedProfiles { version: PROFILE_VERSION, ..Default::default() });
+    serde_json::to_string(&table).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_eta_core;
+    use crate::tests::{bus_leg, lock_globals};
+    use shared_types::TransportType;
+
+    fn record(route: &str, departure: &str, distance_m: f64, duration_s: f64) -> TripRecord {
+        TripRecord {
+            route_id: route.to_string(),
+            from: None,
+            to: None,
+            distance_m: Some(distance_m),
+            transport_type: None,
+            departure: departure.to_string(),
+            duration_s,
+        }
+    }
+
+    #[test]
+    fn test_hour_buckets() {
+        let cases = [
+            (0, "night"), (5, "night"), (6, "early"), (7, "am_peak"), (9, "am_peak"), (10, "midday"),
+            (12, "midday"), (13, "lunch"), (14, "lunch"), (15, "afternoon"), (16, "afternoon"),
+            (17, "pm_peak"), (19, "pm_peak"), (20, "evening"), (23, "evening"),
+        ];
+        for (hour, bucket) in cases {
+            assert_eq!(hour_bucket(hour), bucket, "{}h", hour);
+        }
+    }
+
+    #[test]
+    fn test_median() {
+        assert_eq!(median(&mut [3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(median(&mut [4.0, 1.0, 3.0, 2.0]), 2.5);
+    }
+
+    #[test]
+    fn test_rejects_unusable_records() {
+        let ok = record("R1", "2026-05-12T08:10", 2000.0, 600.0);
+        assert_eq!(ok.observation(), Ok(("am_peak", 12.0)));
+
+        let short = record("R1", "2026-05-12T08:10", 30.0, 60.0);
+        let slow = record("R1", "2026-05-12T08:10", 100.0, 600.0);
+        let fast = record("R1", "2026-05-12T08:10", 5000.0, 60.0);
+        let stopped = record("R1", "2026-05-12T08:10", 2000.0, 0.0);
+        let no_distance = TripRecord { distance_m: None, ..ok.clone() };
+        let bad_time = TripRecord { departure: "ayer".to_string(), ..ok.clone() };
+        assert!(short.observation().unwrap_err().contains("too short"));
+        assert!(slow.observation().unwrap_err().contains("implausible speed 0.6"));
+        assert!(fast.observation().unwrap_err().contains("implausible speed 300.0"));
+        assert!(stopped.observation().unwrap_err().contains("positive"));
+        assert!(no_distance.observation().unwrap_err().contains("no distance_m"));
+        assert!(bad_time.observation().is_err());
+
+        // Coordinates stand in for a missing distance
+        let coords = TripRecord { distance_m: None, from: Some([21.1714, -86.8219]), to: Some([21.1460, -86.8230]), ..ok };
+        assert!(coords.observation().is_ok());
+    }
+
+    #[test]
+    fn test_fit_profiles() {
+        let records = vec![
+            // R1 morning peak: 10, 12 and 15 km/h
+            record("R1", "2026-05-12T07:30", 2000.0, 720.0),
+            record("R1", "2026-05-12T08:10", 2000.0, 600.0),
+            record("R1", "2026-05-12T09:45", 2500.0, 600.0),
+            // R1 midday: fewer than MIN_SAMPLES
+            record("R1", "2026-05-12T11:00", 2000.0, 400.0),
+            record("R1", "2026-05-12T11:30", 2000.0, 400.0),
+            // R2 evening: 20, 24, 24 and 30 km/h
+            record("R2", "2026-05-12T21:00", 5000.0, 900.0),
+            record("R2", "2026-05-12T21:10", 4000.0, 600.0),
+            record("R2", "2026-05-12T22:00", 4000.0, 600.0),
+            record("R2", "2026-05-12T23:00", 5000.0, 600.0),
+            // Rejected
+            record("R2", "2026-05-12T21:00", 20.0, 10.0),
+        ];
+        let report = fit_profiles(&records);
+        assert_eq!(report.rejected.len(), 1);
+        assert_eq!(report.rejected[0].0, 9);
+        assert_eq!(report.used, 7);
+        let p = &report.profiles;
+        assert_eq!(p.version, PROFILE_VERSION);
+        assert_eq!(p.len(), 2);
+        assert_eq!(p.get("R1", 8), Some(&SpeedProfile { speed_kmh: 12.0, samples: 3 }));
+        assert_eq!(p.get("R1", 11), None);
+        assert_eq!(p.get("R2", 22), Some(&SpeedProfile { speed_kmh: 24.0, samples: 4 }));
+    }
+
+    #[test]
+    fn test_load_checks_version() {
+        let _g = lock_globals();
+        let err = load_speed_profiles_core(r#"{"version": 99, "profiles": {}}"#).unwrap_err();
+        assert!(err.contains("version 99"));
+        assert!(load_speed_profiles_core("{").is_err());
+        let json = serde_json::to_string(&SpeedProfiles { version: PROFILE_VERSION, ..Default::default() }).unwrap();
+        assert_eq!(load_speed_profiles_core(&json), Ok(0));
+    }
+
+    #[test]
+    fn test_compute_eta_prefers_profile() {
+        let _g = lock_globals();
+        let mut table = SpeedProfiles { version: PROFILE_VERSION, ..Default::default() };
+        table.profiles.entry("R_CAL".to_string()).or_default()
+            .insert("am_peak".to_string(), SpeedProfile { speed_kmh: 11.0, samples: 12 });
+        load_speed_profiles_core(&serde_json::to_string(&table).unwrap()).unwrap();
+
+        let stops = vec![[21.1714, -86.8219], [21.1600, -86.8225], [21.1460, -86.8230]];
+        let mut input = bus_leg(stops, "08:15");
+        input.route_id = Some("R_CAL".to_string());
+        let eta = compute_eta_core(&input).unwrap();
+        assert!(eta.calibrated);
+        assert_eq!(eta.effective_speed_kmh, 11.0);
+        // No dwell on top of an observed speed
+        assert!((eta.minutes - eta.distance_km / 11.0 * 60.0).abs() < 1e-9);
+
+        // Other hours and routes keep the mode default
+        input.departure = Some("2026-05-12T12:00".to_string());
+        let midday = compute_eta_core(&input).unwrap();
+        assert!(!midday.calibrated);
+        assert_eq!(midday.effective_speed_kmh, TransportType::Bus.avg_speed_kmh() / midday.congestion_factor);
+        input.route_id = Some("R_OTHER".to_string());
+        input.departure = Some("2026-05-12T08:15".to_string());
+        assert!(!compute_eta_core(&input).unwrap().calibrated);
+        load_speed_profiles_core(r#"{"version": 1, "profiles": {}}"#).unwrap();
+    }
+
+    #[test]
+    fn test_profile_from_coordinates_reproduces_observed_time() {
+        let _g = lock_globals();
+        let (a, b) = ([21.1714, -86.8219], [21.1460, -86.8230]);
+        // Three 12-minute rides between two bare stops
+        let records: Vec<TripRecord> = ["08:00", "08:20", "08:40"].iter().map(|hhmm| TripRecord {
+            from: Some(a),
+            to: Some(b),
+            distance_m: None,
+            ..record("R_COORDS", &format!("2026-05-12T{}", hhmm), 0.0, 720.0)
+        }).collect();
+        let report = fit_profiles(&records);
+        assert_eq!(report.used, 3);
+        load_speed_profiles_core(&serde_json::to_string(&report.profiles).unwrap()).unwrap();
+
+        let mut input = bus_leg(vec![a, b], "08:30");
+        input.route_id = Some("R_COORDS".to_string());
+        let eta = compute_eta_core(&input).unwrap();
+        load_speed_profiles_core(r#"{"version": 1, "profiles": {}}"#).unwrap();
+        assert!(eta.calibrated);
+        // Fitted and applied on the same street distance: no circuity inflation
+        assert!((eta.in_vehicle_minutes - 12.0).abs() < 0.1, "{}", eta.in_vehicle_minutes);
+    }
+}
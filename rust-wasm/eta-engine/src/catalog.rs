@@ -0,0 +1,82 @@
+//! What the engine needs from the route catalog: headway, service hours,
+//! fare and stop coordinates per route id.
+
+use serde::{Deserialize, Serialize};
+use shared_types::normalize::normalize_str;
+use shared_types::schedule::Schedule;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use wasm_bindgen::prelude::*;
+
+static ROUTES: RwLock<Option<HashMap<String, RouteService>>> = RwLock::new(None);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RouteStop {
+    #[serde(rename = "nombre", alias = "parada")]
+    pub name: String,
+    pub lat: f64,
+    pub lng: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RouteService {
+    #[serde(default)]
+    pub frecuencia_minutos: Option<u32>,
+    #[serde(default)]
+    pub horario: Option<Schedule>,
+    #[serde(default)]
+    pub tarifa: Option<f64>,
+    #[serde(default)]
+    pub paradas: Vec<RouteStop>,
+}
+
+impl RouteService {
+    /// Index of the stop named `name` (accents, case and spacing aside), or
+    /// at position `name` when it is a number.
+    pub fn stop_index(&self, name: &str) -> Option<usize> {
+        if let Ok(i) = name.trim().parse::<usize>() {
+            return (i < self.paradas.len()).then_some(i);
+        }
+        let wanted = normalize_str(name);
+        self.paradas.iter().position(|s| normalize_str(&s.name) == wanted)
+    }
+}
+
+#[derive(Deserialize)]
+struct CatalogRoute {
+    id: String,
+    #[serde(flatten)]
+    service: RouteService,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Catalog {
+    Master { rutas: Vec<CatalogRoute> },
+    List(Vec<CatalogRoute>),
+}
+
+pub fn route(id: &str) -> Option<RouteService> {
+    ROUTES.read().ok()?.as_ref()?.get(id).cloned()
+}
+
+// --- WASM ---
+
+pub fn load_route_service_core(json: &str) -> Result<usize, String> {
+    let routes = match serde_json::from_str(json).map_err(|e| e.to_string())? {
+        Catalog::Master { rutas } => rutas,
+        Catalog::List(list) => list,
+    };
+    let table: HashMap<String, RouteService> = routes.into_iter().map(|r| (r.id, r.service)).collect();
+    let n = table.len();
+    *ROUTES.write().map_err(|_| "Lock failed".to_string())? = Some(table);
+    Ok(n)
+}
+
+/// Loads `frecuencia_minutos`, `horario`, `tarifa` and `paradas` per route
+/// from the catalog (`master_routes.json` or its `rutas` array). Returns how
+/// many routes.
+#[wasm_bindgen]
+pub fn load_route_service(json: &str) -> Result<usize, JsValue> {
+    load_route_service_core(json).map_err(|e| JsValue::from_str(&e))
+}
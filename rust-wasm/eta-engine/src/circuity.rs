@@ -0,0 +1,69 @@
+//! Street distance for straight-line estimates.
+//!
+//! A leg given only by its two ends, and `quick_eta`, would otherwise use
+//! the straight line. The loaded `CircuityModel` (mode defaults until one
+//! is loaded) stretches it to the expected street distance.
+
+use serde::Deserialize;
+use shared_types::circuity::{calibrate_zone, CircuityModel};
+use shared_types::TransportType;
+use std::sync::RwLock;
+use wasm_bindgen::prelude::*;
+
+static MODEL: RwLock<Option<CircuityModel>> = RwLock::new(None);
+
+/// Estimated street distance between two points, meters.
+pub fn road_distance_m(mode: &TransportType, from: [f64; 2], to: [f64; 2]) -> f64 {
+    let guard = MODEL.read().ok();
+    let default = CircuityModel::default();
+    let model = guard.as_ref().and_then(|g| g.as_ref()).unwrap_or(&default);
+    model.road_distance_m(mode, from, to)
+}
+
+/// A zone to calibrate: name and polygon `[lat, lng]`.
+#[derive(Deserialize)]
+struct ZoneArea {
+    name: String,
+    area: Vec<[f64; 2]>,
+}
+
+// --- WASM ---
+
+pub fn load_circuity_core(json: &str) -> Result<usize, String> {
+    let model: CircuityModel = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    model.validate()?;
+    let n = model.zones.len();
+    *MODEL.write().map_err(|_| "Lock failed".to_string())? = Some(model);
+    Ok(n)
+}
+
+pub fn calibrate_circuity_core(zones_json: &str, shapes_json: &str) -> Result<CircuityModel, String> {
+    let zones: Vec<ZoneArea> = serde_json::from_str(zones_json).map_err(|e| e.to_string())?;
+    let shapes: Vec<Vec<[f64; 2]>> = serde_json::from_str(shapes_json).map_err(|e| e.to_string())?;
+    let mut guard = MODEL.write().map_err(|_| "Lock failed".to_string())?;
+    let mut model = guard.clone().unwrap_or_default();
+    for z in zones {
+        let Some(zone) = calibrate_zone(&z.name, z.area, &shapes) else { continue };
+        model.zones.retain(|old| old.name != zone.name);
+        model.zones.push(zone);
+    }
+    *guard = Some(model.clone());
+    Ok(model)
+}
+
+/// Replaces the circuity model: `modes` (label → factor) and `zones`
+/// (`name`, `area`, `factor`). Returns how many zones it holds.
+#[wasm_bindgen]
+pub fn load_circuity(json: &str) -> Result<usize, JsValue> {
+    load_circuity_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Fits a factor for each zone (`[{ name, area }]`) from traced route
+/// shapes (`[lat, lng][][]`) and adds it to the loaded model. Zones with too
+/// little shape inside are left out. Returns the model to store for
+/// `load_circuity`.
+#[wasm_bindgen]
+pub fn calibrate_circuity(zones_json: &str, shapes_json: &str) -> Result<JsValue, JsValue> {
+    let model = calibrate_circuity_core(zones_json, shapes_json).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&model).map_err(|e| JsValue::from_str(&e.to_string()))
+}
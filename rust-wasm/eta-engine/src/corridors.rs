@@ -0,0 +1,212 @@
+//! Corridor congestion: Av. Kukulcán, Av. Tulum, Bonampak and the airport
+//! road each have their own rush pattern.
+//!
+//! A corridor is a named polygon and/or a list of stop-to-stop segments with
+//! a congestion factor per hour bucket. Each segment of a leg's path takes
+//! the factor of the corridor it lies in, or the city-wide factor outside
+//! them.
+
+use serde::{Deserialize, Serialize};
+use shared_types::geo::{Contains, Coord, LineString, Point, Polygon};
+use shared_types::{haversine_distance, ride, shape, TrafficConditions};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use wasm_bindgen::prelude::*;
+
+use crate::calibration::hour_bucket;
+
+/// A path segment belongs to a corridor segment when its midpoint is this
+/// close to it.
+const SEGMENT_MATCH_M: f64 = 60.0;
+
+static CORRIDORS: RwLock<Vec<Corridor>> = RwLock::new(Vec::new());
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Corridor {
+    pub name: String,
+    /// Polygon `[lat, lng]`.
+    #[serde(default)]
+    pub area: Option<Vec<[f64; 2]>>,
+    /// Stop-to-stop segments `[[lat, lng], [lat, lng]]`.
+    #[serde(default)]
+    pub segments: Vec<[[f64; 2]; 2]>,
+    /// Hour bucket (see `calibration::hour_bucket`) → congestion factor.
+    #[serde(default)]
+    pub profile: BTreeMap<String, f64>,
+    /// For buckets missing from `profile`; the city factor when absent.
+    #[serde(default)]
+    pub factor: Option<f64>,
+}
+
+impl Corridor {
+    fn validate(&self) -> Result<(), String> {
+        if self.area.as_ref().is_some_and(|a| a.len() < 3) {
+            return Err(format!("Corridor {}: area needs at least 3 points", self.name));
+        }
+        if self.area.is_none() && self.segments.is_empty() {
+            return Err(format!("Corridor {}: needs an area or segments", self.name));
+        }
+        if self.profile.values().chain(&self.factor).any(|f| !(1.0..=5.0).contains(f)) {
+            return Err(format!("Corridor {}: factors must be between 1 and 5", self.name));
+        }
+        Ok(())
+    }
+}
+
+/// Time spent on a stretch of the path.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SegmentEta {
+    /// `None` outside every corridor.
+    pub corridor: Option<String>,
+    pub distance_km: f64,
+    pub congestion_factor: f64,
+    pub minutes: f64,
+}
+
+struct Prepared<'a> {
+    corridor: &'a Corridor,
+    area: Option<Polygon<f64>>,
+}
+
+impl Prepared<'_> {
+    fn matches(&self, mid: [f64; 2]) -> bool {
+        self.area.as_ref().is_some_and(|a| a.contains(&Point::new(mid[1], mid[0])))
+            || self.corridor.segments.iter().any(|s| {
+                shape::project_point(s, mid).is_some_and(|p| p.off_shape_m <= SEGMENT_MATCH_M)
+            })
+    }
+}
+
+/// Per-corridor breakdown of `path` at `base_speed_kmh` under `traffic`.
+/// Consecutive segments in the same corridor are merged. Empty when no
+/// corridors are loaded.
+pub fn segment_etas(path: &[[f64; 2]], base_speed_kmh: f64, traffic: &TrafficConditions) -> Vec<SegmentEta> {
+    let Ok(corridors) = CORRIDORS.read() else { return Vec::new() };
+    if corridors.is_empty() { return Vec::new(); }
+    let prepared: Vec<Prepared> = corridors.iter().map(|c| Prepared {
+        corridor: c,
+        area: c.area.as_ref().map(|pts| {
+            Polygon::new(pts.iter().map(|p| Coord { x: p[1], y: p[0] }).collect::<LineString<f64>>(), vec![])
+        }),
+    }).collect();
+    let bucket = hour_bucket(traffic.hour);
+
+    let mut out: Vec<SegmentEta> = Vec::new();
+    for w in path.windows(2) {
+        let mid = [(w[0][0] + w[1][0]) / 2.0, (w[0][1] + w[1][1]) / 2.0];
+        let hit = prepared.iter().find(|p| p.matches(mid)).map(|p| p.corridor);
+        let factor = hit
+            .and_then(|c| c.profile.get(bucket).copied().or(c.factor))
+            .unwrap_or(traffic.congestion_factor);
+        let km = haversine_distance(w[0][0], w[0][1], w[1][0], w[1][1]) / 1000.0;
+        let minutes = ride::minutes(km, ride::speed_kmh(base_speed_kmh, factor, traffic), 0);
+        let name = hit.map(|c| c.name.clone());
+        match out.last_mut() {
+            Some(last) if last.corridor == name && last.congestion_factor == factor => {
+                last.distance_km += km;
+                last.minutes += minutes;
+            }
+            _ => out.push(SegmentEta { corridor: name, distance_km: km, congestion_factor: factor, minutes }),
+        }
+    }
+    out
+}
+
+// --- WASM ---
+
+pub fn load_corridors_core(json: &str) -> Result<usize, String> {
+    let corridors: Vec<Corridor> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    for c in &corridors {
+        c.validate()?;
+    }
+    let mut guard = CORRIDORS.write().map_err(|_| "Lock failed".to_string())?;
+    *guard = corridors;
+    Ok(guard.len())
+}
+
+/// Replaces the corridor table. Returns how many corridors it holds.
+#[wasm_bindgen]
+pub fn load_corridors(json: &str) -> Result<usize, JsValue> {
+    load_corridors_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compute_eta_core;
+    use crate::tests::{bus_leg, lock_globals};
+
+    /// Four 1 km-ish stretches heading east along lat 21.14.
+    fn path() -> Vec<[f64; 2]> {
+        (0..5).map(|i| [21.14, -86.80 + 0.01 * i as f64]).collect()
+    }
+
+    /// "Kukulcán" by area over the first two stretches (2.0 at the morning
+    /// peak, 1.8 otherwise), "Bonampak" by segment over the third with no
+    /// factor of its own.
+    const CORRIDORS_JSON: &str = r#"[
+        {"name": "Kukulcán", "area": [[21.13, -86.80], [21.15, -86.80], [21.15, -86.78], [21.13, -86.78]],
+         "profile": {"am_peak": 2.0}, "factor": 1.8},
+        {"name": "Bonampak", "segments": [[[21.14, -86.78], [21.14, -86.77]]]}
+    ]"#;
+
+    fn traffic(hour: u8) -> TrafficConditions {
+        TrafficConditions::from_hour(hour)
+    }
+
+    #[test]
+    fn test_validate() {
+        let c = |json: &str| serde_json::from_str::<Corridor>(json).unwrap().validate();
+        assert!(c(r#"{"name": "A", "area": [[21.1, -86.8], [21.2, -86.8], [21.2, -86.7]]}"#).is_ok());
+        assert!(c(r#"{"name": "A", "area": [[21.1, -86.8], [21.2, -86.8]]}"#).unwrap_err().contains("3 points"));
+        assert!(c(r#"{"name": "A"}"#).unwrap_err().contains("area or segments"));
+        assert!(c(r#"{"name": "A", "segments": [[[21.1, -86.8], [21.2, -86.8]]], "factor": 0.5}"#).is_err());
+        assert!(c(r#"{"name": "A", "segments": [[[21.1, -86.8], [21.2, -86.8]]], "profile": {"night": 6}}"#).is_err());
+        assert!(load_corridors_core(r#"[{"name": "A"}]"#).is_err());
+    }
+
+    #[test]
+    fn test_segments_by_area_and_segment() {
+        let _g = lock_globals();
+        load_corridors_core("[]").unwrap();
+        assert!(segment_etas(&path(), 22.0, &traffic(8)).is_empty());
+        assert_eq!(load_corridors_core(CORRIDORS_JSON), Ok(2));
+
+        let out = segment_etas(&path(), 22.0, &traffic(8));
+        let names: Vec<Option<&str>> = out.iter().map(|s| s.corridor.as_deref()).collect();
+        // The two stretches inside the area are merged
+        assert_eq!(names, [Some("Kukulcán"), Some("Bonampak"), None]);
+        let stretch = |i: usize| haversine_distance(path()[i][0], path()[i][1], path()[i + 1][0], path()[i + 1][1]) / 1000.0;
+        assert!((out[0].distance_km - (stretch(0) + stretch(1))).abs() < 1e-9);
+        // Profile, then the city factor for a corridor without one
+        assert_eq!(out[0].congestion_factor, 2.0);
+        assert_eq!(out[1].congestion_factor, 2.2);
+        assert_eq!(out[2].congestion_factor, 2.2);
+        assert!((out[0].minutes - out[0].distance_km / (22.0 / 2.0) * 60.0).abs() < 1e-9);
+
+        // Outside the profiled hours the corridor's own factor applies
+        let midday = segment_etas(&path(), 22.0, &traffic(12));
+        assert_eq!(midday[0].congestion_factor, 1.8);
+        assert_eq!(midday[1].congestion_factor, 1.1);
+        // Same factor but a different corridor: not merged
+        assert_eq!(midday.len(), 3);
+        load_corridors_core("[]").unwrap();
+    }
+
+    #[test]
+    fn test_compute_eta_reports_segments() {
+        let _g = lock_globals();
+        load_corridors_core(CORRIDORS_JSON).unwrap();
+        let eta = compute_eta_core(&bus_leg(path(), "08:15")).unwrap();
+        load_corridors_core("[]").unwrap();
+
+        assert_eq!(eta.segments.len(), 3);
+        let km: f64 = eta.segments.iter().map(|s| s.distance_km).sum();
+        let minutes: f64 = eta.segments.iter().map(|s| s.minutes).sum();
+        assert!((km - eta.distance_km).abs() < 1e-9);
+        // Corridor time plus the dwell at five stops
+        assert!((eta.minutes - (minutes + 2.5)).abs() < 1e-9);
+        let factor = (2.0 * eta.segments[0].distance_km + 2.2 * (km - eta.segments[0].distance_km)) / km;
+        assert!((eta.congestion_factor - factor).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,39 @@
+//! Fares for on-demand legs.
+//!
+//! Ride-hailing, motortaxi and taxi legs are priced from the loaded
+//! `FareTable` (Cancún defaults until one is loaded) by distance, time,
+//! taxi zone and airport. Other modes keep their flat fare.
+
+use shared_types::fares::FareTable;
+use shared_types::TransportType;
+use std::sync::RwLock;
+use wasm_bindgen::prelude::*;
+
+static TABLE: RwLock<Option<FareTable>> = RwLock::new(None);
+
+/// Fare in MXN for `mode` between two points; `None` without a rate card.
+pub fn fare(mode: &TransportType, from: [f64; 2], to: [f64; 2], distance_m: f64, minutes: f64) -> Option<f64> {
+    let guard = TABLE.read().ok();
+    let default = FareTable::default();
+    let table = guard.as_ref().and_then(|g| g.as_ref()).unwrap_or(&default);
+    table.fare(mode, from, to, distance_m, minutes)
+}
+
+// --- WASM ---
+
+pub fn load_fares_core(json: &str) -> Result<usize, String> {
+    let table: FareTable = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    table.validate()?;
+    let n = table.modes.len();
+    *TABLE.write().map_err(|_| "Lock failed".to_string())? = Some(table);
+    Ok(n)
+}
+
+/// Replaces the fare table: `modes` (label → `base`, `per_km`, `per_min`,
+/// `minimum`, `airport_surcharge`), `taxi_zones`, `zone_fares` and
+/// `airports`. Missing sections keep the defaults. Returns how many rate
+/// cards it holds.
+#[wasm_bindgen]
+pub fn load_fares(json: &str) -> Result<usize, JsValue> {
+    load_fares_core(json).map_err(|e| JsValue::from_str(&e))
+}
@@ -0,0 +1,81 @@
+//! Ferry legs on the loaded timetable.
+//!
+//! A ferry leg's ends must be at terminals joined by a line. Its wait runs
+//! until the next listed departure after the rider reaches the pier, and
+//! its ride is the crossing. The loaded `FerryTimetable` (the defaults until
+//! one is loaded) holds terminals, departures and fares.
+
+use shared_types::ferry::{Crossing, FareClass, FerryTerminal, FerryTimetable};
+use std::sync::RwLock;
+use wasm_bindgen::prelude::*;
+
+use crate::calendar;
+
+static TABLE: RwLock<Option<FerryTimetable>> = RwLock::new(None);
+
+/// Farthest a leg end may be from its terminal, meters.
+pub const TERMINAL_RADIUS_M: f64 = 1000.0;
+
+fn with_table<R>(f: impl FnOnce(&FerryTimetable) -> R) -> R {
+    let guard = TABLE.read().ok();
+    let default = FerryTimetable::default();
+    f(guard.as_ref().and_then(|g| g.as_ref()).unwrap_or(&default))
+}
+
+/// Terminals at the ends of a crossing, or the leg field at fault and why.
+pub fn terminals(from: [f64; 2], to: [f64; 2]) -> Result<(FerryTerminal, FerryTerminal), (&'static str, String)> {
+    with_table(|table| {
+        let near = |field, p| table.terminal_near(p, TERMINAL_RADIUS_M).cloned()
+            .ok_or((field, format!("no ferry terminal within {:.0} m", TERMINAL_RADIUS_M)));
+        let (a, b) = (near("from", from)?, near("to", to)?);
+        if table.lines_between(&a.id, &b.id).next().is_none() {
+            return Err(("to", format!("no ferry line from {} to {}", a.name, b.name)));
+        }
+        Ok((a, b))
+    })
+}
+
+/// First crossing from terminal `from` to `to` at or after `unix_s`.
+pub fn next_crossing(from: &str, to: &str, unix_s: i64, class: FareClass) -> Option<Crossing> {
+    with_table(|table| table.next_crossings(from, to, unix_s, class, 1).into_iter().next())
+}
+
+// --- WASM ---
+
+pub fn load_ferry_timetable_core(json: &str) -> Result<usize, String> {
+    let table: FerryTimetable = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    table.validate()?;
+    let n = table.lines.len();
+    *TABLE.write().map_err(|_| "Lock failed".to_string())? = Some(table);
+    Ok(n)
+}
+
+pub fn next_ferries_core(from: &str, to: &str, time: &str, class: FareClass, limit: usize) -> Result<Vec<Crossing>, String> {
+    let t = if time.trim().is_empty() { calendar::now_s() } else { calendar::moment(Some(time), None)? };
+    with_table(|table| {
+        let a = table.terminal_named(from).ok_or_else(|| format!("Unknown terminal: {}", from))?;
+        let b = table.terminal_named(to).ok_or_else(|| format!("Unknown terminal: {}", to))?;
+        Ok(table.next_crossings(&a.id, &b.id, t, class, limit))
+    })
+}
+
+/// Replaces the ferry timetable: `terminals` (`id`, `name`, `lat`, `lng`,
+/// `hub?`) and `lines` (`id`, `operator`, `from`, `to`, `crossing_min`,
+/// `departures` as `HH:MM`, `fares` `{ tourist, resident }`). Returns how
+/// many lines it holds.
+#[wasm_bindgen]
+pub fn load_ferry_timetable(json: &str) -> Result<usize, JsValue> {
+    load_ferry_timetable_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Next `limit` ferries between two terminals (id or name) from `time`
+/// (ISO 8601, Cancún time; empty for now) at `fare_class` (`tourist` or
+/// `resident`).
+#[wasm_bindgen]
+pub fn next_ferries(from: &str, to: &str, time: &str, fare_class: JsValue, limit: usize) -> Result<JsValue, JsValue> {
+    let class: Option<FareClass> = serde_wasm_bindgen::from_value(fare_class)
+        .map_err(|e| JsValue::from_str(&format!("fare_class: {}", e)))?;
+    let crossings = next_ferries_core(from, to, time, class.unwrap_or_default(), limit)
+        .map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&crossings).map_err(|e| JsValue::from_str(&e.to_string()))
+}
@@ -1,31 +1,78 @@
 //! ETA Engine — Real-time arrival estimation for ¿Qué Ruta Me Lleva?
 //! 
-//! Computes ETAs using haversine distances, transport speeds,
+//! Computes ETAs using along-shape (or haversine) distances, transport speeds,
 //! traffic congestion factors, and rain penalties.
 //! Runs 100% on-device via WASM — no network calls required for estimation.
 
 use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
-use shared_types::{haversine_distance, TransportType, TrafficConditions};
-
-#[derive(Serialize, Deserialize, Debug)]
+use shared_types::distribution::TravelTime;
+use shared_types::weather::WeatherObservation;
+use shared_types::{haversine_distance, polyline, ride, shape, TransportType, TrafficConditions};
+
+pub mod calendar;
+pub mod catalog;
+pub mod calibration;
+pub mod circuity;
+pub mod corridors;
+pub mod fares;
+pub mod ferry;
+pub mod multimodal;
+pub mod waiting;
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct EtaInput {
     /// Ordered list of stop coordinates for the journey segment
+    #[serde(default)]
     pub stops: Vec<[f64; 2]>,  // [lat, lng]
+    /// Route shape [lat, lng]; when given, distance is measured along it
+    #[serde(default)]
+    pub shape: Option<Vec<[f64; 2]>>,
+    /// Leg geometry as an encoded polyline (`RouteLeg.polyline`); replaces
+    /// `stops`/`shape` for distance when given
+    #[serde(default)]
+    pub polyline: Option<String>,
+    /// Polyline precision, defaults to 5
+    #[serde(default)]
+    pub polyline_precision: Option<u32>,
     /// Transport type identifier
     pub transport_type: String,
-    /// Current traffic conditions (optional — defaults to from_hour)
+    /// Current traffic conditions (optional — defaults to the traffic calendar)
     pub traffic: Option<TrafficConditions>,
-    /// Hour of day (0-23) for auto-traffic estimation
+    /// Departure, ISO 8601 (Cancún time without offset); picks weekday,
+    /// holiday and season traffic
+    #[serde(default)]
+    pub departure: Option<String>,
+    /// Hour of day (0-23) today, when no departure is given; now otherwise
     pub hour: Option<u8>,
+    /// Current local weather; sets the rain factor and slows walking
+    /// through flooded streets
+    #[serde(default)]
+    pub weather: Option<WeatherObservation>,
     /// How many stops remain in journey
     pub stops_remaining: Option<u32>,
+    /// Route id; selects a calibrated speed profile when one is loaded
+    #[serde(default)]
+    pub route_id: Option<String>,
+    /// Minutes between vehicles; adds the wait at the stop. Without it the
+    /// headway and service hours of `route_id` are used when loaded
+    #[serde(default)]
+    pub headway_min: Option<f64>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EtaResult {
-    /// Estimated travel time in minutes
+    /// Expected travel time in minutes, wait included
     pub minutes: f64,
+    /// Median and 90th percentile of the travel time
+    pub p50_minutes: f64,
+    pub p90_minutes: f64,
+    /// Expected minutes on board and at the stop
+    pub in_vehicle_minutes: f64,
+    pub wait_minutes: f64,
+    /// Wait breakdown, when a headway or known route was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<waiting::Wait>,
     /// Total distance in km
     pub distance_km: f64,
     /// Effective speed used (km/h, after traffic)
@@ -34,201 +81,174 @@ pub struct EtaResult {
     pub congestion_factor: f64,
     /// Rain penalty applied
     pub rain_factor: f64,
-    /// Human-readable label: "~12 min"
+    /// Human-readable p50–p90 range: "12–18 min"
     pub label: String,
-    /// Confidence level: "high" | "medium" | "low"
-    pub confidence: String,
+    /// Speed came from an observed route/hour profile
+    #[serde(default)]
+    pub calibrated: bool,
+    /// Time per corridor along the path, when corridors are loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub segments: Vec<corridors::SegmentEta>,
+    /// Day the traffic was taken from; absent when `traffic` was given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traffic_day: Option<calendar::TrafficDay>,
+    /// Walking or cycling through a reported flood
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flooded: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct MultiModalLeg {
-    pub mode: String,
-    pub minutes: f64,
-    pub distance_km: f64,
-    pub fare_mxn: f64,
-    pub co2_grams: f64,
-    pub label: String,
+/// Distance covered by `stops` in meters: along `shape` when every stop
+/// projects onto it, stop-to-stop otherwise. Two bare ends say nothing
+/// about the streets between them, so they get the circuity correction;
+/// longer stop lists already trace the street.
+fn path_distance_m(stops: &[[f64; 2]], route_shape: Option<&[[f64; 2]]>, transport: &TransportType) -> f64 {
+    route_shape
+        .and_then(|s| shape::along_shape_distance_m(s, stops))
+        .unwrap_or_else(|| match stops {
+            [from, to] => circuity::road_distance_m(transport, *from, *to),
+            _ => stops.windows(2)
+                .map(|w| haversine_distance(w[0][0], w[0][1], w[1][0], w[1][1]))
+                .sum(),
+        })
 }
 
-#[derive(Serialize, Deserialize, Debug)]
-pub struct MultiModalPlan {
-    pub legs: Vec<MultiModalLeg>,
-    pub total_minutes: f64,
-    pub total_fare_mxn: f64,
-    pub total_co2_grams: f64,
-    pub summary: String,
-    pub eco_score: u8,  // 0-100, higher = greener
-    pub budget_score: u8,  // 0-100, higher = cheaper
+/// Points the leg travels through: the decoded polyline, the shape between
+/// the first and last stop, or the stops themselves.
+fn leg_path(
+    stops: &[[f64; 2]], route_shape: Option<&[[f64; 2]]>, encoded: Option<&str>, precision: Option<u32>,
+) -> Result<Vec<[f64; 2]>, String> {
+    if let Some(encoded) = encoded {
+        return polyline::decode(encoded, precision.unwrap_or(polyline::DEFAULT_PRECISION));
+    }
+    if let (Some(s), [first, .., last]) = (route_shape, stops) {
+        let proj = shape::project_stops(s, &[*first, *last]);
+        if proj.len() == 2 && proj.iter().all(|p| p.off_shape_m <= shape::MAX_STOP_OFF_SHAPE_M) {
+            return Ok(shape::slice_shape(s, proj[0].offset_m, proj[1].offset_m));
+        }
+    }
+    Ok(stops.to_vec())
 }
 
-fn parse_transport(s: &str) -> TransportType {
-    match s {
-        "Bus" | "Bus_Urban" | "Bus_Urbano" | "BusUrban" => TransportType::Bus,
-        "Bus_HotelZone" | "BusHotelZone" => TransportType::BusHotelZone,
-        "Combi" | "Combi_Municipal" => TransportType::Combi,
-        "Van" | "Van_Foranea" => TransportType::Van,
-        "ADO" => TransportType::ADO,
-        "ADO_Airport" | "AdoAirport" => TransportType::AdoAirport,
-        "PlayaExpress" | "Playa_Express" => TransportType::PlayaExpress,
-        "MotorTaxi" => TransportType::MotorTaxi,
-        "Bicicleta" => TransportType::Bicicleta,
-        "Caminata" => TransportType::Caminata,
-        "Indriver" => TransportType::Indriver,
-        "Uber" => TransportType::Uber,
-        "Ferry" => TransportType::Ferry,
-        _ => TransportType::Bus,
+/// Walking and cycling slow down through flooded streets; vehicles only
+/// take the rain factor.
+fn walk_factor(transport: &TransportType, weather: Option<&WeatherObservation>, path: &[[f64; 2]]) -> f64 {
+    match (transport, weather) {
+        (TransportType::Caminata | TransportType::Bicicleta, Some(w)) => w.walk_factor(path),
+        _ => 1.0,
     }
 }
 
-/// Compute ETA for a transit leg
-#[wasm_bindgen]
-pub fn compute_eta(input_js: JsValue) -> JsValue {
-    let input: EtaInput = match serde_wasm_bindgen::from_value(input_js) {
-        Ok(v) => v,
-        Err(e) => {
-            let err = format!("{{\"error\": \"Invalid input: {}\"}}", e);
-            return JsValue::from_str(&err);
-        }
+/// Spread of riding time as a coefficient of variation: observed profiles
+/// are tighter, straight-line guesses and rush hour looser.
+fn ride_cv(traffic: &TrafficConditions, samples: Option<u32>, known_path: bool) -> f64 {
+    let base = match samples {
+        Some(n) if n >= 10 => 0.10,
+        Some(_) => 0.15,
+        None if !known_path => 0.30,
+        None if traffic.is_rush_hour => 0.25,
+        None => 0.15,
     };
+    // Rain makes arrivals less predictable, not only slower
+    base + (traffic.rain_factor - 1.0) * 0.2
+}
+
+/// Chance of missing a connection and waiting one more headway
+fn transfer_miss_p(traffic: &TrafficConditions) -> f64 {
+    if traffic.is_rush_hour { 0.2 } else { 0.1 }
+}
 
+/// Distance of an encoded leg geometry in meters.
+fn polyline_distance_m(encoded: &str, precision: Option<u32>) -> Result<f64, String> {
+    let path = polyline::decode(encoded, precision.unwrap_or(polyline::DEFAULT_PRECISION))?;
+    Ok(shape::polyline_length_m(&path))
+}
+
+/// `compute_eta` without the JS conversion.
+pub fn compute_eta_core(input: &EtaInput) -> Result<EtaResult, String> {
     // Calculate total distance along the route
-    let distance_m: f64 = input.stops.windows(2)
-        .map(|w| haversine_distance(w[0][0], w[0][1], w[1][0], w[1][1]))
-        .sum();
+    let distance_m = match &input.polyline {
+        Some(encoded) => polyline_distance_m(encoded, input.polyline_precision).map_err(|e| format!("Invalid polyline: {}", e))?,
+        None => path_distance_m(&input.stops, input.shape.as_deref(), &TransportType::from_label(&input.transport_type)),
+    };
     let distance_km = distance_m / 1000.0;
 
     // Get transport characteristics
-    let transport = parse_transport(&input.transport_type);
-    let base_speed = transport.avg_speed_kmh();
-
-    // Get traffic conditions
-    let traffic = input.traffic.unwrap_or_else(|| {
-        let hour = input.hour.unwrap_or(12);
-        TrafficConditions::from_hour(hour)
-    });
-
-    let delay = traffic.total_delay_factor();
-    let effective_speed = base_speed / delay;
-
-    // Core ETA calculation: time = distance / speed
-    let hours = distance_km / effective_speed;
-    let mut minutes = hours * 60.0;
-
-    // Add dwell time per stop (average 30 seconds per stop)
-    let stop_count = input.stops_remaining.unwrap_or(input.stops.len() as u32);
-    minutes += (stop_count as f64) * 0.5;
-
-    // Confidence based on data quality
-    let confidence = if distance_km > 0.1 && input.stops.len() > 2 {
-        if traffic.is_rush_hour { "medium" } else { "high" }
-    } else {
-        "low"
+    let transport = TransportType::from_label(&input.transport_type);
+
+    // Get traffic conditions for the day and hour
+    let at = calendar::moment(input.departure.as_deref(), input.hour).map_err(|e| format!("Invalid departure: {}", e))?;
+    let (traffic, traffic_day) = match input.traffic.clone() {
+        Some(t) => (t, None),
+        None => {
+            let (traffic, day) = calendar::traffic_at(at);
+            (traffic, Some(day))
+        }
     };
-
-    let label = if minutes < 1.0 {
-        "< 1 min".to_string()
-    } else if minutes < 60.0 {
-        format!("~{} min", minutes.round() as u32)
+    let traffic = match &input.weather {
+        Some(w) => traffic.with_weather(w),
+        None => traffic,
+    };
+    let path = leg_path(&input.stops, input.shape.as_deref(), input.polyline.as_deref(), input.polyline_precision)
+        .unwrap_or_default();
+
+    // Observed speeds already include traffic and dwell; only rain applies
+    let profile = input.route_id.as_deref()
+        .and_then(|id| calibration::calibrated(id, traffic.hour));
+    // Otherwise each corridor the path crosses brings its own congestion
+    let segments = match profile {
+        Some(_) => Vec::new(),
+        None => corridors::segment_etas(&path, transport.avg_speed_kmh(), &traffic),
+    };
+    let segment_km: f64 = segments.iter().map(|s| s.distance_km).sum();
+    let stop_count = input.stops_remaining.unwrap_or(input.stops.len() as u32);
+    let ride = ride::ride(&transport, distance_m, stop_count, &traffic, profile.as_ref().map(|p| p.speed_kmh));
+    let (effective_speed, congestion_factor, mut minutes) = if segment_km > 0.0 {
+        let segment_minutes: f64 = segments.iter().map(|s| s.minutes).sum();
+        let factor = segments.iter().map(|s| s.congestion_factor * s.distance_km).sum::<f64>() / segment_km;
+        let speed = segment_km / (segment_minutes / 60.0);
+        (speed, factor, ride::minutes(distance_km, speed, stop_count))
     } else {
-        let h = (minutes / 60.0).floor() as u32;
-        let m = (minutes % 60.0).round() as u32;
-        format!("~{}h {}min", h, m)
+        (ride.speed_kmh, ride.congestion_factor, ride.minutes)
     };
 
-    let result = EtaResult {
-        minutes,
+    let walk = walk_factor(&transport, input.weather.as_ref(), &path);
+    minutes *= walk;
+
+    // Spread from the data quality, plus the wait for the next vehicle
+    let known_path = path.len() > 2 && distance_km > 0.1;
+    let cv = ride_cv(&traffic, profile.as_ref().map(|p| p.samples), known_path);
+    let wait = waiting::wait_for(input.headway_min, input.route_id.as_deref(), at);
+    let time = TravelTime::with_cv(minutes, cv) + wait.as_ref().map(|w| w.time).unwrap_or_default();
+
+    Ok(EtaResult {
+        minutes: time.mean,
+        p50_minutes: time.p50(),
+        p90_minutes: time.p90(),
+        in_vehicle_minutes: minutes,
+        wait_minutes: wait.as_ref().map_or(0.0, |w| w.expected_min),
+        wait,
         distance_km,
         effective_speed_kmh: effective_speed,
-        congestion_factor: traffic.congestion_factor,
+        congestion_factor,
         rain_factor: traffic.rain_factor,
-        label,
-        confidence: confidence.to_string(),
-    };
-
-    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+        label: time.range_label(),
+        calibrated: profile.is_some(),
+        segments,
+        traffic_day,
+        flooded: walk > 1.0 && input.weather.as_ref().is_some_and(|w| w.floods_on(&path)),
+    })
 }
 
-/// Plan a multimodal journey with cost, time, and carbon breakdown
+/// Compute ETA for a transit leg
 #[wasm_bindgen]
-pub fn plan_multimodal(legs_js: JsValue, hour: u8) -> JsValue {
-    // legs_js: Array of {mode: string, stops: [[lat,lng],...]}
-    let legs_raw: Vec<serde_json::Value> = match serde_wasm_bindgen::from_value(legs_js) {
-        Ok(v) => v,
-        Err(_) => return JsValue::NULL,
-    };
-
-    let traffic = TrafficConditions::from_hour(hour);
-    let mut plan_legs: Vec<MultiModalLeg> = Vec::new();
-
-    for leg_val in &legs_raw {
-        let mode_str = leg_val["mode"].as_str().unwrap_or("Bus");
-        let transport = parse_transport(mode_str);
-
-        let stops: Vec<[f64; 2]> = leg_val["stops"]
-            .as_array()
-            .map(|arr| {
-                arr.iter().filter_map(|s| {
-                    let lat = s[0].as_f64()?;
-                    let lng = s[1].as_f64()?;
-                    Some([lat, lng])
-                }).collect()
-            })
-            .unwrap_or_default();
-
-        let distance_m: f64 = stops.windows(2)
-            .map(|w| haversine_distance(w[0][0], w[0][1], w[1][0], w[1][1]))
-            .sum();
-        let distance_km = distance_m / 1000.0;
-
-        let base_speed = transport.avg_speed_kmh();
-        let delay = traffic.total_delay_factor();
-        let effective_speed = base_speed / delay;
-        let minutes = (distance_km / effective_speed) * 60.0 + (stops.len() as f64 * 0.5);
-
-        let fare = transport.base_fare();
-        let co2 = transport.co2_per_km() * distance_km;
-
-        let label = format!("{} ~{} min {:.1} km ${:.0}",
-            mode_str, minutes.round() as u32, distance_km, fare);
-
-        plan_legs.push(MultiModalLeg {
-            mode: mode_str.to_string(),
-            minutes,
-            distance_km,
-            fare_mxn: fare,
-            co2_grams: co2,
-            label,
-        });
+pub fn compute_eta(input_js: JsValue) -> JsValue {
+    let result = serde_wasm_bindgen::from_value(input_js)
+        .map_err(|e| format!("Invalid input: {}", e))
+        .and_then(|input: EtaInput| compute_eta_core(&input));
+    match result {
+        Ok(result) => serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL),
+        Err(e) => JsValue::from_str(&format!("{{\"error\": \"{}\"}}", e)),
     }
-
-    let total_minutes: f64 = plan_legs.iter().map(|l| l.minutes).sum();
-    let total_fare: f64 = plan_legs.iter().map(|l| l.fare_mxn).sum();
-    let total_co2: f64 = plan_legs.iter().map(|l| l.co2_grams).sum();
-
-    // Eco score: 100 = zero emissions, 0 = very polluting
-    let eco_score = (100.0 - (total_co2 / 5.0).min(100.0)) as u8;
-
-    // Budget score: 100 = free, 0 = expensive
-    let budget_score = (100.0 - (total_fare / 2.0).min(100.0)) as u8;
-
-    let summary = format!(
-        "{} min · ${:.0} MXN · {}g CO₂",
-        total_minutes.round() as u32,
-        total_fare,
-        total_co2.round() as u32
-    );
-
-    let plan = MultiModalPlan {
-        legs: plan_legs,
-        total_minutes,
-        total_fare_mxn: total_fare,
-        total_co2_grams: total_co2,
-        summary,
-        eco_score,
-        budget_score,
-    };
-
-    serde_wasm_bindgen::to_value(&plan).unwrap_or(JsValue::NULL)
 }
 
 /// Quick ETA from two coordinates and transport type
@@ -239,28 +259,67 @@ pub fn quick_eta(
     transport_type: &str,
     hour: u8
 ) -> JsValue {
-    let distance_m = haversine_distance(from_lat, from_lng, to_lat, to_lng);
+    let transport = TransportType::from_label(transport_type);
+    let distance_m = circuity::road_distance_m(&transport, [from_lat, from_lng], [to_lat, to_lng]);
     let distance_km = distance_m / 1000.0;
-    let transport = parse_transport(transport_type);
-    let traffic = TrafficConditions::from_hour(hour);
-    let effective_speed = transport.avg_speed_kmh() / traffic.total_delay_factor();
-    let minutes = (distance_km / effective_speed) * 60.0;
-
-    let label = if minutes < 60.0 {
-        format!("~{} min", minutes.round() as u32)
-    } else {
-        format!("~{:.0}h", minutes / 60.0)
-    };
+    let Ok(t) = calendar::moment(None, Some(hour)) else { return JsValue::NULL };
+    let (traffic, traffic_day) = calendar::traffic_at(t);
+    let ride = ride::ride(&transport, distance_m, 0, &traffic, None);
+    let (effective_speed, minutes) = (ride.speed_kmh, ride.minutes);
+    // Straight-line distance: the widest spread
+    let time = TravelTime::with_cv(minutes, ride_cv(&traffic, None, false));
 
     let result = EtaResult {
         minutes,
+        p50_minutes: time.p50(),
+        p90_minutes: time.p90(),
+        in_vehicle_minutes: minutes,
+        wait_minutes: 0.0,
+        wait: None,
         distance_km,
         effective_speed_kmh: effective_speed,
         congestion_factor: traffic.congestion_factor,
         rain_factor: traffic.rain_factor,
-        label,
-        confidence: "medium".to_string(),
+        label: time.range_label(),
+        calibrated: false,
+        segments: Vec::new(),
+        traffic_day: Some(traffic_day),
+        flooded: false,
     };
 
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    static GLOBALS: Mutex<()> = Mutex::new(());
+
+    /// Held by tests that load tables (profiles, corridors, ...) into the
+    /// engine's globals, so they don't see each other's.
+    pub(crate) fn lock_globals() -> MutexGuard<'static, ()> {
+        GLOBALS.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// A bus leg along `stops` on a Tuesday at `hh:mm`.
+    pub(crate) fn bus_leg(stops: Vec<[f64; 2]>, hhmm: &str) -> EtaInput {
+        EtaInput {
+            stops,
+            transport_type: "Bus".to_string(),
+            departure: Some(format!("2026-05-12T{}", hhmm)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compute_eta_errors() {
+        let mut input = bus_leg(vec![[21.1714, -86.8219], [21.1460, -86.8230]], "08:15");
+        input.polyline = Some("not a polyline~".to_string());
+        assert!(compute_eta_core(&input).unwrap_err().starts_with("Invalid polyline"));
+        let mut input = bus_leg(vec![[21.1714, -86.8219], [21.1460, -86.8230]], "08:15");
+        input.departure = Some("tomorrow".to_string());
+        assert!(compute_eta_core(&input).unwrap_err().starts_with("Invalid departure"));
+    }
+}
@@ -0,0 +1,710 @@
+//! Multimodal journeys: time, fare and carbon for a chain of legs.
+//!
+//! The request is typed and checked before anything is computed. A leg is
+//! a mode plus exactly one geometry: `from`/`to`, a `stops` list (with an
+//! optional `shape`), an encoded `polyline`, or stop references on a loaded
+//! catalog route (`route_id`, `from_stop`, `to_stop`). Ride-hailing goes
+//! door to door, walking and cycling have no vehicle to wait for, and the
+//! ferry crosses between two terminals on its timetable. Every problem is
+//! reported with the leg it belongs to.
+
+use serde::{Deserialize, Serialize};
+use shared_types::distribution::TravelTime;
+use shared_types::ferry::FareClass;
+use shared_types::weather::WeatherObservation;
+use shared_types::{ride, TransportType};
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    calendar, catalog, fares, ferry, leg_path, path_distance_m, polyline_distance_m, ride_cv, transfer_miss_p,
+    waiting, walk_factor,
+};
+
+/// Spread of a wait given by the caller.
+const GIVEN_WAIT_CV: f64 = 0.2;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct LegRequest {
+    pub mode: String,
+    #[serde(default)]
+    pub from: Option<[f64; 2]>,
+    #[serde(default)]
+    pub to: Option<[f64; 2]>,
+    /// Stops `[lat, lng]` in riding order
+    #[serde(default)]
+    pub stops: Vec<[f64; 2]>,
+    /// Route shape for `stops`
+    #[serde(default)]
+    pub shape: Option<Vec<[f64; 2]>>,
+    #[serde(default)]
+    pub polyline: Option<String>,
+    #[serde(default)]
+    pub precision: Option<u32>,
+    /// Catalog route; with `from_stop`/`to_stop` (name or index) it is the
+    /// leg's geometry, and it also gives the headway and fare
+    #[serde(default)]
+    pub route_id: Option<String>,
+    #[serde(default)]
+    pub from_stop: Option<String>,
+    #[serde(default)]
+    pub to_stop: Option<String>,
+    #[serde(default)]
+    pub headway_min: Option<f64>,
+    /// Known wait (a ride-hailing pickup, a posted departure); replaces the
+    /// headway model
+    #[serde(default)]
+    pub wait_minutes: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MultiModalRequest {
+    pub legs: Vec<LegRequest>,
+    /// Hour today (0-23) when no `departure` is given; now otherwise
+    #[serde(default)]
+    pub hour: Option<u8>,
+    /// ISO 8601, Cancún time without offset
+    #[serde(default)]
+    pub departure: Option<String>,
+    #[serde(default)]
+    pub weather: Option<WeatherObservation>,
+    /// Ferry fares; `tourist` unless given
+    #[serde(default)]
+    pub fare_class: FareClass,
+}
+
+/// A problem with the request; `leg` is `None` for request-wide fields.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LegError {
+    pub leg: Option<usize>,
+    pub field: String,
+    pub message: String,
+}
+
+impl fmt::Display for LegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.leg {
+            Some(i) if self.field.is_empty() => write!(f, "legs[{}]: {}", i, self.message),
+            Some(i) => write!(f, "legs[{}].{}: {}", i, self.field, self.message),
+            None => write!(f, "{}: {}", self.field, self.message),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MultiModalLeg {
+    pub mode: String,
+    /// Expected, wait included
+    pub minutes: f64,
+    pub p50_minutes: f64,
+    pub p90_minutes: f64,
+    pub in_vehicle_minutes: f64,
+    pub wait_minutes: f64,
+    pub distance_km: f64,
+    pub fare_mxn: f64,
+    pub co2_grams: f64,
+    pub label: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub flooded: bool,
+    /// Ferry departure taken, `HH:MM`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub departure: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct MultiModalPlan {
+    pub legs: Vec<MultiModalLeg>,
+    /// Expected, transfer risk included
+    pub total_minutes: f64,
+    /// Percentiles of the whole trip, not sums of the legs' percentiles
+    pub p50_minutes: f64,
+    pub p90_minutes: f64,
+    pub total_fare_mxn: f64,
+    pub total_co2_grams: f64,
+    pub summary: String,
+    pub eco_score: u8,  // 0-100, higher = greener
+    pub budget_score: u8,  // 0-100, higher = cheaper
+    pub traffic_day: calendar::TrafficDay,
+}
+
+/// Where a leg's geometry comes from.
+enum Geometry {
+    Ends([f64; 2], [f64; 2]),
+    Stops,
+    Polyline,
+    StopRefs,
+}
+
+fn is_ride_hail(mode: &TransportType) -> bool {
+    matches!(mode, TransportType::Indriver | TransportType::Uber | TransportType::MotorTaxi | TransportType::Taxi)
+}
+
+fn is_active(mode: &TransportType) -> bool {
+    matches!(mode, TransportType::Caminata | TransportType::Bicicleta)
+}
+
+fn valid_point(p: &[f64; 2]) -> bool {
+    p[0].is_finite() && p[1].is_finite() && (-90.0..=90.0).contains(&p[0]) && (-180.0..=180.0).contains(&p[1])
+}
+
+impl LegRequest {
+    /// Problems with this leg, tagged with index `i`.
+    fn validate(&self, i: usize) -> (Option<TransportType>, Vec<LegError>) {
+        let mut errors = Vec::new();
+        let mut err = |field: &str, message: String| {
+            errors.push(LegError { leg: Some(i), field: field.to_string(), message });
+        };
+
+        let mode = TransportType::parse(&self.mode);
+        if mode.is_none() {
+            err("mode", format!("unknown mode {:?}", self.mode));
+        }
+        for (field, p) in [("from", &self.from), ("to", &self.to)] {
+            if p.as_ref().is_some_and(|p| !valid_point(p)) {
+                err(field, "not a valid [lat, lng]".to_string());
+            }
+        }
+        if let Some(j) = self.stops.iter().position(|p| !valid_point(p)) {
+            err("stops", format!("stops[{}] is not a valid [lat, lng]", j));
+        }
+        if self.shape.as_ref().is_some_and(|s| s.iter().any(|p| !valid_point(p))) {
+            err("shape", "contains an invalid [lat, lng]".to_string());
+        }
+        if self.precision.is_some_and(|p| p != 5 && p != 6) {
+            err("precision", "must be 5 or 6".to_string());
+        }
+        if self.headway_min.is_some_and(|h| !(h.is_finite() && h > 0.0)) {
+            err("headway_min", "must be a positive number of minutes".to_string());
+        }
+        if self.wait_minutes.is_some_and(|w| !(w.is_finite() && w >= 0.0)) {
+            err("wait_minutes", "must be zero or more minutes".to_string());
+        }
+
+        // Exactly one geometry
+        let mut sources = Vec::new();
+        match (self.from, self.to) {
+            (Some(_), Some(_)) => sources.push("from/to"),
+            (Some(_), None) => err("to", "required with from".to_string()),
+            (None, Some(_)) => err("from", "required with to".to_string()),
+            (None, None) => {}
+        }
+        if !self.stops.is_empty() {
+            sources.push("stops");
+            if self.stops.len() < 2 {
+                err("stops", "needs at least 2 points".to_string());
+            }
+        }
+        if self.polyline.is_some() { sources.push("polyline"); }
+        if self.from_stop.is_some() || self.to_stop.is_some() {
+            sources.push("from_stop/to_stop");
+            self.check_stop_refs(&mut err);
+        }
+        match sources.len() {
+            0 => err("", "needs from/to, stops, polyline or route_id with from_stop/to_stop".to_string()),
+            1 => {}
+            _ => err("", format!("give only one of {}", sources.join(", "))),
+        }
+        if self.shape.is_some() && self.stops.is_empty() {
+            err("shape", "only applies to stops".to_string());
+        }
+
+        // Per-mode rules
+        if let Some(mode) = &mode {
+            let label = &self.mode;
+            if is_ride_hail(mode) {
+                if self.from.is_none() || self.to.is_none() {
+                    err("from", format!("{} legs go door to door: give from and to", label));
+                }
+                if self.route_id.is_some() { err("route_id", format!("not allowed for {} legs", label)); }
+                if self.headway_min.is_some() {
+                    err("headway_min", format!("{} is on demand; use wait_minutes for the pickup", label));
+                }
+            } else if is_active(mode) {
+                for (field, set) in [
+                    ("route_id", self.route_id.is_some()),
+                    ("headway_min", self.headway_min.is_some()),
+                    ("wait_minutes", self.wait_minutes.is_some()),
+                ] {
+                    if set { err(field, format!("not allowed for {} legs", label)); }
+                }
+            } else if *mode == TransportType::Ferry {
+                match self.ferry_ends() {
+                    Some((from, to)) => {
+                        if let Err((field, message)) = ferry::terminals(from, to) { err(field, message); }
+                    }
+                    None => err("from", "ferry legs go between two terminals: give from and to".to_string()),
+                }
+                if self.shape.is_some() || self.polyline.is_some() {
+                    err("shape", "ferry legs cross in a straight line; shape and polyline are not allowed".to_string());
+                }
+                for (field, set) in [
+                    ("route_id", self.route_id.is_some()),
+                    ("headway_min", self.headway_min.is_some()),
+                    ("wait_minutes", self.wait_minutes.is_some()),
+                ] {
+                    if set { err(field, "ferries run on the loaded timetable".to_string()); }
+                }
+            }
+        }
+        (mode, errors)
+    }
+
+    fn check_stop_refs(&self, err: &mut impl FnMut(&str, String)) {
+        let Some(id) = &self.route_id else {
+            err("route_id", "required with from_stop/to_stop".to_string());
+            return;
+        };
+        let Some(route) = catalog::route(id) else {
+            err("route_id", format!("unknown route {:?} (load the catalog with load_route_service)", id));
+            return;
+        };
+        let mut indices = Vec::new();
+        for (field, stop) in [("from_stop", &self.from_stop), ("to_stop", &self.to_stop)] {
+            match stop {
+                None => err(field, "required with route_id".to_string()),
+                Some(s) => match route.stop_index(s) {
+                    Some(i) => indices.push(i),
+                    None => err(field, format!("no stop {:?} on route {}", s, id)),
+                },
+            }
+        }
+        if let [a, b] = indices[..] {
+            if a == b { err("to_stop", "same stop as from_stop".to_string()); }
+        }
+    }
+
+    /// Ends of a ferry leg: `from`/`to` or two stops.
+    fn ferry_ends(&self) -> Option<([f64; 2], [f64; 2])> {
+        match (self.from, self.to, &self.stops[..]) {
+            (Some(a), Some(b), _) => Some((a, b)),
+            (None, None, [a, b]) => Some((*a, *b)),
+            _ => None,
+        }
+    }
+
+    fn geometry(&self) -> Geometry {
+        match (self.from, self.to) {
+            (Some(a), Some(b)) => Geometry::Ends(a, b),
+            _ if self.polyline.is_some() => Geometry::Polyline,
+            _ if self.from_stop.is_some() => Geometry::StopRefs,
+            _ => Geometry::Stops,
+        }
+    }
+
+    /// Stops travelled, resolved from references when needed.
+    fn resolved_stops(&self) -> Vec<[f64; 2]> {
+        match self.geometry() {
+            Geometry::Ends(a, b) => vec![a, b],
+            Geometry::Stops | Geometry::Polyline => self.stops.clone(),
+            Geometry::StopRefs => {
+                let route = self.route_id.as_deref().and_then(catalog::route);
+                let idx = |s: &Option<String>| route.as_ref()?.stop_index(s.as_deref()?);
+                match (route.as_ref(), idx(&self.from_stop), idx(&self.to_stop)) {
+                    (Some(r), Some(a), Some(b)) => {
+                        let (lo, hi) = (a.min(b), a.max(b));
+                        let mut pts: Vec<[f64; 2]> = r.paradas[lo..=hi].iter().map(|s| [s.lat, s.lng]).collect();
+                        if a > b { pts.reverse(); }
+                        pts
+                    }
+                    _ => Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+impl MultiModalRequest {
+    /// Reads a request leg by leg, so a malformed leg is reported by index.
+    pub fn from_json(value: serde_json::Value) -> Result<Self, Vec<LegError>> {
+        #[derive(Deserialize)]
+        struct Raw {
+            legs: Vec<serde_json::Value>,
+            #[serde(default)]
+            hour: Option<u8>,
+            #[serde(default)]
+            departure: Option<String>,
+            #[serde(default)]
+            weather: Option<WeatherObservation>,
+            #[serde(default)]
+            fare_class: FareClass,
+        }
+        let top = |field: &str, message: String| vec![LegError { leg: None, field: field.to_string(), message }];
+        let raw: Raw = serde_json::from_value(value).map_err(|e| top("request", e.to_string()))?;
+        let mut legs = Vec::new();
+        let mut errors = Vec::new();
+        for (i, v) in raw.legs.into_iter().enumerate() {
+            match serde_json::from_value::<LegRequest>(v) {
+                Ok(leg) => legs.push(leg),
+                Err(e) => errors.push(LegError { leg: Some(i), field: String::new(), message: e.to_string() }),
+            }
+        }
+        if !errors.is_empty() { return Err(errors); }
+        Ok(MultiModalRequest { legs, hour: raw.hour, departure: raw.departure, weather: raw.weather, fare_class: raw.fare_class })
+    }
+
+    /// Every problem in the request; empty when it can be planned.
+    pub fn validate(&self) -> Vec<LegError> {
+        let mut errors = Vec::new();
+        if self.legs.is_empty() {
+            errors.push(LegError { leg: None, field: "legs".into(), message: "needs at least one leg".into() });
+        }
+        if self.hour.is_some_and(|h| h > 23) {
+            errors.push(LegError { leg: None, field: "hour".into(), message: "must be 0-23".into() });
+        }
+        if let Err(e) = calendar::moment(self.departure.as_deref(), self.hour) {
+            errors.push(LegError { leg: None, field: "departure".into(), message: e });
+        }
+        for (i, leg) in self.legs.iter().enumerate() {
+            errors.extend(leg.validate(i).1);
+        }
+        errors
+    }
+}
+
+/// Plans a validated request.
+pub fn plan_multimodal_core(req: &MultiModalRequest) -> Result<MultiModalPlan, Vec<LegError>> {
+    let errors = req.validate();
+    if !errors.is_empty() { return Err(errors); }
+    let leg_err = |i: usize, field: &str, message: String| vec![LegError { leg: Some(i), field: field.into(), message }];
+
+    let t = calendar::moment(req.departure.as_deref(), req.hour)
+        .map_err(|e| vec![LegError { leg: None, field: "departure".into(), message: e }])?;
+    let (traffic, traffic_day) = calendar::traffic_at(t);
+    let traffic = match &req.weather {
+        Some(w) => traffic.with_weather(w),
+        None => traffic,
+    };
+    let weather = req.weather.as_ref();
+    let mut plan_legs: Vec<MultiModalLeg> = Vec::new();
+    let mut total_time = TravelTime::default();
+
+    for (i, leg) in req.legs.iter().enumerate() {
+        let transport = TransportType::parse(&leg.mode).unwrap_or_default();
+        let stops = leg.resolved_stops();
+        let encoded = leg.polyline.as_deref();
+        let distance_m = match encoded {
+            Some(e) => polyline_distance_m(e, leg.precision).map_err(|e| leg_err(i, "polyline", e))?,
+            None => path_distance_m(&stops, leg.shape.as_deref(), &transport),
+        };
+        let distance_km = distance_m / 1000.0;
+
+        let path = match weather {
+            Some(_) => leg_path(&stops, leg.shape.as_deref(), encoded, leg.precision).unwrap_or_default(),
+            None => Vec::new(),
+        };
+        let walk = walk_factor(&transport, weather, &path);
+        let known_path = encoded.is_some() || leg.shape.is_some() || stops.len() > 2;
+
+        // Waits start when the earlier legs get the rider to the stop
+        let at_stop = t + (total_time.mean * 60.0).round() as i64;
+        // The ferry leaves on its timetable after the rider reaches the pier
+        let crossing = match (transport == TransportType::Ferry, leg.ferry_ends()) {
+            (true, Some((from, to))) => {
+                let (a, b) = ferry::terminals(from, to).map_err(|(field, message)| leg_err(i, field, message))?;
+                let c = ferry::next_crossing(&a.id, &b.id, at_stop, req.fare_class)
+                    .ok_or_else(|| leg_err(i, "", format!("no ferry from {} to {}", a.name, b.name)))?;
+                Some(c)
+            }
+            _ => None,
+        };
+        let ride_minutes = match &crossing {
+            Some(c) => c.crossing_min as f64,
+            None => ride::ride(&transport, distance_m, stops.len() as u32, &traffic, None).minutes * walk,
+        };
+
+        let modelled = match (leg.wait_minutes, &crossing) {
+            (None, None) => waiting::wait_for(leg.headway_min, leg.route_id.as_deref(), at_stop),
+            _ => None,
+        };
+        let wait_time = match (leg.wait_minutes, &crossing, &modelled) {
+            (Some(w), _, _) => TravelTime::with_cv(w, GIVEN_WAIT_CV),
+            (None, Some(c), _) => TravelTime { mean: c.wait_min as f64, variance: 0.0 },
+            (None, None, Some(w)) => w.time,
+            (None, None, None) => TravelTime::default(),
+        };
+        // A crossing is on the clock; only getting to the pier is uncertain
+        let ride_spread = if crossing.is_some() { 0.0 } else { ride_cv(&traffic, None, known_path) };
+        let time = TravelTime::with_cv(ride_minutes, ride_spread) + wait_time;
+        // Arriving late from the previous leg can cost a whole headway, or
+        // the wait for the next boat
+        let missed_cost = crossing.as_ref().map(|c| c.next_gap_min as f64).or(modelled.as_ref().map(|w| w.headway_min));
+        let transfer = match missed_cost {
+            Some(cost) if i > 0 => TravelTime::risk(transfer_miss_p(&traffic), cost),
+            _ => TravelTime::default(),
+        };
+        total_time = total_time + time + transfer;
+
+        let metered = match (leg.from, leg.to) {
+            (Some(from), Some(to)) if is_ride_hail(&transport) => fares::fare(&transport, from, to, distance_m, ride_minutes),
+            _ => None,
+        };
+        let fare = leg.route_id.as_deref()
+            .and_then(catalog::route)
+            .and_then(|r| r.tarifa)
+            .or(metered)
+            .or(crossing.as_ref().map(|c| c.fare_mxn))
+            .unwrap_or_else(|| transport.base_fare());
+        let co2 = transport.co2_per_km() * distance_km;
+
+        let label = format!("{} {} {:.1} km ${:.0}",
+            leg.mode, time.range_label(), distance_km, fare);
+
+        plan_legs.push(MultiModalLeg {
+            mode: leg.mode.clone(),
+            minutes: time.mean,
+            p50_minutes: time.p50(),
+            p90_minutes: time.p90(),
+            in_vehicle_minutes: ride_minutes,
+            wait_minutes: wait_time.mean,
+            distance_km,
+            fare_mxn: fare,
+            co2_grams: co2,
+            label,
+            flooded: walk > 1.0 && weather.is_some_and(|w| w.floods_on(&path)),
+            departure: crossing.map(|c| c.depart),
+        });
+    }
+
+    let total_minutes = total_time.mean;
+    let total_fare: f64 = plan_legs.iter().map(|l| l.fare_mxn).sum();
+    let total_co2: f64 = plan_legs.iter().map(|l| l.co2_grams).sum();
+
+    // Eco score: 100 = zero emissions, 0 = very polluting
+    let eco_score = (100.0 - (total_co2 / 5.0).min(100.0)) as u8;
+
+    // Budget score: 100 = free, 0 = expensive
+    let budget_score = (100.0 - (total_fare / 2.0).min(100.0)) as u8;
+
+    let summary = format!(
+        "{} · ${:.0} MXN · {}g CO₂",
+        total_time.range_label(),
+        total_fare,
+        total_co2.round() as u32
+    );
+
+    Ok(MultiModalPlan {
+        legs: plan_legs,
+        total_minutes,
+        p50_minutes: total_time.p50(),
+        p90_minutes: total_time.p90(),
+        total_fare_mxn: total_fare,
+        total_co2_grams: total_co2,
+        summary,
+        eco_score,
+        budget_score,
+        traffic_day,
+    })
+}
+
+fn errors_js(errors: &[LegError]) -> JsValue {
+    JsValue::from_str(&errors.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))
+}
+
+/// Plan a multimodal journey with cost, time, and carbon breakdown.
+/// Throws every validation problem, each prefixed with its leg
+/// (`legs[1].from: ...`).
+#[wasm_bindgen]
+pub fn plan_multimodal(request_js: JsValue) -> Result<JsValue, JsValue> {
+    let value: serde_json::Value = serde_wasm_bindgen::from_value(request_js)
+        .map_err(|e| JsValue::from_str(&format!("request: {}", e)))?;
+    let req = MultiModalRequest::from_json(value).map_err(|e| errors_js(&e))?;
+    let plan = plan_multimodal_core(&req).map_err(|e| errors_js(&e))?;
+    serde_wasm_bindgen::to_value(&plan).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::lock_globals;
+
+    /// R_WAIT runs 05:00-23:00 every 10 minutes.
+    const CATALOG_JSON: &str = r#"[
+        {"id": "R_WAIT", "frecuencia_minutos": 10, "horario": {"inicio": "05:00", "fin": "23:00"},
+         "paradas": [{"nombre": "Crucero", "lat": 21.1714, "lng": -86.8219},
+                     {"nombre": "Plaza Las Américas", "lat": 21.1460, "lng": -86.8230}]}
+    ]"#;
+
+    fn request(json: serde_json::Value) -> MultiModalRequest {
+        MultiModalRequest::from_json(json).unwrap()
+    }
+
+    /// Validation errors of a single leg, as `legs[0]...` strings.
+    fn leg_errors(leg: serde_json::Value) -> Vec<String> {
+        request(serde_json::json!({"legs": [leg]})).validate().iter().map(|e| e.to_string()).collect()
+    }
+
+    fn has(errors: &[String], prefix: &str) -> bool {
+        errors.iter().any(|e| e.starts_with(prefix))
+    }
+
+    #[test]
+    fn test_from_json_reports_malformed_legs() {
+        let err = MultiModalRequest::from_json(serde_json::json!({"legs": [
+            {"mode": "Bus", "stops": [[21.17, -86.82], [21.15, -86.82]]},
+            {"mode": "Bus", "stops": "Crucero"},
+            {"mode": "Bus", "color": "red"},
+        ]})).unwrap_err();
+        assert_eq!(err.iter().map(|e| e.leg).collect::<Vec<_>>(), vec![Some(1), Some(2)]);
+        assert!(err[1].message.contains("color"), "{}", err[1].message);
+        // A missing or non-object request is a request-wide error, not a null plan
+        let err = MultiModalRequest::from_json(serde_json::Value::Null).unwrap_err();
+        assert_eq!((err[0].leg, err[0].field.as_str()), (None, "request"));
+        let err = MultiModalRequest::from_json(serde_json::json!({"hour": 8})).unwrap_err();
+        assert!(err[0].to_string().starts_with("request: "));
+    }
+
+    #[test]
+    fn test_error_display() {
+        let e = |leg, field: &str| LegError { leg, field: field.into(), message: "bad".into() }.to_string();
+        assert_eq!(e(Some(1), "from"), "legs[1].from: bad");
+        assert_eq!(e(Some(0), ""), "legs[0]: bad");
+        assert_eq!(e(None, "hour"), "hour: bad");
+    }
+
+    #[test]
+    fn test_validate_request_fields() {
+        let errors: Vec<String> = request(serde_json::json!({"legs": [], "hour": 24}))
+            .validate().iter().map(|e| e.to_string()).collect();
+        assert!(has(&errors, "legs: "));
+        assert!(has(&errors, "hour: "));
+        let bad = request(serde_json::json!({"legs": [], "departure": "mañana"})).validate();
+        assert!(bad.iter().any(|e| e.field == "departure" && e.leg.is_none()));
+    }
+
+    #[test]
+    fn test_validate_exactly_one_geometry() {
+        let e = leg_errors(serde_json::json!({"mode": "Bus"}));
+        assert!(has(&e, "legs[0]: needs from/to"), "{:?}", e);
+        let e = leg_errors(serde_json::json!({"mode": "Bus", "from": [21.17, -86.82], "to": [21.15, -86.82],
+            "stops": [[21.17, -86.82], [21.15, -86.82]]}));
+        assert!(has(&e, "legs[0]: give only one of from/to, stops"), "{:?}", e);
+        assert!(has(&leg_errors(serde_json::json!({"mode": "Bus", "from": [21.17, -86.82]})), "legs[0].to: required with from"));
+        assert!(has(&leg_errors(serde_json::json!({"mode": "Bus", "stops": [[21.17, -86.82]]})), "legs[0].stops: needs at least 2"));
+        let e = leg_errors(serde_json::json!({"mode": "Bus", "polyline": "_p~iF~ps|U", "shape": [[21.17, -86.82], [21.15, -86.82]]}));
+        assert!(has(&e, "legs[0].shape: only applies to stops"), "{:?}", e);
+        let e = leg_errors(serde_json::json!({"mode": "Bus", "stops": [[91.0, -86.82], [21.15, -86.82]], "precision": 7}));
+        assert!(has(&e, "legs[0].stops: stops[0]"), "{:?}", e);
+        assert!(has(&e, "legs[0].precision: "), "{:?}", e);
+        assert!(has(&leg_errors(serde_json::json!({"mode": "Tren", "from": [21.17, -86.82], "to": [21.15, -86.82]})), "legs[0].mode: "));
+    }
+
+    #[test]
+    fn test_validate_mode_rules() {
+        let (a, b) = ([21.1714, -86.8219], [21.1460, -86.8230]);
+        // Ride-hailing is door to door and on demand
+        let e = leg_errors(serde_json::json!({"mode": "Uber", "stops": [a, b], "headway_min": 5}));
+        assert!(has(&e, "legs[0].from: Uber legs go door to door"), "{:?}", e);
+        assert!(has(&e, "legs[0].headway_min: "), "{:?}", e);
+        assert!(leg_errors(serde_json::json!({"mode": "Uber", "from": a, "to": b, "wait_minutes": 4})).is_empty());
+        // Nothing to wait for on foot or by bike
+        let e = leg_errors(serde_json::json!({"mode": "Caminata", "from": a, "to": b, "wait_minutes": 2, "route_id": "R1"}));
+        assert!(has(&e, "legs[0].wait_minutes: not allowed for Caminata legs"), "{:?}", e);
+        assert!(has(&e, "legs[0].route_id: "), "{:?}", e);
+        // Ferries cross between terminals on the timetable
+        let e = leg_errors(serde_json::json!({"mode": "Ferry", "from": a, "to": b, "headway_min": 30}));
+        assert!(has(&e, "legs[0].from: no ferry terminal"), "{:?}", e);
+        assert!(has(&e, "legs[0].headway_min: ferries run on the loaded timetable"), "{:?}", e);
+    }
+
+    #[test]
+    fn test_validate_stop_refs_against_catalog() {
+        let _g = lock_globals();
+        catalog::load_route_service_core(CATALOG_JSON).unwrap();
+        let refs = |route: &str, from: &str, to: &str| leg_errors(serde_json::json!({
+            "mode": "Bus", "route_id": route, "from_stop": from, "to_stop": to,
+        }));
+        assert!(refs("R_WAIT", "Crucero", "plaza las americas").is_empty());
+        assert!(has(&refs("R_NOPE", "Crucero", "1"), "legs[0].route_id: unknown route"));
+        assert!(has(&refs("R_WAIT", "Crucero", "Mercado 28"), "legs[0].to_stop: no stop"));
+        assert!(has(&refs("R_WAIT", "0", "Crucero"), "legs[0].to_stop: same stop"));
+        let e = leg_errors(serde_json::json!({"mode": "Bus", "from_stop": "Crucero", "to_stop": "1"}));
+        assert!(has(&e, "legs[0].route_id: required with from_stop/to_stop"), "{:?}", e);
+        catalog::load_route_service_core("[]").unwrap();
+    }
+
+    #[test]
+    fn test_plans_every_geometry() {
+        let _g = lock_globals();
+        catalog::load_route_service_core(CATALOG_JSON).unwrap();
+        let (a, b) = ([21.1714, -86.8219], [21.1460, -86.8230]);
+        let encoded = shared_types::polyline::encode(&[a, b], 6).unwrap();
+        let plan = plan_multimodal_core(&request(serde_json::json!({
+            "departure": "2026-05-12T10:00",
+            "legs": [
+                {"mode": "Caminata", "from": [21.1750, -86.8219], "to": a},
+                {"mode": "Bus", "stops": [a, b], "headway_min": 10},
+                {"mode": "Bus", "polyline": encoded, "precision": 6, "headway_min": 10},
+                {"mode": "Bus", "route_id": "R_WAIT", "from_stop": "Crucero", "to_stop": "Plaza Las Américas"},
+            ],
+        }))).unwrap();
+        assert_eq!(plan.legs.len(), 4);
+        assert!(plan.legs.iter().all(|l| l.distance_km > 0.0 && l.minutes > 0.0));
+        // The same two stops, by reference or by coordinates
+        assert!((plan.legs[3].distance_km - plan.legs[1].distance_km).abs() < 1e-9);
+        // A polyline is the path itself; two bare stops get the circuity correction
+        assert!(plan.legs[2].distance_km < plan.legs[1].distance_km);
+        assert_eq!(plan.legs[0].wait_minutes, 0.0);
+        catalog::load_route_service_core("[]").unwrap();
+    }
+
+    #[test]
+    fn test_ferry_boards_when_the_pier_is_reached() {
+        let _g = lock_globals();
+        // Ultramar to Isla Mujeres leaves every 30 minutes from 05:00
+        let (pier, isla) = ([21.1819, -86.8028], [21.2580, -86.7490]);
+        let ferry = serde_json::json!({"mode": "Ferry", "from": pier, "to": isla});
+        let alone = plan_multimodal_core(&request(serde_json::json!({
+            "departure": "2026-05-12T10:00", "legs": [ferry.clone()],
+        }))).unwrap();
+        assert_eq!(alone.legs[0].departure.as_deref(), Some("10:00"));
+        assert_eq!(alone.legs[0].wait_minutes, 0.0);
+
+        // Riding from El Crucero first misses the 10:00 boat
+        let bused = plan_multimodal_core(&request(serde_json::json!({
+            "departure": "2026-05-12T10:00",
+            "legs": [{"mode": "Combi", "stops": [[21.1714, -86.8219], pier], "headway_min": 10}, ferry],
+        }))).unwrap();
+        let (combi, boat) = (&bused.legs[0], &bused.legs[1]);
+        assert_eq!(boat.departure.as_deref(), Some("10:30"));
+        // The wait runs from reaching the pier to the departure
+        assert!((combi.minutes + boat.wait_minutes - 30.0).abs() <= 1.0, "{} + {}", combi.minutes, boat.wait_minutes);
+        assert_eq!(boat.in_vehicle_minutes, 20.0);
+    }
+
+    #[test]
+    fn test_ride_hail_fare_is_metered() {
+        let _g = lock_globals();
+        let (a, b) = ([21.1714, -86.8219], [21.1460, -86.8230]);
+        let uber = serde_json::json!({"departure": "2026-05-12T10:00", "legs": [{"mode": "Uber", "from": a, "to": b}]});
+        let plan = plan_multimodal_core(&request(uber.clone())).unwrap();
+        let leg = &plan.legs[0];
+        let metered = fares::fare(&TransportType::Uber, a, b, leg.distance_km * 1000.0, leg.in_vehicle_minutes).unwrap();
+        assert_eq!(leg.fare_mxn, metered);
+        assert_ne!(leg.fare_mxn, TransportType::Uber.base_fare());
+
+        // A loaded rate card replaces the default
+        fares::load_fares_core(r#"{"modes": {"Uber": {"base": 0, "per_km": 0, "minimum": 400}}}"#).unwrap();
+        assert_eq!(plan_multimodal_core(&request(uber)).unwrap().legs[0].fare_mxn, 400.0);
+        fares::load_fares_core("{}").unwrap();
+    }
+
+    #[test]
+    fn test_wait_starts_when_the_stop_is_reached() {
+        let _g = lock_globals();
+        catalog::load_route_service_core(CATALOG_JSON).unwrap();
+        let bus = serde_json::json!({"mode": "Bus", "route_id": "R_WAIT", "from_stop": "Crucero", "to_stop": "Plaza Las Américas"});
+        // Leaving at 04:20, the first bus is 40 minutes away
+        let alone = plan_multimodal_core(&request(serde_json::json!({
+            "departure": "2026-05-12T04:20", "legs": [bus.clone()],
+        }))).unwrap();
+        assert!(alone.legs[0].wait_minutes > 40.0);
+        // A long walk first reaches the stop after 05:00: a regular wait
+        let walked = plan_multimodal_core(&request(serde_json::json!({
+            "departure": "2026-05-12T04:20",
+            "legs": [{"mode": "Caminata", "from": [21.2000, -86.8219], "to": [21.1714, -86.8219]}, bus],
+        }))).unwrap();
+        assert!(walked.legs[0].minutes > 40.0, "{}", walked.legs[0].minutes);
+        assert_eq!(walked.legs[1].wait_minutes, 5.0);
+    }
+}
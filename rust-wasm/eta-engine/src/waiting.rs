@@ -0,0 +1,119 @@
+//! Time spent waiting at the stop, usually the biggest part of a Cancún
+//! trip.
+//!
+//! The wait comes from the route headway (`frecuencia_minutos`), either
+//! given with the request or looked up by route id in the loaded catalog.
+//! Riders arrive at random, so the regular wait is uniform over one
+//! headway. Night guard units run half as often and leave when full, so
+//! their wait is longer and much less predictable. Outside service the wait
+//! runs until the first departure.
+
+use serde::{Deserialize, Serialize};
+use shared_types::clock;
+use shared_types::distribution::TravelTime;
+use shared_types::schedule::{Service, ServiceHours, DEFAULT_HEADWAY_MIN, DEFAULT_SERVICE_START_MIN};
+
+use crate::catalog;
+
+/// Spread of a night guard wait relative to a uniform one.
+const NIGHT_GUARD_VARIANCE: f64 = 3.0;
+
+/// Expected and worst-case wait, minutes.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Wait {
+    pub headway_min: f64,
+    pub service: Service,
+    pub expected_min: f64,
+    /// One headway, or the 90th percentile for night guard.
+    pub worst_min: f64,
+    #[serde(skip)]
+    pub time: TravelTime,
+}
+
+/// Wait at `unix_s` for a headway given directly or a loaded route.
+pub fn wait_for(headway_min: Option<f64>, route_id: Option<&str>, unix_s: i64) -> Option<Wait> {
+    let route = route_id.and_then(catalog::route);
+    if headway_min.is_none() && route.is_none() { return None; }
+    let route = route.unwrap_or_default();
+    let headway = headway_min
+        .or(route.frecuencia_minutos.filter(|&f| f > 0).map(f64::from))
+        .unwrap_or(DEFAULT_HEADWAY_MIN as f64);
+    // A bare headway means the route runs; hours only come with a route
+    let hours = route.horario.as_ref().map(|h| h.parse().0);
+    let minute = clock::cancun_minute_of_day(unix_s);
+    let service = hours.as_ref().map_or(Service::Regular, |h| h.service_at(minute));
+    Some(wait_in(headway, service, hours.as_ref(), minute))
+}
+
+fn wait_in(headway: f64, service: Service, hours: Option<&ServiceHours>, minute: u32) -> Wait {
+    let (time, worst) = match service {
+        Service::Regular => (TravelTime::wait(headway), headway),
+        Service::NightGuard => {
+            let h = headway * 2.0;
+            let uniform = TravelTime::wait(h);
+            let time = TravelTime { variance: uniform.variance * NIGHT_GUARD_VARIANCE, ..uniform };
+            (time, time.p90())
+        }
+        Service::NotRunning => {
+            // Until the next window opens, then the wait of that service
+            let until = |start: u32| ((start + 1440 - minute) % 1440) as f64;
+            let regular = until(hours.and_then(|h| h.regular.as_ref()).map_or(DEFAULT_SERVICE_START_MIN, |w| w.start_min));
+            let (gap, next) = match hours.and_then(|h| h.night_guard.as_ref()).map(|w| until(w.start_min)) {
+                Some(guard) if guard < regular => (guard, wait_in(headway, Service::NightGuard, hours, minute)),
+                _ => (regular, wait_in(headway, Service::Regular, hours, minute)),
+            };
+            (TravelTime { mean: gap, variance: 0.0 } + next.time, gap + next.worst_min)
+        }
+    };
+    Wait { headway_min: headway, service, expected_min: time.mean, worst_min: worst, time }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared_types::schedule::{Schedule, TimeWindow};
+
+    fn hours() -> ServiceHours {
+        Schedule {
+            inicio_oficial: Some("05:00".into()),
+            fin_oficial: Some("23:00".into()),
+            guardia_nocturna: Some("03:00 - 05:00 (Sistema de llenado en OXXO)".into()),
+            ..Default::default()
+        }.parse().0
+    }
+
+    #[test]
+    fn test_regular_wait_is_half_a_headway() {
+        let w = wait_in(12.0, Service::Regular, Some(&hours()), 10 * 60);
+        assert_eq!(w.expected_min, 6.0);
+        assert_eq!(w.worst_min, 12.0);
+        assert_eq!(w.time, TravelTime::wait(12.0));
+    }
+
+    #[test]
+    fn test_night_guard_waits_longer_and_looser() {
+        let w = wait_in(12.0, Service::NightGuard, Some(&hours()), 4 * 60);
+        let uniform = TravelTime::wait(24.0);
+        assert_eq!(w.expected_min, 12.0);
+        assert_eq!(w.time.variance, uniform.variance * NIGHT_GUARD_VARIANCE);
+        assert!(w.worst_min > uniform.p90());
+    }
+
+    #[test]
+    fn test_not_running_rolls_over_to_next_window() {
+        // 23:30: the night guard opens first, at 03:00
+        let w = wait_in(12.0, Service::NotRunning, Some(&hours()), 23 * 60 + 30);
+        assert_eq!(w.service, Service::NotRunning);
+        assert_eq!(w.expected_min, 210.0 + 12.0);
+        // Without a guard, until regular service at 05:00
+        let day_only = ServiceHours { night_guard: None, ..hours() };
+        let w = wait_in(12.0, Service::NotRunning, Some(&day_only), 23 * 60 + 30);
+        assert_eq!(w.expected_min, 330.0 + 6.0);
+        assert_eq!(w.worst_min, 330.0 + 12.0);
+        // Unknown hours default to 05:00
+        let w = wait_in(12.0, Service::NotRunning, None, 4 * 60 + 50);
+        assert_eq!(w.expected_min, 10.0 + 6.0);
+        let late = ServiceHours { regular: Some(TimeWindow { start_min: 6 * 60, end_min: 22 * 60, note: None }), night_guard: None };
+        assert_eq!(wait_in(12.0, Service::NotRunning, Some(&late), 5 * 60).expected_min, 60.0 + 6.0);
+    }
+}
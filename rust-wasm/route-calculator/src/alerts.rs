@@ -0,0 +1,255 @@
+//! Structured service alerts.
+//!
+//! Free-text `social_alerts` stay as they are; these alerts carry a scope
+//! (routes, stops, an area), a validity window and an effect the router
+//! acts on. Closed stops are not used to board, alight or transfer; delays
+//! and fare changes are added to the journeys that touch them, and every
+//! journey lists the alerts that concern it.
+
+use serde::{Deserialize, Serialize};
+use shared_types::geo::{Contains, Coord, LineString, Point, Polygon};
+use wasm_bindgen::prelude::*;
+
+use crate::clock::parse_datetime;
+use crate::{normalize_str, Closures, Journey, Route, DB};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertEffect {
+    /// Matching stops cannot be used. Without stops or area, the whole route.
+    ClosedStop,
+    /// Service runs but off its usual path; informational.
+    Detour {
+        #[serde(default)]
+        description: String,
+    },
+    Delay { minutes: u32 },
+    /// New fare (MXN) for legs on the affected routes.
+    FareChange { fare: f64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ServiceAlert {
+    pub id: String,
+    #[serde(alias = "mensaje")]
+    pub message: String,
+    /// Empty means every route.
+    #[serde(default)]
+    pub route_ids: Vec<String>,
+    /// Stop names, stop ids or station ids.
+    #[serde(default)]
+    pub stops: Vec<String>,
+    /// Polygon `[lat, lng]`; stops inside it are affected.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub area: Option<Vec<[f64; 2]>>,
+    /// ISO 8601, Cancún local time when no offset is given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_until: Option<String>,
+    pub effect: AlertEffect,
+}
+
+impl ServiceAlert {
+    pub fn validate(&self) -> Result<(), String> {
+        for t in self.valid_from.iter().chain(&self.valid_until) {
+            parse_datetime(t).map_err(|e| format!("Alert {}: {}", self.id, e))?;
+        }
+        if self.area.as_ref().is_some_and(|a| a.len() < 3) {
+            return Err(format!("Alert {}: area needs at least 3 points", self.id));
+        }
+        Ok(())
+    }
+
+    pub fn is_active(&self, now_s: i64) -> bool {
+        let bound = |t: &Option<String>| t.as_deref().and_then(|t| parse_datetime(t).ok());
+        bound(&self.valid_from).is_none_or(|from| now_s >= from)
+            && bound(&self.valid_until).is_none_or(|until| now_s < until)
+    }
+
+    fn applies_to_route(&self, route_id: &str) -> bool {
+        self.route_ids.is_empty() || self.route_ids.iter().any(|r| r == route_id)
+    }
+
+    fn has_stop_scope(&self) -> bool {
+        !self.stops.is_empty() || self.area.is_some()
+    }
+}
+
+/// An alert with its scope prepared for repeated stop tests.
+struct Scope<'a> {
+    alert: &'a ServiceAlert,
+    stops_norm: Vec<String>,
+    area: Option<Polygon<f64>>,
+}
+
+impl<'a> Scope<'a> {
+    fn new(alert: &'a ServiceAlert) -> Self {
+        let area = alert.area.as_ref().map(|pts| {
+            Polygon::new(pts.iter().map(|p| Coord { x: p[1], y: p[0] }).collect::<LineString<f64>>(), vec![])
+        });
+        Scope { alert, stops_norm: alert.stops.iter().map(|s| normalize_str(s)).collect(), area }
+    }
+
+    fn matches_stop(&self, route: &Route, idx: usize) -> bool {
+        let stop = &route.stops[idx];
+        let listed = |v: &str| self.alert.stops.iter().any(|s| s == v);
+        stop.id.as_deref().is_some_and(listed)
+            || route.station_ids.get(idx).is_some_and(|s| listed(s))
+            || route.stops_normalized.get(idx).is_some_and(|n| self.stops_norm.contains(n))
+            || self.area.as_ref().is_some_and(|a| a.contains(&Point::new(stop.lng, stop.lat)))
+    }
+}
+
+pub fn active_alerts(alerts: &[ServiceAlert], now_s: i64) -> Vec<&ServiceAlert> {
+    alerts.iter().filter(|a| a.is_active(now_s)).collect()
+}
+
+/// Stops and routes closed by `ClosedStop` alerts.
+pub fn closures(active: &[&ServiceAlert], routes: &[Route]) -> Closures {
+    let mut out = Closures::default();
+    for alert in active.iter().filter(|a| a.effect == AlertEffect::ClosedStop) {
+        let scope = Scope::new(alert);
+        for route in routes.iter().filter(|r| alert.applies_to_route(&r.id)) {
+            if !alert.has_stop_scope() {
+                out.close_route(&route.id);
+                continue;
+            }
+            for idx in (0..route.stops.len()).filter(|&i| scope.matches_stop(route, i)) {
+                out.close_stop(&route.id, idx);
+            }
+        }
+    }
+    out
+}
+
+/// Alerts that concern boarding `route` at stop `idx`.
+pub fn alerts_at_stop(active: &[&ServiceAlert], route: &Route, idx: usize) -> Vec<ServiceAlert> {
+    active.iter()
+        .filter(|a| a.applies_to_route(&route.id) && (!a.has_stop_scope() || Scope::new(a).matches_stop(route, idx)))
+        .map(|a| (*a).clone())
+        .collect()
+}
+
+/// Attaches the alerts that touch each journey and applies delays and fare
+/// changes. A leg is touched when its route is in scope and, for alerts
+/// scoped to stops or an area, when it rides through one of them.
+pub fn apply_alerts(journeys: &mut [Journey], active: &[&ServiceAlert], routes: &[Route]) {
+    let scopes: Vec<Scope> = active.iter().map(|a| Scope::new(a)).collect();
+    for journey in journeys.iter_mut() {
+        let mut fare_changed = false;
+        for leg in journey.legs.iter_mut() {
+            let Some(route) = routes.iter().find(|r| r.id == leg.route_id) else { continue };
+            let from = route.stop_name_to_index.get(&normalize_str(&leg.origin_stop)).copied();
+            let to = route.stop_name_to_index.get(&normalize_str(&leg.dest_stop)).copied();
+            let span = match (from, to) {
+                (Some(a), Some(b)) => a.min(b)..=a.max(b),
+                _ => 0..=route.stops.len().saturating_sub(1),
+            };
+            for scope in &scopes {
+                let alert = scope.alert;
+                let touched = alert.applies_to_route(&route.id)
+                    && (!alert.has_stop_scope() || span.clone().any(|i| scope.matches_stop(route, i)));
+                if !touched { continue; }
+                if let AlertEffect::FareChange { fare } = alert.effect {
+                    leg.price = fare;
+                    fare_changed = true;
+                }
+                if !journey.alerts.iter().any(|a| a.id == alert.id) {
+                    if let AlertEffect::Delay { minutes } = alert.effect {
+                        journey.delay_minutes += minutes;
+                    }
+                    journey.alerts.push(alert.clone());
+                }
+            }
+        }
+        if fare_changed {
+            journey.total_price = journey.legs.iter().map(|l| l.price).sum();
+        }
+    }
+}
+
+// --- WASM ---
+
+pub fn load_alerts_core(json: &str) -> Result<usize, String> {
+    if json.len() > 1024 * 1024 { return Err("Payload too large".to_string()); }
+    let alerts: Vec<ServiceAlert> = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    for a in &alerts {
+        a.validate()?;
+    }
+    let mut db = DB.write().map_err(|_| "Lock failed".to_string())?;
+    db.alerts = alerts;
+    Ok(db.alerts.len())
+}
+
+/// Replaces the loaded alerts (catalog `service_alerts` included).
+#[wasm_bindgen]
+pub fn load_alerts(json_payload: &str) -> Result<usize, JsValue> {
+    load_alerts_core(json_payload).map_err(|e| JsValue::from_str(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_route;
+    use crate::{clock, find_route_closed_rs};
+
+    fn network() -> Vec<Route> {
+        vec![
+            mock_route("R1", vec![("A", 21.10, -86.80), ("Hub", 21.12, -86.82), ("B", 21.14, -86.84)]),
+            mock_route("R2", vec![("A", 21.10, -86.80), ("Mercado 28", 21.16, -86.83)]),
+            mock_route("R3", vec![("Mercado 28", 21.16, -86.83), ("B", 21.14, -86.84)]),
+        ]
+    }
+
+    fn alert(json: &str) -> ServiceAlert {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn plan(routes: &[Route], alerts: &[ServiceAlert], now: i64) -> Vec<Journey> {
+        let active = active_alerts(alerts, now);
+        let mut journeys = find_route_closed_rs("A", "B", routes, &closures(&active, routes));
+        apply_alerts(&mut journeys, &active, routes);
+        journeys
+    }
+
+    #[test]
+    fn test_closed_stop_is_not_used() {
+        let routes = network();
+        let closed = alert(r#"{"id": "obra", "message": "Parada B cerrada en R1", "route_ids": ["R1"],
+            "stops": ["B"], "effect": {"type": "closed_stop"}}"#);
+        let journeys = plan(&routes, &[closed], 0);
+        assert!(journeys.iter().all(|j| j.legs.iter().all(|l| l.route_id != "R1")));
+        assert_eq!(journeys[0].transfer_point.as_deref(), Some("Mercado 28"));
+
+        // Area closure around Mercado 28 blocks the R2/R3 transfer.
+        let area = alert(r#"{"id": "evento", "message": "Cierre", "area": [[21.15, -86.84], [21.17, -86.84], [21.17, -86.82], [21.15, -86.82]],
+            "effect": {"type": "closed_stop"}}"#);
+        let journeys = plan(&routes, &[area], 0);
+        assert_eq!(journeys.len(), 1);
+        assert_eq!(journeys[0].legs[0].route_id, "R1");
+    }
+
+    #[test]
+    fn test_delay_and_fare_respect_validity_window() {
+        let routes = network();
+        let alerts = [
+            alert(r#"{"id": "zh", "message": "Retraso en Zona Hotelera por obra pública", "route_ids": ["R1"],
+                "valid_from": "2026-05-01T06:00", "valid_until": "2026-05-01T20:00", "effect": {"type": "delay", "minutes": 15}}"#),
+            alert(r#"{"id": "madrugada", "message": "Tarifa nocturna", "route_ids": ["R3"], "effect": {"type": "fare_change", "fare": 20.0}}"#),
+        ];
+        let morning = clock::parse_datetime("2026-05-01T08:00").unwrap();
+        let journeys = plan(&routes, &alerts, morning);
+        let direct = journeys.iter().find(|j| j.type_ == "Direct").unwrap();
+        assert_eq!(direct.delay_minutes, 15);
+        assert_eq!(direct.alerts[0].id, "zh");
+        let transfer = journeys.iter().find(|j| j.type_ == "Transfer").unwrap();
+        assert_eq!(transfer.total_price, 30.0);
+        assert_eq!(transfer.alerts.len(), 1);
+
+        let night = clock::parse_datetime("2026-05-01T22:00").unwrap();
+        let journeys = plan(&routes, &alerts, night);
+        assert_eq!(journeys.iter().find(|j| j.type_ == "Direct").unwrap().delay_minutes, 0);
+        assert!(alerts[0].validate().is_ok());
+    }
+}
@@ -0,0 +1,336 @@
+//! Stop aliases: what riders type vs. what the catalog calls a stop.
+//!
+//! Two layers. Phrase aliases map a whole query ("ZH", "Hotel Zone",
+//! "la 23") to a canonical stop name or station id. Token rewrites expand
+//! abbreviations ("Av." → avenida, "SM" → supermanzana), translate common
+//! English words and turn Spanish number words into digits, and are applied
+//! to both the query and the stop names before comparing them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use shared_types::normalize::{fold, is_stop_word};
+use wasm_bindgen::prelude::*;
+
+use crate::stations::Station;
+use crate::{find_route_closed_rs, normalize_str, Closures, Journey, Route, DB};
+
+/// Nicknames and bilingual names for Cancún stops. Entries whose canonical
+/// stop is not in the loaded catalog are simply never used.
+const DEFAULT_PHRASES: &[(&str, &str)] = &[
+    ("zh", "Zona Hotelera"),
+    ("hotel zone", "Zona Hotelera"),
+    ("hotel zone entrance", "Entrada Zona Hotelera"),
+    ("downtown", "Terminal ADO Centro"),
+    ("centro", "Terminal ADO Centro"),
+    ("ado", "Terminal ADO Centro"),
+    ("ado centro", "Terminal ADO Centro"),
+    ("bus station", "Terminal ADO Centro"),
+    ("central de autobuses", "Terminal ADO Centro"),
+    ("la 23", "Mercado 23"),
+    ("la 28", "Mercado 28"),
+    ("el 28", "Mercado 28"),
+    ("airport", "Aeropuerto T2"),
+    ("aeropuerto", "Aeropuerto T2"),
+    ("cun", "Aeropuerto T2"),
+    ("las americas", "Plaza Las Américas"),
+    ("americas", "Plaza Las Américas"),
+    ("crucero", "El Crucero"),
+    ("ultramar", "Muelle Ultramar (Puerto Juárez)"),
+    ("isla mujeres ferry", "Muelle Ultramar (Puerto Juárez)"),
+    ("puerto juarez", "Muelle Ultramar (Puerto Juárez)"),
+    ("palapas", "Parque Las Palapas"),
+    ("kukulcan", "Boulevard Kukulcán"),
+    ("unicaribe", "Universidad del Caribe"),
+    ("hospital general", "Hospital General (Av. Kabah)"),
+    ("outlet", "Plaza Outlet"),
+];
+
+/// Abbreviations and English words, after `normalize::fold`.
+const DEFAULT_TOKENS: &[(&str, &str)] = &[
+    ("av", "avenida"),
+    ("ave", "avenida"),
+    ("avenue", "avenida"),
+    ("blvd", "boulevard"),
+    ("blvr", "boulevard"),
+    ("bulevar", "boulevard"),
+    ("sm", "supermanzana"),
+    ("smz", "supermanzana"),
+    ("mz", "manzana"),
+    ("mza", "manzana"),
+    ("carr", "carretera"),
+    ("fed", "federal"),
+    ("col", "colonia"),
+    ("reg", "region"),
+    ("hosp", "hospital"),
+    ("pto", "puerto"),
+    ("airport", "aeropuerto"),
+    ("market", "mercado"),
+    ("beach", "playa"),
+    ("park", "parque"),
+    ("downtown", "centro"),
+];
+
+const UNITS: &[&str] = &[
+    "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+    "diez", "once", "doce", "trece", "catorce", "quince",
+    "dieciseis", "diecisiete", "dieciocho", "diecinueve", "veinte",
+    "veintiuno", "veintidos", "veintitres", "veinticuatro", "veinticinco",
+    "veintiseis", "veintisiete", "veintiocho", "veintinueve",
+];
+const TENS: &[&str] = &["treinta", "cuarenta", "cincuenta", "sesenta", "setenta", "ochenta", "noventa"];
+
+fn number_word(w: &str) -> Option<u32> {
+    match w {
+        "un" | "una" => return Some(1),
+        "cien" => return Some(100),
+        _ => {}
+    }
+    if let Some(i) = UNITS.iter().position(|u| *u == w) {
+        return Some(i as u32);
+    }
+    TENS.iter().position(|t| *t == w).map(|i| 30 + 10 * i as u32)
+}
+
+/// One way a query was rewritten before it matched a stop.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AliasMatch {
+    /// What the rider typed.
+    pub input: String,
+    /// The alias entry (or rewritten key) that matched.
+    pub alias: String,
+    /// Catalog stop name used for routing.
+    pub stop: String,
+}
+
+/// Loadable format: `{ "aliases": { "zh": "Zona Hotelera" }, "tokens": { "sm": "supermanzana" } }`.
+/// Canonical values may be a stop name or a station id (`ST…`).
+#[derive(Deserialize, Default)]
+struct AliasFile {
+    #[serde(default)]
+    aliases: HashMap<String, String>,
+    #[serde(default)]
+    tokens: HashMap<String, String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AliasTable {
+    /// `phrase_key(alias)` → canonical stop name or station id.
+    phrases: HashMap<String, String>,
+    tokens: HashMap<String, String>,
+}
+
+impl Default for AliasTable {
+    fn default() -> Self {
+        let mut table = AliasTable { phrases: HashMap::new(), tokens: HashMap::new() };
+        for (short, long) in DEFAULT_TOKENS {
+            table.tokens.insert(short.to_string(), long.to_string());
+        }
+        for (alias, canonical) in DEFAULT_PHRASES {
+            table.add_phrase(alias, canonical);
+        }
+        table
+    }
+}
+
+impl AliasTable {
+    pub fn add_phrase(&mut self, alias: &str, canonical: &str) {
+        let key = self.phrase_key(alias);
+        if !key.is_empty() {
+            self.phrases.insert(key, canonical.to_string());
+        }
+    }
+
+    /// Adds (or overrides) entries from a JSON alias file.
+    pub fn extend_from_json(&mut self, json: &str) -> Result<usize, String> {
+        let file: AliasFile = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        // Tokens first so phrase keys are built with them.
+        for (short, long) in &file.tokens {
+            self.tokens.insert(fold(short), fold(long));
+        }
+        for (alias, canonical) in &file.aliases {
+            self.add_phrase(alias, canonical);
+        }
+        Ok(file.aliases.len() + file.tokens.len())
+    }
+
+    /// Comparison key: punctuation dropped, tokens rewritten, number words
+    /// as digits ("treinta y dos" → "32"), then stop words dropped.
+    pub fn match_key(&self, text: &str) -> String {
+        self.key(text, true)
+    }
+
+    /// Like `match_key` but keeping stop words, so phrase aliases match the
+    /// whole phrase: "la 23" is an alias, a bare "23" is not.
+    pub fn phrase_key(&self, text: &str) -> String {
+        self.key(text, false)
+    }
+
+    fn key(&self, text: &str, drop_stop_words: bool) -> String {
+        let folded = fold(text);
+        let words: Vec<&str> = folded.split(' ').filter(|w| !w.is_empty()).collect();
+        let mut out: Vec<String> = Vec::with_capacity(words.len());
+        let mut i = 0;
+        while i < words.len() {
+            let w = words[i];
+            if let Some(n) = number_word(w) {
+                let unit = words.get(i + 2).and_then(|u| number_word(u)).filter(|u| (1..10).contains(u));
+                if let Some(unit) = unit.filter(|_| (30..100).contains(&n) && words.get(i + 1) == Some(&"y")) {
+                    out.push((n + unit).to_string());
+                    i += 3;
+                    continue;
+                }
+                out.push(n.to_string());
+            } else if !(drop_stop_words && is_stop_word(w)) {
+                out.push(self.tokens.get(w).cloned().unwrap_or_else(|| w.to_string()));
+            }
+            i += 1;
+        }
+        out.join(" ")
+    }
+
+    /// Catalog stop name for a query, and the alias used to get there.
+    /// Queries that already name a stop are returned untouched.
+    pub fn resolve(&self, query: &str, routes: &[Route], stations: &[Station]) -> (String, Option<AliasMatch>) {
+        let norm = normalize_str(query);
+        if routes.iter().any(|r| r.stop_name_to_index.contains_key(&norm)) {
+            return (query.to_string(), None);
+        }
+        let found = |alias: String, stop: String| {
+            let m = AliasMatch { input: query.to_string(), alias, stop: stop.clone() };
+            (stop, Some(m))
+        };
+
+        let phrase = self.phrase_key(query);
+        if let Some(canonical) = self.phrases.get(&phrase) {
+            let target = stations.iter().find(|s| &s.id == canonical).map(|s| s.name.as_str()).unwrap_or(canonical);
+            if let Some(stop) = self.find_stop(target, routes) {
+                return found(phrase, stop);
+            }
+        }
+        let key = self.match_key(query);
+        if let Some(stop) = self.find_stop_by_key(&key, routes) {
+            return found(key, stop);
+        }
+        (query.to_string(), None)
+    }
+
+    fn find_stop(&self, name: &str, routes: &[Route]) -> Option<String> {
+        let norm = normalize_str(name);
+        routes.iter()
+            .find_map(|r| r.stop_name_to_index.get(&norm).map(|&i| r.stops[i].name.clone()))
+            .or_else(|| self.find_stop_by_key(&self.match_key(name), routes))
+    }
+
+    fn find_stop_by_key(&self, key: &str, routes: &[Route]) -> Option<String> {
+        if key.is_empty() { return None; }
+        routes.iter()
+            .flat_map(|r| r.stops.iter())
+            .find(|s| self.match_key(&s.name) == key)
+            .map(|s| s.name.clone())
+    }
+}
+
+/// `find_route_rs` with both ends resolved through the alias table. Each
+/// journey records which alias, if any, was applied to each end.
+pub fn find_route_aliased(
+    origin: &str, dest: &str, routes: &[Route], stations: &[Station], table: &AliasTable, closures: &Closures,
+) -> Vec<Journey> {
+    let (origin_stop, origin_alias) = table.resolve(origin, routes, stations);
+    let (dest_stop, dest_alias) = table.resolve(dest, routes, stations);
+    let mut journeys = find_route_closed_rs(&origin_stop, &dest_stop, routes, closures);
+    for j in &mut journeys {
+        j.origin_alias = origin_alias.clone();
+        j.dest_alias = dest_alias.clone();
+    }
+    journeys
+}
+
+// --- WASM ---
+
+pub fn load_aliases_core(json: &str) -> Result<usize, String> {
+    if json.len() > 1024 * 1024 { return Err("Payload too large".to_string()); }
+    let mut db = DB.write().map_err(|_| "Lock failed".to_string())?;
+    db.aliases.extend_from_json(json)
+}
+
+/// Adds entries to the alias table (see `AliasFile`). Returns how many were read.
+#[wasm_bindgen]
+pub fn load_aliases(json_payload: &str) -> Result<usize, JsValue> {
+    load_aliases_core(json_payload).map_err(|e| JsValue::from_str(&e))
+}
+
+#[wasm_bindgen]
+pub fn resolve_stop(query: &str) -> Result<JsValue, JsValue> {
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    let (stop, alias) = db.aliases.resolve(query, &db.routes_list, &db.stations);
+    let m = alias.unwrap_or(AliasMatch { input: query.to_string(), alias: String::new(), stop });
+    serde_wasm_bindgen::to_value(&m).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_route;
+
+    fn network() -> Vec<Route> {
+        vec![
+            mock_route("R1", vec![("Zona Hotelera", 21.13, -86.75), ("Mercado 23", 21.16, -86.83)]),
+            mock_route("R2", vec![("Mercado 23", 21.16, -86.83), ("Carr. Federal 307", 21.04, -86.87)]),
+        ]
+    }
+
+    #[test]
+    fn test_match_key_rewrites_tokens_and_numbers() {
+        let t = AliasTable::default();
+        assert_eq!(t.match_key("Av. Tulum"), "avenida tulum");
+        assert_eq!(t.match_key("Mercado Veintitrés"), "mercado 23");
+        assert_eq!(t.match_key("SM treinta y dos"), "supermanzana 32");
+        assert_eq!(t.match_key("Carr. Fed. 307"), t.match_key("Carretera Federal 307"));
+        assert_eq!(t.phrase_key("La Veintitrés"), "la 23");
+    }
+
+    #[test]
+    fn test_bare_number_does_not_resolve() {
+        let mut routes = network();
+        routes.push(mock_route("R3", vec![("Mercado 28", 21.16, -86.84), ("Mercado 23", 21.16, -86.83)]));
+        let t = AliasTable::default();
+        assert_eq!(t.resolve("la 23", &routes, &[]).0, "Mercado 23");
+        assert_eq!(t.resolve("El 28", &routes, &[]).0, "Mercado 28");
+        for q in ["23", "28", "veintiocho"] {
+            let (stop, alias) = t.resolve(q, &routes, &[]);
+            assert_eq!(stop, q);
+            assert!(alias.is_none(), "{} resolved to {:?}", q, alias);
+        }
+    }
+
+    #[test]
+    fn test_find_route_reports_alias_used() {
+        let routes = network();
+        let t = AliasTable::default();
+        let journeys = find_route_aliased("ZH", "la 23", &routes, &[], &t, &Closures::default());
+        assert_eq!(journeys.len(), 1);
+        let j = &journeys[0];
+        assert_eq!(j.origin_alias.as_ref().unwrap().alias, "zh");
+        assert_eq!(j.dest_alias.as_ref().unwrap().stop, "Mercado 23");
+
+        let journeys = find_route_aliased("Mercado Veintitres", "carretera federal 307", &routes, &[], &t, &Closures::default());
+        assert_eq!(journeys[0].origin_alias.as_ref().unwrap().alias, "mercado 23");
+        assert_eq!(journeys[0].legs[0].dest_stop, "Carr. Federal 307");
+
+        // Exact stop names go through untouched.
+        let journeys = find_route_aliased("Zona Hotelera", "Mercado 23", &routes, &[], &t, &Closures::default());
+        assert!(journeys[0].origin_alias.is_none());
+    }
+
+    #[test]
+    fn test_loaded_alias_to_station_id() {
+        let routes = network();
+        let stations = crate::stations::cluster_stations(&routes);
+        let market = stations.iter().find(|s| s.name == "Mercado 23").unwrap();
+        let mut t = AliasTable::default();
+        let json = format!(r#"{{"aliases": {{"El Mercadito": "{}"}}, "tokens": {{"mcdo": "mercado"}}}}"#, market.id);
+        assert_eq!(t.extend_from_json(&json).unwrap(), 2);
+        assert_eq!(t.resolve("el mercadito", &routes, &stations).0, "Mercado 23");
+        assert_eq!(t.resolve("Mcdo 23", &routes, &stations).0, "Mercado 23");
+    }
+}
@@ -0,0 +1,180 @@
+//! Arrive-by planning: "be at the hotel by 06:45".
+//!
+//! Each journey is walked backward from the deadline: alight, ride (at the
+//! traffic of the boarding hour, as in forward itineraries), board, and
+//! before boarding a full headway so any vehicle will do. Transfers add the
+//! walk between stops. The result is the latest time to be at the first
+//! stop.
+
+use serde::Serialize;
+use shared_types::circuity::CircuityModel;
+use shared_types::exclusions::Exclusions;
+use wasm_bindgen::prelude::*;
+
+use crate::clock::{self, format_hhmm};
+use crate::schedule::{service_at, Service};
+use crate::timing::{self, headway_min, TimedLeg};
+use crate::{plan_journeys, AppState, Journey, Route, DB};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ArriveByPlan {
+    pub journey: Journey,
+    /// Latest time to be at the first stop, `HH:MM` Cancún.
+    pub depart_at: String,
+    pub depart_at_s: i64,
+    pub arrive_by: String,
+    pub legs: Vec<TimedLeg>,
+    /// Some boarding falls in the `guardia_nocturna` window.
+    pub night_guard: bool,
+    pub warnings: Vec<String>,
+}
+
+/// Places `journey` on the clock backward from `arrive_by_s`. `None` when a
+/// leg would have to be boarded while its route is not running.
+pub fn schedule_backward(journey: &Journey, routes: &[Route], circuity: &CircuityModel, arrive_by_s: i64) -> Option<ArriveByPlan> {
+    let mut t = arrive_by_s - journey.delay_minutes as i64 * 60;
+    let mut legs = Vec::with_capacity(journey.legs.len());
+    let mut warnings = Vec::new();
+
+    for (i, leg) in journey.legs.iter().enumerate().rev() {
+        let route = routes.iter().find(|r| r.id == leg.route_id)?;
+        let alight_s = t;
+        // Traffic at boarding, like `schedule_forward`: estimate the boarding
+        // time from the ride at the alighting hour, then ride from there
+        let guess_s = alight_s - (timing::ride_min(route, leg, alight_s) * 60.0).round() as i64;
+        let ride_s = (timing::ride_min(route, leg, guess_s) * 60.0).round() as i64;
+        let board_s = alight_s - ride_s;
+        let service = service_at(route, clock::cancun_minute_of_day(board_s));
+        match service {
+            Service::NotRunning => return None,
+            Service::NightGuard => {
+                let guard = route.horario.as_ref().and_then(|h| h.guardia_nocturna.as_deref()).unwrap_or_default();
+                warnings.push(format!(
+                    "{} at {} depends on the night guard service ({}); it leaves when full",
+                    route.name, format_hhmm(clock::cancun_minute_of_day(board_s)), guard,
+                ));
+            }
+            Service::Regular => {}
+        }
+        let at_stop_s = board_s - headway_min(route, service) as i64 * 60;
+        let walk_s = if i > 0 { (timing::walk_min(journey, i - 1, routes, circuity) * 60.0).round() as i64 } else { 0 };
+        legs.push(TimedLeg::new(leg, walk_s, at_stop_s, board_s, alight_s, service));
+        t = at_stop_s - walk_s;
+    }
+    legs.reverse();
+
+    let night_guard = legs.iter().any(|l| l.service == Service::NightGuard);
+    Some(ArriveByPlan {
+        // The forward itinerary assumes leaving at plan time; `legs` replaces it.
+        journey: Journey { itinerary: None, last_connection: None, ..journey.clone() },
+        depart_at: legs[0].at_stop.clone(),
+        depart_at_s: legs[0].at_stop_s,
+        arrive_by: format_hhmm(clock::cancun_minute_of_day(arrive_by_s)),
+        legs,
+        night_guard,
+        warnings,
+    })
+}
+
+/// Journeys that reach `dest` by `arrive_by_s`, latest departure first.
+fn plan_arrive_by(db: &AppState, origin: &str, dest: &str, arrive_by_s: i64) -> Vec<ArriveByPlan> {
+    let journeys = plan_journeys(db, origin, dest, arrive_by_s, &Exclusions::default());
+    let mut plans: Vec<ArriveByPlan> = journeys.iter()
+        .filter(|j| !j.legs.is_empty())
+        .filter_map(|j| schedule_backward(j, &db.routes_list, &db.circuity, arrive_by_s))
+        .collect();
+    plans.sort_by_key(|p| std::cmp::Reverse(p.depart_at_s));
+    plans
+}
+
+// --- WASM ---
+
+/// `arrive_by`: ISO 8601, Cancún time when it has no offset.
+#[wasm_bindgen]
+pub fn find_route_arrive_by(origin: &str, dest: &str, arrive_by: &str) -> Result<JsValue, JsValue> {
+    let deadline = clock::parse_datetime(arrive_by).map_err(|e| JsValue::from_str(&e))?;
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    if db.routes_list.is_empty() {
+        return Err(JsValue::from_str("Catalog not loaded"));
+    }
+    let res = plan_arrive_by(&db, origin, dest, deadline);
+    serde_wasm_bindgen::to_value(&res).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::parse_datetime;
+    use crate::tests::mock_route;
+    use crate::{aliases, realtime, Schedule};
+
+    fn db() -> AppState {
+        let mut r1 = mock_route("R1", vec![("Villas Otoch", 21.1619, -86.8515), ("El Crucero", 21.1714, -86.8219)]);
+        r1.transport_type = "Bus_Urban".to_string();
+        r1.frecuencia_minutos = Some(10);
+        r1.horario = Some(Schedule {
+            inicio_oficial: Some("05:00".into()), fin_oficial: Some("22:30".into()),
+            guardia_nocturna: Some("03:00 - 05:00 (Sistema de llenado en OXXO)".into()), ..Default::default()
+        });
+        let mut r2 = mock_route("R2", vec![("El Crucero", 21.1714, -86.8219), ("Zona Hotelera", 21.135, -86.768)]);
+        r2.transport_type = "Bus_HotelZone".to_string();
+        r2.frecuencia_minutos = Some(8);
+        r2.horario = Some(Schedule { inicio: Some("06:00 - 22:00".into()), ..Default::default() });
+        AppState {
+            routes_list: vec![r1, r2],
+            stations: vec![],
+            aliases: aliases::AliasTable::default(),
+            alerts: vec![],
+            vehicles: realtime::VehicleStore::default(),
+            ferries: Default::default(),
+            circuity: Default::default(),
+            fares: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_arrive_by_works_backward() {
+        let db = db();
+        let deadline = parse_datetime("2026-05-12T06:45").unwrap();
+        let plans = plan_arrive_by(&db, "Villas Otoch", "Zona Hotelera", deadline);
+        assert_eq!(plans.len(), 1);
+        let p = &plans[0];
+        assert_eq!(p.arrive_by, "06:45");
+        assert_eq!(p.legs.len(), 2);
+        assert!(p.legs[1].alight_s <= deadline);
+        assert!(p.legs[0].alight_s <= p.legs[1].at_stop_s);
+        assert_eq!(p.legs[1].wait_minutes, 8);
+        assert!(p.depart_at_s < deadline - 30 * 60, "{}", p.depart_at);
+        assert!(!p.night_guard && p.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_ride_uses_boarding_traffic() {
+        let db = db();
+        // Alighting at 10:10 after boarding in the morning rush
+        let deadline = parse_datetime("2026-05-12T10:10").unwrap();
+        let p = &plan_arrive_by(&db, "Villas Otoch", "El Crucero", deadline)[0];
+        let leg = &p.journey.legs[0];
+        let (board_s, alight_s) = (p.legs[0].board_s, p.legs[0].alight_s);
+        assert_eq!(clock::cancun_hour(board_s), 9);
+        let forward = (timing::ride_min(&db.routes_list[0], leg, board_s) * 60.0).round() as i64;
+        assert_eq!(alight_s - board_s, forward);
+        let at_alight = (timing::ride_min(&db.routes_list[0], leg, alight_s) * 60.0).round() as i64;
+        assert!(forward > at_alight);
+    }
+
+    #[test]
+    fn test_night_guard_warning_and_no_service() {
+        let db = db();
+        // Only R1 is needed; boarding at ~04:30 falls in the night guard.
+        let deadline = parse_datetime("2026-05-12T04:50").unwrap();
+        let plans = plan_arrive_by(&db, "Villas Otoch", "El Crucero", deadline);
+        assert!(plans[0].night_guard);
+        assert_eq!(plans[0].legs[0].wait_minutes, 20);
+        assert!(plans[0].warnings[0].contains("OXXO"));
+
+        // R2 does not run before 06:00.
+        let early = parse_datetime("2026-05-12T05:30").unwrap();
+        assert!(plan_arrive_by(&db, "Villas Otoch", "Zona Hotelera", early).is_empty());
+    }
+}
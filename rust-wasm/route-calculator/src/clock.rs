@@ -0,0 +1,3 @@
+//! Clock helpers, re-exported from `shared_types::clock`.
+
+pub use shared_types::clock::*;
@@ -0,0 +1,203 @@
+//! Departure board: what leaves a stop next, across every route serving it
+//! or its station.
+//!
+//! Waits come from live vehicles when one is heading to the stop, otherwise
+//! from the route headway (half of `frecuencia_minutos`, the expected wait
+//! when arriving at random). Service hours come from `schedule`.
+
+use serde::Serialize;
+use shared_types::TrafficConditions;
+use std::collections::HashSet;
+use wasm_bindgen::prelude::*;
+
+use crate::alerts::{self, AlertEffect, ServiceAlert};
+use crate::clock::{self, format_hhmm};
+use crate::realtime::{self, VehicleStore};
+use crate::schedule::{service_at, service_window, Service};
+use crate::timing::headway_min;
+use crate::{find_stop_on_route, Route, DB};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Departure {
+    pub route_id: String,
+    pub route_name: String,
+    pub transport_type: String,
+    /// Stop name on this route; may differ from the query within a station.
+    pub stop: String,
+    pub stop_index: usize,
+    /// Terminal the route heads to.
+    pub headsign: String,
+    pub wait_minutes: u32,
+    /// `wait_minutes` comes from a tracked vehicle.
+    pub live: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vehicle_id: Option<String>,
+    pub in_service: bool,
+    /// `HH:MM–HH:MM`, Cancún time.
+    pub service_hours: String,
+    /// A `closed_stop` alert keeps the route from stopping here.
+    pub stop_closed: bool,
+    pub alerts: Vec<ServiceAlert>,
+}
+
+/// Every (route, stop index) where `stop` can be boarded: the stop itself
+/// and the other members of its station.
+fn boarding_points<'a>(stop: &str, routes: &'a [Route]) -> Vec<(&'a Route, usize)> {
+    let direct: Vec<(&Route, usize)> = routes.iter()
+        .filter_map(|r| find_stop_on_route(r, stop).map(|i| (r, i)))
+        .collect();
+    let station_ids: HashSet<&str> = direct.iter()
+        .filter_map(|(r, i)| r.station_ids.get(*i).map(|s| s.as_str()))
+        .collect();
+    routes.iter()
+        .filter_map(|r| {
+            direct.iter().find(|(d, _)| d.id == r.id).map(|(_, i)| *i)
+                .or_else(|| station_ids.iter().find_map(|s| r.station_to_index.get(*s).copied()))
+                .map(|i| (r, i))
+        })
+        .collect()
+}
+
+/// Next departures from `stop` at `now_s`, in service first, soonest first.
+pub fn departures_rs(
+    stop: &str, routes: &[Route], vehicles: &VehicleStore, active: &[&ServiceAlert], now_s: i64, limit: usize,
+) -> Vec<Departure> {
+    let minute = clock::cancun_minute_of_day(now_s);
+    let traffic = TrafficConditions::from_hour(clock::cancun_hour(now_s));
+
+    let mut out: Vec<Departure> = boarding_points(stop, routes).into_iter()
+        // Nothing departs from the last stop.
+        .filter(|(r, i)| *i + 1 < r.stops.len())
+        .map(|(route, idx)| {
+            let window = service_window(route);
+            let service = service_at(route, minute);
+            let headway = headway_min(route, service);
+
+            let alerts = alerts::alerts_at_stop(active, route, idx);
+            let stop_closed = alerts.iter().any(|a| a.effect == AlertEffect::ClosedStop);
+            let delay: u32 = alerts.iter()
+                .map(|a| if let AlertEffect::Delay { minutes } = a.effect { minutes } else { 0 })
+                .sum();
+
+            let live = realtime::predict_route(route, idx, vehicles, now_s, &traffic).into_iter().next();
+            let wait_minutes = match (&live, service) {
+                (Some(p), _) => p.minutes,
+                (None, Service::NotRunning) => (window.0 + 1440 - minute) % 1440 + headway.div_ceil(2),
+                (None, _) => headway.div_ceil(2) + delay,
+            };
+
+            Departure {
+                route_id: route.id.clone(),
+                route_name: route.name.clone(),
+                transport_type: route.transport_type.clone(),
+                stop: route.stops[idx].name.clone(),
+                stop_index: idx,
+                headsign: route.stops.last().map(|s| s.name.clone()).unwrap_or_default(),
+                wait_minutes,
+                live: live.is_some(),
+                vehicle_id: live.map(|p| p.vehicle_id),
+                in_service: service != Service::NotRunning,
+                service_hours: format!("{}–{}", format_hhmm(window.0), format_hhmm(window.1)),
+                stop_closed,
+                alerts,
+            }
+        })
+        .collect();
+
+    out.sort_by_key(|d| (d.stop_closed || !d.in_service, d.wait_minutes));
+    out.truncate(limit);
+    out
+}
+
+// --- WASM ---
+
+/// Board for `stop` at `time` (ISO 8601, Cancún time without offset; empty
+/// for now).
+#[wasm_bindgen]
+pub fn departures(stop: &str, time: &str, limit: usize) -> Result<JsValue, JsValue> {
+    let now = if time.trim().is_empty() {
+        clock::now_unix_s()
+    } else {
+        clock::parse_datetime(time).map_err(|e| JsValue::from_str(&e))?
+    };
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    if db.routes_list.is_empty() {
+        return Err(JsValue::from_str("Catalog not loaded"));
+    }
+    let (stop, _) = db.aliases.resolve(stop, &db.routes_list, &db.stations);
+    let active = alerts::active_alerts(&db.alerts, now);
+    let res = departures_rs(&stop, &db.routes_list, &db.vehicles, &active, now, limit);
+    serde_wasm_bindgen::to_value(&res).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::parse_datetime;
+    use crate::stations::{assign_stations, cluster_stations};
+    use crate::tests::mock_route;
+    use crate::Schedule;
+
+    fn network() -> Vec<Route> {
+        let mut r1 = mock_route("R1", vec![
+            ("La Rehoyada", 21.1619, -86.8515), ("El Crucero", 21.1714, -86.8219), ("Zona Hotelera", 21.135, -86.768),
+        ]);
+        r1.frecuencia_minutos = Some(10);
+        r1.horario = Some(Schedule { inicio: Some("06:00".into()), fin: Some("22:30".into()), ..Default::default() });
+        // Same corner, named differently on another route.
+        let mut r2 = mock_route("R2", vec![
+            ("Crucero", 21.1714, -86.8219), ("Mercado 28", 21.16, -86.83), ("Puerto Juárez", 21.18, -86.80),
+        ]);
+        r2.frecuencia_minutos = Some(20);
+        r2.horario = Some(Schedule { inicio: Some("05:30 - 00:30".into()), ..Default::default() });
+        let r3 = mock_route("R3", vec![("Mercado 28", 21.16, -86.83), ("El Crucero", 21.1714, -86.8219)]);
+        let mut routes = vec![r1, r2, r3];
+        let stations = cluster_stations(&routes);
+        assign_stations(&mut routes, &stations);
+        routes
+    }
+
+    #[test]
+    fn test_board_lists_station_routes_by_wait() {
+        let routes = network();
+        let now = parse_datetime("2026-05-12T07:10").unwrap();
+        let board = departures_rs("el crucero", &routes, &VehicleStore::default(), &[], now, 10);
+        // R3 ends at El Crucero: nothing departs from there.
+        let ids: Vec<&str> = board.iter().map(|d| d.route_id.as_str()).collect();
+        assert_eq!(ids, ["R1", "R2"]);
+        assert_eq!(board[0].headsign, "Zona Hotelera");
+        assert_eq!(board[0].wait_minutes, 5);
+        assert_eq!(board[1].stop, "Crucero");
+        assert_eq!(board[1].service_hours, "05:30–00:30");
+        assert!(board.iter().all(|d| d.in_service && !d.live));
+
+        // 23:45: R1 has stopped, R2 runs past midnight.
+        let late = parse_datetime("2026-05-12T23:45").unwrap();
+        let board = departures_rs("El Crucero", &routes, &VehicleStore::default(), &[], late, 10);
+        assert_eq!(board[0].route_id, "R2");
+        assert!(!board[1].in_service);
+        assert_eq!(board[1].wait_minutes, 6 * 60 + 15 + 5);
+    }
+
+    #[test]
+    fn test_board_uses_live_vehicles_and_alerts() {
+        let routes = network();
+        let now = parse_datetime("2026-05-12T07:10").unwrap();
+        let mut vehicles = VehicleStore::default();
+        vehicles.ingest_json(r#"[{"id": "U1", "route_id": "R2", "lat": 21.1714, "lng": -86.8222,
+            "updated_at": "2026-05-12T07:09:50"}]"#).unwrap();
+        let delay: ServiceAlert = serde_json::from_str(r#"{"id": "obra", "message": "Obra en El Crucero", "route_ids": ["R1"],
+            "effect": {"type": "delay", "minutes": 10}}"#).unwrap();
+
+        let board = departures_rs("El Crucero", &routes, &vehicles, &[&delay], now, 1);
+        assert_eq!(board.len(), 1);
+        assert_eq!(board[0].route_id, "R2");
+        assert_eq!(board[0].vehicle_id.as_deref(), Some("U1"));
+        assert!(board[0].live && board[0].wait_minutes <= 1);
+
+        let board = departures_rs("El Crucero", &routes, &VehicleStore::default(), &[&delay], now, 5);
+        let r1 = board.iter().find(|d| d.route_id == "R1").unwrap();
+        assert_eq!(r1.wait_minutes, 15);
+        assert_eq!(r1.alerts[0].id, "obra");
+    }
+}
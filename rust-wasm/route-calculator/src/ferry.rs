@@ -0,0 +1,222 @@
+//! Journeys that include a ferry crossing.
+//!
+//! One end of the trip is a ferry terminal (Isla Mujeres, Cozumel, or a
+//! mainland pier). The bus part runs between the other end and the pier's
+//! `hub` stop, placed on the clock like any itinerary, then the rider walks
+//! to the pier and takes the next listed departure; coming off the island
+//! it is the other way round.
+
+use serde::{Deserialize, Serialize};
+use shared_types::circuity::CircuityModel;
+use shared_types::exclusions::Exclusions;
+use shared_types::ferry::{Crossing, FareClass, FerryTerminal, FerryTimetable};
+use shared_types::TransportType;
+use wasm_bindgen::prelude::*;
+
+use crate::clock::{self, format_hhmm};
+use crate::timing::schedule_forward;
+use crate::{leg_stop_indices, plan_journeys, AppState, Journey, Route, DB, MAX_SEARCH_RESULTS};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FerryTrip {
+    /// Bus part, with its itinerary.
+    pub journey: Journey,
+    pub crossing: Crossing,
+    /// The crossing comes before the bus part.
+    pub ferry_first: bool,
+    /// Between the pier and its hub stop.
+    pub walk_minutes: u32,
+    pub depart: String,
+    pub arrive: String,
+    pub depart_s: i64,
+    pub arrive_s: i64,
+    pub total_fare_mxn: f64,
+}
+
+/// Walking seconds between `terminal` and the stop of `journey` at the
+/// pier (its last stop, or its first when `first`).
+fn pier_walk_s(journey: &Journey, routes: &[Route], circuity: &CircuityModel, terminal: &FerryTerminal, first: bool) -> i64 {
+    let leg = if first { journey.legs.first() } else { journey.legs.last() };
+    let stop = leg.and_then(|leg| {
+        let route = routes.iter().find(|r| r.id == leg.route_id)?;
+        let (a, b) = leg_stop_indices(route, leg)?;
+        route.stops.get(if first { a } else { b })
+    });
+    let Some(stop) = stop else { return 0 };
+    let m = circuity.road_distance_m(&TransportType::Caminata, [stop.lat, stop.lng], [terminal.lat, terminal.lng]);
+    (m / 1000.0 / TransportType::Caminata.avg_speed_kmh() * 3600.0).round() as i64
+}
+
+fn trip(journey: Journey, crossing: Crossing, ferry_first: bool, walk_s: i64, depart_s: i64, arrive_s: i64) -> FerryTrip {
+    let hhmm = |t: i64| format_hhmm(clock::cancun_minute_of_day(t));
+    FerryTrip {
+        total_fare_mxn: journey.total_price + crossing.fare_mxn,
+        walk_minutes: ((walk_s + 59) / 60) as u32,
+        depart: hhmm(depart_s),
+        arrive: hhmm(arrive_s),
+        depart_s,
+        arrive_s,
+        journey,
+        crossing,
+        ferry_first,
+    }
+}
+
+/// Trips between `origin` and `dest` leaving at `depart_s` where one of
+/// them is a ferry terminal (id or name). Earliest arrival first.
+fn plan_ferry_trips(db: &AppState, origin: &str, dest: &str, depart_s: i64, class: FareClass) -> Result<Vec<FerryTrip>, String> {
+    let table = &db.ferries;
+    let mut trips = Vec::new();
+    match (table.terminal_named(origin), table.terminal_named(dest)) {
+        (Some(_), Some(_)) => return Err("Both ends are ferry terminals; use next_ferries".to_string()),
+        (None, None) => return Err(format!("Neither {} nor {} is a ferry terminal", origin, dest)),
+        // Mainland piers are reached by bus; their crossings go to the islands
+        (Some(from), None) if from.hub.is_some() => {
+            return Err(format!("{} is a mainland pier; use next_ferries for its crossings", from.name));
+        }
+        (None, Some(to)) if to.hub.is_some() => {
+            return Err(format!("{} is a mainland pier; use find_route to {}", to.name, to.hub.as_deref().unwrap_or_default()));
+        }
+        // Bus to the pier, then the boat
+        (None, Some(to)) => {
+            for line in table.lines.iter().filter(|l| l.to == to.id) {
+                let Some(pier) = table.terminal(&line.from) else { continue };
+                let Some(hub) = &pier.hub else { continue };
+                for j in plan_journeys(db, origin, hub, depart_s, &Exclusions::default()) {
+                    let Some(at_hub) = j.itinerary.as_ref().map(|it| it.arrive_s) else { continue };
+                    let walk_s = pier_walk_s(&j, &db.routes_list, &db.circuity, pier, false);
+                    let Some(c) = table.next_crossings(&pier.id, &to.id, at_hub + walk_s, class, 1).pop() else { continue };
+                    let arrive_s = c.arrive_s;
+                    trips.push(trip(j, c, false, walk_s, depart_s, arrive_s));
+                }
+            }
+        }
+        // The boat, then the bus from the pier
+        (Some(from), None) => {
+            for line in table.lines.iter().filter(|l| l.from == from.id) {
+                let Some(pier) = table.terminal(&line.to) else { continue };
+                let Some(hub) = &pier.hub else { continue };
+                let Some(c) = table.next_crossings(&from.id, &pier.id, depart_s, class, 1).pop() else { continue };
+                for j in plan_journeys(db, hub, dest, c.arrive_s, &Exclusions::default()) {
+                    let walk_s = pier_walk_s(&j, &db.routes_list, &db.circuity, pier, true);
+                    // Replace the itinerary with one leaving the pier on foot
+                    let it = schedule_forward(&j, &db.routes_list, &db.circuity, c.arrive_s + walk_s);
+                    let Some(arrive_s) = it.as_ref().map(|it| it.arrive_s) else { continue };
+                    let j = Journey { itinerary: it, ..j };
+                    trips.push(trip(j, c.clone(), true, walk_s, depart_s, arrive_s));
+                }
+            }
+        }
+    }
+    trips.sort_by(|a, b| a.arrive_s.cmp(&b.arrive_s).then(a.total_fare_mxn.total_cmp(&b.total_fare_mxn)));
+    trips.truncate(MAX_SEARCH_RESULTS);
+    Ok(trips)
+}
+
+// --- WASM ---
+
+pub fn load_ferry_timetable_core(json: &str) -> Result<usize, String> {
+    let table: FerryTimetable = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    table.validate()?;
+    let n = table.lines.len();
+    DB.write().map_err(|_| "Lock failed".to_string())?.ferries = table;
+    Ok(n)
+}
+
+/// Replaces the ferry timetable (same shape as in `eta-engine`). Returns
+/// how many lines it holds.
+#[wasm_bindgen]
+pub fn load_ferry_timetable(json: &str) -> Result<usize, JsValue> {
+    load_ferry_timetable_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Like `find_route_at` when `origin` or `dest` is a ferry terminal
+/// ("Isla Mujeres", "cozumel", ...). `depart_at`: ISO 8601, Cancún time;
+/// empty for now. `fare_class`: `tourist` (default) or `resident`.
+#[wasm_bindgen]
+pub fn find_route_with_ferry(origin: &str, dest: &str, depart_at: &str, fare_class: JsValue) -> Result<JsValue, JsValue> {
+    let depart = if depart_at.trim().is_empty() {
+        clock::now_unix_s()
+    } else {
+        clock::parse_datetime(depart_at).map_err(|e| JsValue::from_str(&e))?
+    };
+    let class: Option<FareClass> = serde_wasm_bindgen::from_value(fare_class)
+        .map_err(|e| JsValue::from_str(&format!("fare_class: {}", e)))?;
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    if db.routes_list.is_empty() {
+        return Err(JsValue::from_str("Catalog not loaded"));
+    }
+    let trips = plan_ferry_trips(&db, origin, dest, depart, class.unwrap_or_default()).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&trips).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::parse_datetime;
+    use crate::tests::mock_route;
+    use crate::{aliases, realtime};
+
+    fn db() -> AppState {
+        let mut r = mock_route("CR_PTO_JUAREZ", vec![
+            ("El Crucero", 21.1714, -86.8219),
+            ("Muelle Ultramar (Puerto Juárez)", 21.1819, -86.8028),
+        ]);
+        r.transport_type = "Combi_Municipal".to_string();
+        r.frecuencia_minutos = Some(10);
+        AppState {
+            routes_list: vec![r],
+            stations: vec![],
+            aliases: aliases::AliasTable::default(),
+            alerts: vec![],
+            vehicles: realtime::VehicleStore::default(),
+            ferries: FerryTimetable::default(),
+            circuity: CircuityModel::default(),
+            fares: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_bus_then_ferry_and_back() {
+        let db = db();
+        let depart = parse_datetime("2026-05-12T10:00").unwrap();
+        let out = plan_ferry_trips(&db, "El Crucero", "Isla Mujeres", depart, FareClass::Tourist).unwrap();
+        assert!(!out.is_empty());
+        let t = &out[0];
+        assert!(!t.ferry_first);
+        let bus = t.journey.itinerary.as_ref().unwrap();
+        assert!(t.crossing.depart_s >= bus.arrive_s + t.walk_minutes as i64 * 60 - 60);
+        assert_eq!(t.arrive_s, t.crossing.arrive_s);
+        assert_eq!(t.total_fare_mxn, t.journey.total_price + t.crossing.fare_mxn);
+
+        let back = plan_ferry_trips(&db, "Isla Mujeres", "El Crucero", depart, FareClass::Resident).unwrap();
+        let b = &back[0];
+        assert!(b.ferry_first);
+        assert!(b.journey.itinerary.as_ref().unwrap().depart_s >= b.crossing.arrive_s);
+        assert!(b.crossing.fare_mxn < t.crossing.fare_mxn);
+
+        assert!(plan_ferry_trips(&db, "El Crucero", "Zona Hotelera", depart, FareClass::Tourist).is_err());
+    }
+
+    #[test]
+    fn test_mainland_pier_is_not_a_ferry_trip() {
+        let db = db();
+        let depart = parse_datetime("2026-05-12T10:00").unwrap();
+        let err = plan_ferry_trips(&db, "Puerto Juárez", "El Crucero", depart, FareClass::Tourist).unwrap_err();
+        assert!(err.contains("next_ferries"), "{}", err);
+        let err = plan_ferry_trips(&db, "El Crucero", "Puerto Juárez", depart, FareClass::Tourist).unwrap_err();
+        assert!(err.contains("find_route"), "{}", err);
+    }
+
+    #[test]
+    fn test_pier_walk_uses_loaded_circuity() {
+        let mut db = db();
+        let depart = parse_datetime("2026-05-12T10:00").unwrap();
+        let plain = plan_ferry_trips(&db, "El Crucero", "Isla Mujeres", depart, FareClass::Tourist).unwrap();
+        db.circuity = CircuityModel { modes: [("Caminata".to_string(), 3.0)].into(), ..Default::default() };
+        let winding = plan_ferry_trips(&db, "El Crucero", "Isla Mujeres", depart, FareClass::Tourist).unwrap();
+        // The hub stop is ~500 m from the Puerto Juárez pier
+        let walk = |trips: &[FerryTrip]| trips.iter().find(|t| t.crossing.line_id == "MARITIMA_PJ_ISLA").unwrap().walk_minutes;
+        assert!(walk(&winding) > walk(&plain));
+    }
+}
@@ -0,0 +1,320 @@
+//! GeoJSON export for the Leaflet map: routes, stops, hubs and journeys.
+//!
+//! Geometry is built with `geo` types and serialized as RFC 7946
+//! (`[lng, lat]` coordinate order).
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use shared_types::geo::{Centroid, Coord, LineString, MultiPoint, Point};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+use crate::gtfs::gtfs_stop_id;
+use crate::{leg_coords, normalize_str, Journey, Route, RouteLeg, Stop, DB, PREFERRED_HUBS};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", content = "coordinates")]
+pub enum Geometry {
+    Point([f64; 2]),
+    LineString(Vec<[f64; 2]>),
+}
+
+impl From<Point<f64>> for Geometry {
+    fn from(p: Point<f64>) -> Self {
+        Geometry::Point([p.x(), p.y()])
+    }
+}
+
+impl From<LineString<f64>> for Geometry {
+    fn from(ls: LineString<f64>) -> Self {
+        Geometry::LineString(ls.coords().map(|c| [c.x, c.y]).collect())
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Feature {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub geometry: Geometry,
+    pub properties: Map<String, Value>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct FeatureCollection {
+    #[serde(rename = "type")]
+    pub type_: &'static str,
+    pub features: Vec<Feature>,
+}
+
+fn feature(geometry: impl Into<Geometry>, properties: Value) -> Feature {
+    let properties = match properties {
+        Value::Object(m) => m,
+        _ => Map::new(),
+    };
+    Feature { type_: "Feature", geometry: geometry.into(), properties }
+}
+
+fn collection(features: Vec<Feature>) -> FeatureCollection {
+    FeatureCollection { type_: "FeatureCollection", features }
+}
+
+fn stop_point(s: &Stop) -> Point<f64> {
+    Point::new(s.lng, s.lat)
+}
+
+fn ordered_stops(route: &Route) -> Vec<&Stop> {
+    let mut stops: Vec<&Stop> = route.stops.iter().collect();
+    stops.sort_by_key(|s| s.orden);
+    stops
+}
+
+fn line(stops: &[&Stop]) -> LineString<f64> {
+    stops.iter().map(|s| Coord { x: s.lng, y: s.lat }).collect()
+}
+
+fn shape_line(shape: &[[f64; 2]]) -> LineString<f64> {
+    shape.iter().map(|p| Coord { x: p[1], y: p[0] }).collect()
+}
+
+/// The traced shape when there is one, the stop sequence otherwise.
+fn route_line(route: &Route) -> LineString<f64> {
+    match route.shape.as_deref() {
+        Some(shape) if shape.len() >= 2 => shape_line(shape),
+        _ => line(&ordered_stops(route)),
+    }
+}
+
+// --- BUILDERS ---
+
+/// One LineString per route with color, fare and transport type.
+/// Uses the traced shape when the route has one.
+pub fn routes_geojson_rs(routes: &[Route]) -> FeatureCollection {
+    let features = routes.iter()
+        .filter(|r| r.stops.len() >= 2)
+        .map(|r| feature(route_line(r), json!({
+            "route_id": r.id,
+            "name": r.name,
+            "color": r.color,
+            "fare": r.price,
+            "type": r.transport_type,
+            "empresa": r.empresa,
+            "frecuencia_minutos": r.frecuencia_minutos,
+        })))
+        .collect();
+    collection(features)
+}
+
+/// One Point per distinct stop, listing every route that serves it.
+pub fn stops_geojson_rs(routes: &[Route]) -> FeatureCollection {
+    let mut order: Vec<String> = Vec::new();
+    let mut by_id: HashMap<String, (&Stop, Vec<&str>)> = HashMap::new();
+    for route in routes {
+        for stop in &route.stops {
+            let id = gtfs_stop_id(stop);
+            let entry = by_id.entry(id.clone()).or_insert_with(|| {
+                order.push(id);
+                (stop, Vec::new())
+            });
+            if !entry.1.contains(&route.id.as_str()) {
+                entry.1.push(&route.id);
+            }
+        }
+    }
+    let features = order.iter().map(|id| {
+        let (stop, route_ids) = &by_id[id];
+        feature(stop_point(stop), json!({
+            "stop_id": id,
+            "name": stop.name,
+            "routes": route_ids,
+            "route_count": route_ids.len(),
+        }))
+    }).collect();
+    collection(features)
+}
+
+/// Whether the words of `phrase` appear together in `name` (both
+/// normalized), so "ado" matches "ado centro" but not "mercado 28".
+fn has_phrase(name: &str, phrase: &str) -> bool {
+    let words: Vec<&str> = name.split_whitespace().collect();
+    let wanted: Vec<&str> = phrase.split_whitespace().collect();
+    !wanted.is_empty() && words.windows(wanted.len()).any(|w| w == wanted.as_slice())
+}
+
+/// One Point per preferred hub, placed at the centroid of its matching stops.
+pub fn hubs_geojson_rs(routes: &[Route]) -> FeatureCollection {
+    let mut features = Vec::new();
+    for hub in PREFERRED_HUBS {
+        let hub_norm = normalize_str(hub);
+        let mut points = Vec::new();
+        let mut route_ids: Vec<&str> = Vec::new();
+        for route in routes {
+            for (stop, norm) in route.stops.iter().zip(&route.stops_normalized) {
+                if has_phrase(norm, &hub_norm) {
+                    points.push(stop_point(stop));
+                    if !route_ids.contains(&route.id.as_str()) { route_ids.push(&route.id); }
+                }
+            }
+        }
+        if let Some(center) = MultiPoint::new(points).centroid() {
+            features.push(feature(center, json!({
+                "name": hub,
+                "routes": route_ids,
+                "route_count": route_ids.len(),
+            })));
+        }
+    }
+    collection(features)
+}
+
+struct LegPath<'a> {
+    route: &'a Route,
+    stops: Vec<&'a Stop>,
+    geometry: LineString<f64>,
+}
+
+fn leg_path<'a>(leg: &RouteLeg, routes: &'a [Route]) -> Option<LegPath<'a>> {
+    let route = routes.iter().find(|r| r.id == leg.route_id)?;
+    let from = *route.stop_name_to_index.get(&normalize_str(&leg.origin_stop))?;
+    let to = *route.stop_name_to_index.get(&normalize_str(&leg.dest_stop))?;
+    let stops: Vec<&Stop> = if from <= to {
+        route.stops[from..=to].iter().collect()
+    } else {
+        route.stops[to..=from].iter().rev().collect()
+    };
+    let geometry = shape_line(&leg_coords(route, from, to));
+    Some(LegPath { route, stops, geometry })
+}
+
+/// Each leg of a journey as its own LineString, plus the walk between legs
+/// when the transfer is geographic rather than at a shared stop.
+pub fn journey_geojson_rs(journey: &Journey, routes: &[Route]) -> FeatureCollection {
+    let mut features = Vec::new();
+    let mut prev_end: Option<&Stop> = None;
+    for (i, leg) in journey.legs.iter().enumerate() {
+        let Some(LegPath { route, stops, geometry }) = leg_path(leg, routes) else { continue };
+        if let (Some(end), Some(start)) = (prev_end, stops.first()) {
+            if end.lat != start.lat || end.lng != start.lng {
+                features.push(feature(line(&[end, start]), json!({
+                    "journey_id": journey.id,
+                    "kind": "walk",
+                    "from": end.name,
+                    "to": start.name,
+                })));
+            }
+        }
+        prev_end = stops.last().copied();
+        features.push(feature(geometry, json!({
+            "journey_id": journey.id,
+            "kind": "leg",
+            "leg_index": i,
+            "route_id": leg.route_id,
+            "route_name": leg.route_name,
+            "origin_stop": leg.origin_stop,
+            "dest_stop": leg.dest_stop,
+            "price": leg.price,
+            "distance_m": leg.distance_m,
+            "color": route.color,
+            "type": route.transport_type,
+        })));
+    }
+    collection(features)
+}
+
+// --- WASM ---
+
+fn to_js(fc: &FeatureCollection) -> Result<JsValue, JsValue> {
+    // json_compatible: plain objects instead of ES Maps, so Leaflet can read it.
+    fc.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+fn with_routes(f: impl Fn(&[Route]) -> FeatureCollection) -> Result<JsValue, JsValue> {
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    to_js(&f(&db.routes_list))
+}
+
+#[wasm_bindgen]
+pub fn routes_geojson() -> Result<JsValue, JsValue> {
+    with_routes(routes_geojson_rs)
+}
+
+#[wasm_bindgen]
+pub fn stops_geojson() -> Result<JsValue, JsValue> {
+    with_routes(stops_geojson_rs)
+}
+
+#[wasm_bindgen]
+pub fn hubs_geojson() -> Result<JsValue, JsValue> {
+    with_routes(hubs_geojson_rs)
+}
+
+/// `journey_js` is one element of the array returned by `find_route`.
+#[wasm_bindgen]
+pub fn journey_geojson(journey_js: JsValue) -> Result<JsValue, JsValue> {
+    let journey: Journey = serde_wasm_bindgen::from_value(journey_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    with_routes(|routes| journey_geojson_rs(&journey, routes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_route_rs;
+    use crate::tests::mock_route;
+
+    fn network() -> Vec<Route> {
+        let mut r1 = mock_route("R1", vec![("A", 21.10, -86.80), ("El Crucero", 21.1714, -86.8219)]);
+        r1.color = Some("#4ECDC4".to_string());
+        let r2 = mock_route("R2", vec![("El Crucero", 21.1714, -86.8219), ("B", 21.20, -86.83)]);
+        vec![r1, r2]
+    }
+
+    #[test]
+    fn test_route_linestring_is_lng_lat() {
+        let fc = routes_geojson_rs(&network());
+        assert_eq!(fc.features.len(), 2);
+        let f = &fc.features[0];
+        assert_eq!(f.geometry, Geometry::LineString(vec![[-86.80, 21.10], [-86.8219, 21.1714]]));
+        assert_eq!(f.properties["color"], "#4ECDC4");
+        assert_eq!(f.properties["fare"], 10.0);
+        let v = serde_json::to_value(&fc).unwrap();
+        assert_eq!(v["type"], "FeatureCollection");
+        assert_eq!(v["features"][0]["geometry"]["type"], "LineString");
+    }
+
+    #[test]
+    fn test_shared_stop_lists_both_routes() {
+        let fc = stops_geojson_rs(&network());
+        assert_eq!(fc.features.len(), 3);
+        let crucero = fc.features.iter().find(|f| f.properties["name"] == "El Crucero").unwrap();
+        assert_eq!(crucero.properties["routes"], json!(["R1", "R2"]));
+
+        let hubs = hubs_geojson_rs(&network());
+        assert_eq!(hubs.features.len(), 1);
+        assert_eq!(hubs.features[0].properties["route_count"], 2);
+    }
+
+    #[test]
+    fn test_hub_matches_whole_words() {
+        let routes = vec![mock_route("R1", vec![
+            ("ADO Centro", 21.1610, -86.8260),
+            ("Mercado 28", 21.1640, -86.8330),
+            ("Mercado 23", 21.1680, -86.8290),
+        ])];
+        let hubs = hubs_geojson_rs(&routes);
+        let ado = hubs.features.iter().find(|f| f.properties["name"] == "ADO").unwrap();
+        assert_eq!(ado.geometry, Geometry::Point([-86.8260, 21.1610]));
+        let m28 = hubs.features.iter().find(|f| f.properties["name"] == "Mercado 28").unwrap();
+        assert_eq!(m28.geometry, Geometry::Point([-86.8330, 21.1640]));
+    }
+
+    #[test]
+    fn test_journey_legs_are_separate_features() {
+        let routes = network();
+        let journeys = find_route_rs("A", "B", &routes);
+        let fc = journey_geojson_rs(&journeys[0], &routes);
+        assert_eq!(fc.features.len(), 2);
+        assert_eq!(fc.features[0].properties["route_id"], "R1");
+        assert_eq!(fc.features[1].properties["leg_index"], 1);
+    }
+}
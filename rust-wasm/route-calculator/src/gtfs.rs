@@ -0,0 +1,555 @@
+//! GTFS static export of the loaded catalog.
+//!
+//! Every route becomes one frequency-based trip (`frequencies.txt`) built from
+//! `frecuencia_minutos` and `horario`; stop times are a template spaced by the
+//! mode's average speed. Fares map one-to-one from `Route.price`.
+//! The feed is returned as `file name → CSV content`; zipping is left to JS.
+
+use serde::Serialize;
+use shared_types::TransportType;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+use crate::schedule::{guard_window, service_window, DEFAULT_HEADWAY_MIN};
+use crate::{haversine_distance_m, normalize_str, Route, Stop, DB};
+
+const AGENCY_URL: &str = "https://mueve-cancun.vercel.app";
+const AGENCY_TIMEZONE: &str = "America/Cancun";
+const DEFAULT_AGENCY: &str = "MueveCancún";
+const SERVICE_ID: &str = "DIARIO";
+
+const DWELL_SECS: u32 = 30;
+
+pub const REQUIRED_FILES: &[&str] = &[
+    "agency.txt", "stops.txt", "routes.txt", "trips.txt",
+    "stop_times.txt", "calendar.txt",
+];
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GtfsOptions {
+    /// YYYYMMDD
+    pub start_date: String,
+    /// YYYYMMDD
+    pub end_date: String,
+}
+
+impl Default for GtfsOptions {
+    fn default() -> Self {
+        Self { start_date: "20260101".to_string(), end_date: "20261231".to_string() }
+    }
+}
+
+impl GtfsOptions {
+    /// Service dates as `YYYYMMDD`, `start_date` not after `end_date`.
+    pub fn new(start_date: &str, end_date: &str) -> Result<Self, String> {
+        let opts = Self { start_date: start_date.trim().to_string(), end_date: end_date.trim().to_string() };
+        opts.validate()?;
+        Ok(opts)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, date) in [("start_date", &self.start_date), ("end_date", &self.end_date)] {
+            if !is_gtfs_date(date) {
+                return Err(format!("{}: expected a YYYYMMDD date, got {:?}", name, date));
+            }
+        }
+        if self.start_date > self.end_date {
+            return Err(format!("start_date {} is after end_date {}", self.start_date, self.end_date));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct GtfsFeed {
+    pub files: BTreeMap<String, String>,
+}
+
+// --- STABLE IDS ---
+
+/// FNV-1a, 64 bit. Deterministic across builds, unlike `DefaultHasher`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for b in bytes {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// `Stop.id` when present, otherwise a stable id from the normalized name and
+/// the coordinates rounded to ~1 m, so re-exports keep the same stop_ids.
+pub fn gtfs_stop_id(stop: &Stop) -> String {
+    if let Some(id) = stop.id.as_deref().filter(|id| !id.trim().is_empty()) {
+        return id.trim().to_string();
+    }
+    let key = format!("{}|{:.5}|{:.5}", normalize_str(&stop.name), stop.lat, stop.lng);
+    format!("S{:016x}", fnv1a(key.as_bytes()))
+}
+
+// --- TIME HELPERS ---
+
+fn gtfs_time(total_secs: u32) -> String {
+    format!("{:02}:{:02}:{:02}", total_secs / 3600, (total_secs / 60) % 60, total_secs % 60)
+}
+
+fn gtfs_route_type(transport: &TransportType) -> u8 {
+    match transport {
+        TransportType::Ferry => 4,
+        _ => 3,
+    }
+}
+
+// --- CSV ---
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+struct Csv(String);
+
+impl Csv {
+    fn new(header: &[&str]) -> Self {
+        let mut c = Csv(String::new());
+        c.row(header.iter().map(|h| h.to_string()).collect());
+        c
+    }
+
+    fn row(&mut self, fields: Vec<String>) {
+        let line: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        self.0.push_str(&line.join(","));
+        self.0.push('\n');
+    }
+}
+
+/// Splits a line written by `Csv`, honouring quoted fields.
+fn parse_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut cur = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => { cur.push('"'); chars.next(); }
+            ('"', _) => quoted = !quoted,
+            (',', false) => fields.push(std::mem::take(&mut cur)),
+            _ => cur.push(c),
+        }
+    }
+    fields.push(cur);
+    fields
+}
+
+// --- EXPORT ---
+
+/// Two catalog stops that may share a `stop_id`: same normalized name,
+/// coordinates within ~1 m.
+fn same_stop(a: &Stop, b: &Stop) -> bool {
+    normalize_str(&a.name) == normalize_str(&b.name)
+        && format!("{:.5}|{:.5}", a.lat, a.lng) == format!("{:.5}|{:.5}", b.lat, b.lng)
+}
+
+/// Fails when two different stops carry the same explicit `Stop.id`.
+pub fn export_gtfs_rs(routes: &[Route], opts: &GtfsOptions) -> Result<GtfsFeed, String> {
+    let mut agency = Csv::new(&["agency_id", "agency_name", "agency_url", "agency_timezone", "agency_lang"]);
+    let mut stops = Csv::new(&["stop_id", "stop_name", "stop_lat", "stop_lon"]);
+    let mut routes_csv = Csv::new(&["route_id", "agency_id", "route_short_name", "route_long_name", "route_type", "route_color"]);
+    let mut trips = Csv::new(&["route_id", "service_id", "trip_id", "direction_id"]);
+    let mut stop_times = Csv::new(&["trip_id", "arrival_time", "departure_time", "stop_id", "stop_sequence"]);
+    let mut frequencies = Csv::new(&["trip_id", "start_time", "end_time", "headway_secs", "exact_times"]);
+    let mut fare_attributes = Csv::new(&["fare_id", "price", "currency_type", "payment_method", "transfers"]);
+    let mut fare_rules = Csv::new(&["fare_id", "route_id"]);
+
+    let mut calendar = Csv::new(&[
+        "service_id", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday",
+        "start_date", "end_date",
+    ]);
+    let mut cal_row = vec![SERVICE_ID.to_string()];
+    cal_row.extend((0..7).map(|_| "1".to_string()));
+    cal_row.extend([opts.start_date.clone(), opts.end_date.clone()]);
+    calendar.row(cal_row);
+
+    let mut agencies: HashMap<String, String> = HashMap::new();
+    let mut seen_stops: HashMap<String, &Stop> = HashMap::new();
+    let mut fares: HashMap<String, f64> = HashMap::new();
+
+    for route in routes {
+        if route.stops.is_empty() { continue; }
+
+        let agency_name = route.empresa.clone().unwrap_or_else(|| DEFAULT_AGENCY.to_string());
+        let next_id = format!("A{}", agencies.len() + 1);
+        let agency_id = agencies.entry(agency_name.clone()).or_insert_with(|| {
+            agency.row(vec![next_id.clone(), agency_name.clone(), AGENCY_URL.into(), AGENCY_TIMEZONE.into(), "es".into()]);
+            next_id
+        }).clone();
+
+        let transport = TransportType::from_label(&route.transport_type);
+        let color = route.color.as_deref().unwrap_or("").trim_start_matches('#').to_uppercase();
+        // Anything but six hex digits (named colours, "#FFF") is left out.
+        let color = if is_gtfs_color(&color) { color } else { String::new() };
+        routes_csv.row(vec![
+            route.id.clone(), agency_id, String::new(), route.name.clone(),
+            gtfs_route_type(&transport).to_string(), color,
+        ]);
+
+        let fare_id = format!("MXN_{:.2}", route.price).replace('.', "_");
+        fares.entry(fare_id.clone()).or_insert(route.price);
+        fare_rules.row(vec![fare_id, route.id.clone()]);
+
+        let trip_id = format!("{}_T0", route.id);
+        trips.row(vec![route.id.clone(), SERVICE_ID.into(), trip_id.clone(), "0".into()]);
+
+        let (start, end) = service_window(route);
+        let speed_ms = transport.avg_speed_kmh() / 3.6;
+        let mut elapsed = 0.0_f64;
+        let mut ordered: Vec<&Stop> = route.stops.iter().collect();
+        ordered.sort_by_key(|s| s.orden);
+        for (seq, stop) in ordered.iter().enumerate() {
+            if seq > 0 {
+                let prev = ordered[seq - 1];
+                elapsed += haversine_distance_m(prev.lat, prev.lng, stop.lat, stop.lng) / speed_ms + DWELL_SECS as f64;
+            }
+            let stop_id = gtfs_stop_id(stop);
+            match seen_stops.get(&stop_id) {
+                Some(first) if !same_stop(first, stop) => {
+                    return Err(format!(
+                        "stop_id {} is used by {} ({}, {}) and {} ({}, {}) on route {}",
+                        stop_id, first.name, first.lat, first.lng, stop.name, stop.lat, stop.lng, route.id,
+                    ));
+                }
+                Some(_) => {}
+                None => {
+                    seen_stops.insert(stop_id.clone(), stop);
+                    stops.row(vec![stop_id.clone(), stop.name.clone(), format!("{:.6}", stop.lat), format!("{:.6}", stop.lng)]);
+                }
+            }
+            let t = gtfs_time(start * 60 + elapsed.round() as u32);
+            stop_times.row(vec![trip_id.clone(), t.clone(), t, stop_id, (seq + 1).to_string()]);
+        }
+
+        let headway = route.frecuencia_minutos.filter(|&f| f > 0).unwrap_or(DEFAULT_HEADWAY_MIN);
+        frequencies.row(vec![
+            trip_id.clone(), gtfs_time(start * 60), gtfs_time(end * 60), (headway * 60).to_string(), "0".into(),
+        ]);
+        // Night guard departures leave when full: modelled as half the frequency.
+        if let Some((g_start, g_end)) = guard_window(route) {
+            if g_end <= start || g_start >= end {
+                frequencies.row(vec![
+                    trip_id, gtfs_time(g_start * 60), gtfs_time(g_end * 60), (headway * 120).to_string(), "0".into(),
+                ]);
+            }
+        }
+    }
+
+    let mut fare_ids: Vec<&String> = fares.keys().collect();
+    fare_ids.sort();
+    for id in fare_ids {
+        fare_attributes.row(vec![id.clone(), format!("{:.2}", fares[id]), "MXN".into(), "0".into(), "0".into()]);
+    }
+
+    let mut feed = GtfsFeed::default();
+    for (name, csv) in [
+        ("agency.txt", agency), ("stops.txt", stops), ("routes.txt", routes_csv),
+        ("trips.txt", trips), ("stop_times.txt", stop_times), ("calendar.txt", calendar),
+        ("frequencies.txt", frequencies), ("fare_attributes.txt", fare_attributes),
+        ("fare_rules.txt", fare_rules),
+    ] {
+        feed.files.insert(name.to_string(), csv.0);
+    }
+    Ok(feed)
+}
+
+// --- VALIDATION ---
+
+fn table(feed: &GtfsFeed, name: &str) -> Result<(Vec<String>, Vec<Vec<String>>), String> {
+    let content = feed.files.get(name).ok_or_else(|| format!("{}: missing", name))?;
+    let mut lines = content.lines();
+    let header = parse_csv_line(lines.next().ok_or_else(|| format!("{}: empty", name))?);
+    let mut rows = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let row = parse_csv_line(line);
+        if row.len() != header.len() {
+            return Err(format!("{}:{}: expected {} fields, got {}", name, i + 2, header.len(), row.len()));
+        }
+        rows.push(row);
+    }
+    Ok((header, rows))
+}
+
+fn column(header: &[String], file: &str, col: &str) -> Result<usize, String> {
+    header.iter().position(|h| h == col).ok_or_else(|| format!("{}: missing column {}", file, col))
+}
+
+fn ids(feed: &GtfsFeed, file: &str, col: &str) -> Result<HashSet<String>, String> {
+    let (header, rows) = table(feed, file)?;
+    let c = column(&header, file, col)?;
+    let mut set = HashSet::new();
+    for row in rows {
+        if row[c].is_empty() { return Err(format!("{}: empty {}", file, col)); }
+        if !set.insert(row[c].clone()) { return Err(format!("{}: duplicate {} {}", file, col, row[c])); }
+    }
+    Ok(set)
+}
+
+fn is_gtfs_time(s: &str) -> bool {
+    let parts: Vec<&str> = s.split(':').collect();
+    parts.len() == 3
+        && parts.iter().all(|p| p.len() == 2 && p.bytes().all(|b| b.is_ascii_digit()))
+        && parts[1] < "60" && parts[2] < "60"
+}
+
+fn is_gtfs_date(s: &str) -> bool {
+    if s.len() != 8 || !s.bytes().all(|b| b.is_ascii_digit()) { return false; }
+    let (year, month, day): (u32, u32, u32) = (s[..4].parse().unwrap_or(0), s[4..6].parse().unwrap_or(0), s[6..].parse().unwrap_or(0));
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    (1..=days).contains(&day)
+}
+
+fn is_gtfs_color(s: &str) -> bool {
+    s.len() == 6 && s.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Structural checks: required files and columns, unique primary keys,
+/// foreign keys between tables, well-formed times, service dates and route
+/// colours.
+pub fn validate_feed(feed: &GtfsFeed) -> Result<(), String> {
+    for f in REQUIRED_FILES {
+        if !feed.files.contains_key(*f) { return Err(format!("{}: missing", f)); }
+    }
+    let agencies = ids(feed, "agency.txt", "agency_id")?;
+    let stops = ids(feed, "stops.txt", "stop_id")?;
+    let routes = ids(feed, "routes.txt", "route_id")?;
+    let trips = ids(feed, "trips.txt", "trip_id")?;
+    let services = ids(feed, "calendar.txt", "service_id")?;
+
+    let check_fk = |file: &str, col: &str, known: &HashSet<String>| -> Result<(), String> {
+        let (header, rows) = table(feed, file)?;
+        let c = column(&header, file, col)?;
+        match rows.iter().find(|r| !known.contains(&r[c])) {
+            Some(r) => Err(format!("{}: unknown {} {}", file, col, r[c])),
+            None => Ok(()),
+        }
+    };
+    check_fk("routes.txt", "agency_id", &agencies)?;
+    check_fk("trips.txt", "route_id", &routes)?;
+    check_fk("trips.txt", "service_id", &services)?;
+    check_fk("stop_times.txt", "trip_id", &trips)?;
+    check_fk("stop_times.txt", "stop_id", &stops)?;
+
+    let (header, rows) = table(feed, "calendar.txt")?;
+    let (start_c, end_c) = (column(&header, "calendar.txt", "start_date")?, column(&header, "calendar.txt", "end_date")?);
+    for r in &rows {
+        if let Some(d) = [&r[start_c], &r[end_c]].into_iter().find(|d| !is_gtfs_date(d)) {
+            return Err(format!("calendar.txt: bad date {}", d));
+        }
+        if r[start_c] > r[end_c] {
+            return Err(format!("calendar.txt: start_date {} after end_date {}", r[start_c], r[end_c]));
+        }
+    }
+
+    let (header, rows) = table(feed, "routes.txt")?;
+    for col in ["route_color", "route_text_color"] {
+        let Some(c) = header.iter().position(|h| h == col) else { continue };
+        if let Some(r) = rows.iter().find(|r| !r[c].is_empty() && !is_gtfs_color(&r[c])) {
+            return Err(format!("routes.txt: bad {} {}", col, r[c]));
+        }
+    }
+
+    let (header, rows) = table(feed, "stops.txt")?;
+    let (lat_c, lon_c) = (column(&header, "stops.txt", "stop_lat")?, column(&header, "stops.txt", "stop_lon")?);
+    for r in &rows {
+        let lat: f64 = r[lat_c].parse().map_err(|_| format!("stops.txt: bad stop_lat {}", r[lat_c]))?;
+        let lon: f64 = r[lon_c].parse().map_err(|_| format!("stops.txt: bad stop_lon {}", r[lon_c]))?;
+        if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+            return Err(format!("stops.txt: coordinates out of range {} {}", lat, lon));
+        }
+    }
+
+    let (header, rows) = table(feed, "stop_times.txt")?;
+    let (arr_c, seq_c, trip_c) = (
+        column(&header, "stop_times.txt", "arrival_time")?,
+        column(&header, "stop_times.txt", "stop_sequence")?,
+        column(&header, "stop_times.txt", "trip_id")?,
+    );
+    let mut last: HashMap<&str, (u32, &str)> = HashMap::new();
+    let mut served: HashSet<&str> = HashSet::new();
+    for r in &rows {
+        if !is_gtfs_time(&r[arr_c]) { return Err(format!("stop_times.txt: bad time {}", r[arr_c])); }
+        let seq: u32 = r[seq_c].parse().map_err(|_| format!("stop_times.txt: bad stop_sequence {}", r[seq_c]))?;
+        if let Some((prev_seq, prev_time)) = last.get(r[trip_c].as_str()) {
+            if seq <= *prev_seq || r[arr_c].as_str() < *prev_time {
+                return Err(format!("stop_times.txt: trip {} not increasing at sequence {}", r[trip_c], seq));
+            }
+        }
+        last.insert(&r[trip_c], (seq, &r[arr_c]));
+        served.insert(&r[trip_c]);
+    }
+    if let Some(t) = trips.iter().find(|t| !served.contains(t.as_str())) {
+        return Err(format!("trips.txt: trip {} has no stop_times", t));
+    }
+
+    if feed.files.contains_key("frequencies.txt") {
+        check_fk("frequencies.txt", "trip_id", &trips)?;
+        let (header, rows) = table(feed, "frequencies.txt")?;
+        let (s_c, e_c, h_c) = (
+            column(&header, "frequencies.txt", "start_time")?,
+            column(&header, "frequencies.txt", "end_time")?,
+            column(&header, "frequencies.txt", "headway_secs")?,
+        );
+        for r in &rows {
+            if !is_gtfs_time(&r[s_c]) || !is_gtfs_time(&r[e_c]) || r[s_c] >= r[e_c] {
+                return Err(format!("frequencies.txt: bad window {} - {}", r[s_c], r[e_c]));
+            }
+            if r[h_c].parse::<u32>().map_or(true, |h| h == 0) {
+                return Err(format!("frequencies.txt: bad headway_secs {}", r[h_c]));
+            }
+        }
+    }
+
+    if feed.files.contains_key("fare_attributes.txt") {
+        let fares = ids(feed, "fare_attributes.txt", "fare_id")?;
+        check_fk("fare_rules.txt", "fare_id", &fares)?;
+        check_fk("fare_rules.txt", "route_id", &routes)?;
+    }
+    Ok(())
+}
+
+pub fn export_gtfs_core(opts: &GtfsOptions) -> Result<GtfsFeed, String> {
+    opts.validate()?;
+    let db = DB.read().map_err(|_| "Lock failed".to_string())?;
+    let feed = export_gtfs_rs(&db.routes_list, opts)?;
+    validate_feed(&feed)?;
+    Ok(feed)
+}
+
+/// Returns `{ files: { "stops.txt": "...", ... } }` for the loaded catalog.
+#[wasm_bindgen]
+pub fn export_gtfs(start_date: &str, end_date: &str) -> Result<JsValue, JsValue> {
+    let opts = GtfsOptions::new(start_date, end_date).map_err(|e| JsValue::from_str(&e))?;
+    let feed = export_gtfs_core(&opts).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&feed).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_route;
+    use crate::Schedule;
+
+    fn sample_routes() -> Vec<Route> {
+        let mut r1 = mock_route("R1", vec![("Villas Otoch", 21.1685, -86.885), ("El Crucero", 21.1714, -86.8219), ("Zona Hotelera, Km 9", 21.135, -86.768)]);
+        r1.frecuencia_minutos = Some(10);
+        r1.color = Some("#4ECDC4".to_string());
+        r1.horario = Some(Schedule {
+            inicio_oficial: Some("05:00".into()), fin_oficial: Some("22:30".into()),
+            guardia_nocturna: Some("03:00 - 05:00 (Sistema de llenado en OXXO)".into()),
+            ..Default::default()
+        });
+        let mut r2 = mock_route("R2", vec![("El Crucero", 21.1714, -86.8219), ("Puerto Juárez", 21.1860, -86.8070)]);
+        r2.price = 12.0;
+        r2.horario = Some(Schedule { inicio: Some("05:30".into()), fin: Some("00:30".into()), ..Default::default() });
+        r2.stops[1].id = Some("PJ".to_string());
+        vec![r1, r2]
+    }
+
+    #[test]
+    fn test_feed_passes_validation() {
+        let feed = export_gtfs_rs(&sample_routes(), &GtfsOptions::default()).unwrap();
+        assert_eq!(validate_feed(&feed), Ok(()));
+        // "El Crucero" is shared and written once; the quoted name survives.
+        let stops = &feed.files["stops.txt"];
+        assert_eq!(stops.matches("El Crucero").count(), 1);
+        assert!(stops.contains("\"Zona Hotelera, Km 9\""));
+        assert!(stops.contains("PJ,Puerto Juárez"));
+    }
+
+    #[test]
+    fn test_stop_ids_are_stable() {
+        let a = export_gtfs_rs(&sample_routes(), &GtfsOptions::default()).unwrap();
+        let b = export_gtfs_rs(&sample_routes(), &GtfsOptions::default()).unwrap();
+        assert_eq!(a.files["stops.txt"], b.files["stops.txt"]);
+        let stop = &sample_routes()[0].stops[0];
+        assert_eq!(gtfs_stop_id(stop), gtfs_stop_id(&stop.clone()));
+        assert!(gtfs_stop_id(stop).starts_with('S'));
+    }
+
+    #[test]
+    fn test_frequencies_and_fares() {
+        let feed = export_gtfs_rs(&sample_routes(), &GtfsOptions::default()).unwrap();
+        let freq = &feed.files["frequencies.txt"];
+        assert!(freq.contains("R1_T0,05:00:00,22:30:00,600,0"));
+        assert!(freq.contains("R1_T0,03:00:00,05:00:00,1200,0"));
+        // Service past midnight is expressed as 24:30:00.
+        assert!(freq.contains("R2_T0,05:30:00,24:30:00,900,0"));
+        let fares = &feed.files["fare_attributes.txt"];
+        assert!(fares.contains("MXN_10_00,10.00,MXN,0,0"));
+        assert!(fares.contains("MXN_12_00,12.00,MXN,0,0"));
+        assert!(feed.files["routes.txt"].contains("R1,A1,,R1,3,4ECDC4"));
+    }
+
+    #[test]
+    fn test_validation_catches_broken_references() {
+        let mut feed = export_gtfs_rs(&sample_routes(), &GtfsOptions::default()).unwrap();
+        feed.files.get_mut("stop_times.txt").unwrap().push_str("R1_T0,09:00:00,09:00:00,NOPE,9\n");
+        assert!(validate_feed(&feed).unwrap_err().contains("unknown stop_id NOPE"));
+    }
+
+    #[test]
+    fn test_route_colors() {
+        let mut routes = sample_routes();
+        routes[1].color = Some("red".to_string());
+        let feed = export_gtfs_rs(&routes, &GtfsOptions::default()).unwrap();
+        assert!(feed.files["routes.txt"].contains("R2,A1,,R2,3,\n"));
+        assert_eq!(validate_feed(&feed), Ok(()));
+
+        let mut bad = feed.clone();
+        let csv = bad.files.get_mut("routes.txt").unwrap();
+        *csv = csv.replace("4ECDC4", "#4ECDC");
+        assert!(validate_feed(&bad).unwrap_err().contains("bad route_color #4ECDC"));
+
+        let mut bad = feed.clone();
+        let csv = bad.files.get_mut("routes.txt").unwrap();
+        *csv = csv.replacen("route_color\n", "route_color,route_text_color\n", 1)
+            .replace("4ECDC4\n", "4ECDC4,FFFFFF\n").replace(",3,\n", ",3,,GG0000\n");
+        assert!(validate_feed(&bad).unwrap_err().contains("bad route_text_color GG0000"));
+    }
+
+    #[test]
+    fn test_service_dates() {
+        assert!(GtfsOptions::new("20260101", "20261231").is_ok());
+        assert!(GtfsOptions::new("20240229", "20240301").is_ok());
+        assert!(GtfsOptions::new("2026-01-01", "20261231").unwrap_err().starts_with("start_date"));
+        assert!(GtfsOptions::new("20260101", "20250230").unwrap_err().starts_with("end_date"));
+        assert!(GtfsOptions::new("20261231", "20260101").unwrap_err().contains("after"));
+
+        let mut feed = export_gtfs_rs(&sample_routes(), &GtfsOptions::default()).unwrap();
+        let csv = feed.files.get_mut("calendar.txt").unwrap();
+        *csv = csv.replace("20261231", "20261301");
+        assert!(validate_feed(&feed).unwrap_err().contains("bad date 20261301"));
+    }
+
+    #[test]
+    fn test_conflicting_stop_ids() {
+        let mut routes = sample_routes();
+        // The same explicit id on the same stop is fine...
+        routes[0].stops[2].id = Some("KM9".to_string());
+        let km9 = routes[0].stops[2].clone();
+        routes[1].stops.push(km9);
+        assert!(export_gtfs_rs(&routes, &GtfsOptions::default()).is_ok());
+        // ...but not on a different one
+        routes[1].stops[0].id = Some("KM9".to_string());
+        let err = export_gtfs_rs(&routes, &GtfsOptions::default()).unwrap_err();
+        assert!(err.contains("stop_id KM9") && err.contains("El Crucero"), "{}", err);
+    }
+}
@@ -0,0 +1,227 @@
+//! Last-connection checks for late-night journeys.
+//!
+//! Each leg of the itinerary is compared with its route's service window: a
+//! leg is at risk when fewer than one headway is left before the last
+//! departure, and missed when the rider reaches the stop after service has
+//! ended. Missed connections come with ride-hailing fallbacks from that stop
+//! to the destination.
+
+use serde::{Deserialize, Serialize};
+use shared_types::circuity::CircuityModel;
+use shared_types::fares::FareTable;
+use shared_types::{TrafficConditions, TransportType};
+
+use crate::clock::{self, format_hhmm};
+use crate::schedule::{service_window, Service};
+use crate::timing::{headway_min, TimedLeg};
+use crate::{leg_stop_indices, Journey, Route, RouteLeg, Stop};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ConnectionWarning {
+    pub leg_index: usize,
+    pub route_id: String,
+    /// End of service, `HH:MM` Cancún.
+    pub last_departure: String,
+    pub at_stop: String,
+    /// Minutes between the expected boarding and the last departure.
+    pub margin_minutes: i32,
+    /// Service had already ended when the rider reaches the stop.
+    pub missed: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FallbackLeg {
+    pub mode: TransportType,
+    pub from_stop: String,
+    pub to_stop: String,
+    pub distance_m: f64,
+    pub minutes: u32,
+    /// Estimate, MXN.
+    pub fare_mxn: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LastConnection {
+    pub warnings: Vec<ConnectionWarning>,
+    /// Leaving by this time keeps a full headway of margin on every leg.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_safe_departure: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latest_safe_departure_s: Option<i64>,
+    /// Cheapest first; only when a connection is missed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback: Vec<FallbackLeg>,
+}
+
+fn leg_stop<'a>(routes: &'a [Route], leg: &RouteLeg, origin: bool) -> Option<&'a Stop> {
+    let route = routes.iter().find(|r| r.id == leg.route_id)?;
+    let (a, b) = leg_stop_indices(route, leg)?;
+    route.stops.get(if origin { a } else { b })
+}
+
+/// Ride-hailing from `from` to `to` leaving at `depart_s`.
+pub fn ride_hail_options(from: &Stop, to: &Stop, circuity: &CircuityModel, fares: &FareTable, depart_s: i64) -> Vec<FallbackLeg> {
+    let traffic = TrafficConditions::from_hour(clock::cancun_hour(depart_s));
+    let mut out: Vec<FallbackLeg> = [TransportType::Indriver, TransportType::Uber].into_iter()
+        .map(|mode| {
+            let (a, b) = ([from.lat, from.lng], [to.lat, to.lng]);
+            let distance_m = circuity.road_distance_m(&mode, a, b);
+            let speed = mode.avg_speed_kmh() / traffic.total_delay_factor();
+            let minutes = distance_m / 1000.0 / speed * 60.0;
+            let fare = fares.fare(&mode, a, b, distance_m, minutes).unwrap_or_else(|| mode.base_fare());
+            FallbackLeg {
+                from_stop: from.name.clone(),
+                to_stop: to.name.clone(),
+                distance_m,
+                minutes: minutes.ceil() as u32,
+                fare_mxn: (fare / 5.0).ceil() * 5.0,
+                mode,
+            }
+        })
+        .collect();
+    out.sort_by(|a, b| a.fare_mxn.total_cmp(&b.fare_mxn));
+    out
+}
+
+/// Minutes left in the service window at `leg.at_stop`, negative once it
+/// ended. `None` before the first departure of the day.
+fn minutes_left(route: &Route, leg: &TimedLeg) -> Option<i32> {
+    let (start, end) = service_window(route);
+    let m = clock::cancun_minute_of_day(leg.at_stop_s);
+    if leg.service != Service::NotRunning {
+        let m = if m < start { m + 1440 } else { m };
+        return Some(end as i32 - m as i32);
+    }
+    let since_end = (m + 1440 - end % 1440) % 1440;
+    let until_start = (start + 1440 - m) % 1440;
+    (since_end < until_start).then(|| -(since_end as i32))
+}
+
+/// Warnings for `journey` as placed by its itinerary; `None` when every leg
+/// has at least a headway of margin.
+pub fn check_last_connection(
+    journey: &Journey, routes: &[Route], circuity: &CircuityModel, fares: &FareTable,
+) -> Option<LastConnection> {
+    let itinerary = journey.itinerary.as_ref()?;
+    let mut warnings = Vec::new();
+    let mut latest_safe: Option<i64> = None;
+
+    for (i, (leg, timed)) in journey.legs.iter().zip(&itinerary.legs).enumerate() {
+        let Some(route) = routes.iter().find(|r| r.id == leg.route_id) else { continue };
+        // Night guard service is its own warning, see arrive-by.
+        if timed.service == Service::NightGuard { continue; }
+        let Some(left) = minutes_left(route, timed) else { continue };
+        let headway = headway_min(route, Service::Regular) as i32;
+
+        let end_s = timed.at_stop_s + left as i64 * 60;
+        let safe = end_s - headway as i64 * 60 - (timed.at_stop_s - itinerary.depart_s);
+        latest_safe = Some(latest_safe.map_or(safe, |s| s.min(safe)));
+
+        if left < headway {
+            warnings.push(ConnectionWarning {
+                leg_index: i,
+                route_id: route.id.clone(),
+                last_departure: format_hhmm(clock::cancun_minute_of_day(end_s)),
+                at_stop: timed.at_stop.clone(),
+                margin_minutes: left - timed.wait_minutes as i32,
+                missed: left <= 0,
+            });
+        }
+    }
+    if warnings.is_empty() { return None; }
+
+    let fallback = match warnings.iter().find(|w| w.missed) {
+        Some(w) => {
+            let from = leg_stop(routes, &journey.legs[w.leg_index], true);
+            let to = journey.legs.last().and_then(|l| leg_stop(routes, l, false));
+            match (from, to) {
+                (Some(from), Some(to)) => ride_hail_options(from, to, circuity, fares, itinerary.legs[w.leg_index].at_stop_s),
+                _ => Vec::new(),
+            }
+        }
+        None => Vec::new(),
+    };
+    Some(LastConnection {
+        warnings,
+        latest_safe_departure: latest_safe.map(|t| format_hhmm(clock::cancun_minute_of_day(t))),
+        latest_safe_departure_s: latest_safe,
+        fallback,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::parse_datetime;
+    use crate::tests::mock_route;
+    use crate::timing::schedule_forward;
+    use crate::{find_route_rs, Schedule};
+
+    fn network() -> Vec<Route> {
+        let mut r1 = mock_route("R1", vec![("Villas Otoch", 21.1619, -86.8515), ("El Crucero", 21.1714, -86.8219)]);
+        r1.transport_type = "Bus_Urban".to_string();
+        r1.frecuencia_minutos = Some(10);
+        r1.horario = Some(Schedule { inicio: Some("05:00".into()), fin: Some("00:30".into()), ..Default::default() });
+        let mut r2 = mock_route("R2", vec![("El Crucero", 21.1714, -86.8219), ("Zona Hotelera", 21.135, -86.768)]);
+        r2.transport_type = "Bus_HotelZone".to_string();
+        r2.frecuencia_minutos = Some(20);
+        r2.horario = Some(Schedule { inicio: Some("06:00 - 22:30".into()), ..Default::default() });
+        vec![r1, r2]
+    }
+
+    fn check(routes: &[Route], depart: &str) -> Option<LastConnection> {
+        check_at(routes, parse_datetime(depart).unwrap())
+    }
+
+    fn check_at(routes: &[Route], depart_s: i64) -> Option<LastConnection> {
+        let mut journey = find_route_rs("Villas Otoch", "Zona Hotelera", routes).remove(0);
+        let circuity = CircuityModel::default();
+        journey.itinerary = schedule_forward(&journey, routes, &circuity, depart_s);
+        check_last_connection(&journey, routes, &circuity, &FareTable::default())
+    }
+
+    #[test]
+    fn test_evening_journey_is_fine() {
+        assert_eq!(check(&network(), "2026-05-12T19:00"), None);
+        // Before the first bus is a wait, not a missed connection.
+        assert_eq!(check(&network(), "2026-05-12T05:30"), None);
+    }
+
+    #[test]
+    fn test_late_transfer_is_flagged_with_fallback() {
+        let routes = network();
+        let risky = check(&routes, "2026-05-12T22:00").unwrap();
+        assert_eq!(risky.warnings.len(), 1);
+        assert_eq!(risky.warnings[0].route_id, "R2");
+        assert_eq!(risky.warnings[0].last_departure, "22:30");
+        assert!(!risky.warnings[0].missed);
+        assert!(risky.fallback.is_empty());
+        let safe = risky.latest_safe_departure_s.unwrap();
+        assert!(safe < parse_datetime("2026-05-12T22:00").unwrap());
+        assert_eq!(check_at(&routes, safe), None);
+
+        let missed = check(&routes, "2026-05-12T23:00").unwrap();
+        assert!(missed.warnings[0].missed);
+        assert_eq!(missed.fallback.len(), 2);
+        assert_eq!(missed.fallback[0].mode, TransportType::Indriver);
+        assert_eq!(missed.fallback[0].from_stop, "El Crucero");
+        assert_eq!(missed.fallback[0].to_stop, "Zona Hotelera");
+        assert!(missed.fallback[0].fare_mxn > TransportType::Indriver.base_fare());
+    }
+
+    #[test]
+    fn test_fallback_uses_loaded_fares() {
+        let routes = network();
+        let (from, to) = (&routes[1].stops[0], &routes[1].stops[1]);
+        let fares: FareTable = serde_json::from_str(r#"{"modes": {"Uber": {"base": 0, "per_km": 0, "minimum": 400}}}"#).unwrap();
+        let depart = parse_datetime("2026-05-12T23:00").unwrap();
+        let circuity = CircuityModel::default();
+        let out = ride_hail_options(from, to, &circuity, &fares, depart);
+        let fare = |legs: &[FallbackLeg], mode| legs.iter().find(|f| f.mode == mode).unwrap().fare_mxn;
+        assert_eq!(fare(&out, TransportType::Uber), 400.0);
+        // Indriver keeps its default rate card, not the flat base fare
+        let defaults = ride_hail_options(from, to, &circuity, &FareTable::default(), depart);
+        assert_eq!(fare(&out, TransportType::Indriver), fare(&defaults, TransportType::Indriver));
+        assert!(fare(&out, TransportType::Indriver) > TransportType::Indriver.base_fare());
+    }
+}
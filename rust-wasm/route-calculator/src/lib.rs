@@ -1,14 +1,38 @@
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::sync::RwLock;
 
+use shared_types::circuity::CircuityModel;
+use shared_types::exclusions::Exclusions;
+use shared_types::fares::FareTable;
+pub use shared_types::schedule::Schedule;
+use shared_types::{normalize, polyline, shape};
+
 use wasm_bindgen::prelude::*;
 
+use normalize::normalize_str;
+
+pub mod alerts;
+pub mod arrive_by;
+pub mod aliases;
+pub mod clock;
+pub mod departures;
+pub mod ferry;
+pub mod geojson;
+pub mod gtfs;
+pub mod last_connection;
+pub mod realtime;
+pub mod schedule;
+pub mod stations;
+pub mod timing;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RouteCatalog {
     pub version: String,
     pub rutas: Vec<Route>,
+    #[serde(default)]
+    pub service_alerts: Vec<alerts::ServiceAlert>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -20,6 +44,8 @@ pub struct Route {
     pub price: f64,
     #[serde(rename = "tipo", alias = "tipo_transporte")]
     pub transport_type: String,
+    #[serde(default)]
+    pub color: Option<String>,
 
     #[serde(default)]
     pub empresa: Option<String>,
@@ -30,12 +56,26 @@ pub struct Route {
 
     #[serde(rename = "paradas")]
     pub stops: Vec<Stop>,
+    /// Traced geometry `[lat, lng]`, independent from the stop list.
+    #[serde(default, alias = "trazado", skip_serializing_if = "Option::is_none")]
+    pub shape: Option<Vec<[f64; 2]>>,
 
     // Computed fields (skipped in serialization)
     #[serde(skip)]
     pub stops_normalized: Vec<String>,
     #[serde(skip)]
     pub stop_name_to_index: HashMap<String, usize>,
+    /// Word set of each stop name, for matching regardless of word order.
+    #[serde(skip)]
+    pub stop_tokens: Vec<BTreeSet<String>>,
+    /// Offset of each stop along `shape`, meters. Empty without a shape.
+    #[serde(skip)]
+    pub stop_offsets_m: Vec<f64>,
+    /// Station id of each stop, see `stations`. Empty until the catalog is clustered.
+    #[serde(skip)]
+    pub station_ids: Vec<String>,
+    #[serde(skip)]
+    pub station_to_index: HashMap<String, usize>,
 
     #[serde(default, alias = "advertencias_usuario")]
     pub social_alerts: Vec<String>,
@@ -43,15 +83,6 @@ pub struct Route {
     pub last_updated: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
-pub struct Schedule {
-    pub inicio: Option<String>,
-    pub fin: Option<String>,
-    pub inicio_oficial: Option<String>,
-    pub fin_oficial: Option<String>,
-    pub guardia_nocturna: Option<String>,
-}
-
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Stop {
     #[serde(default)]
@@ -77,6 +108,26 @@ pub struct Journey {
     pub geo_transfer: bool,
     #[serde(default)]
     pub is_forward: bool,
+    /// Set when the origin query was resolved through `aliases`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin_alias: Option<aliases::AliasMatch>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dest_alias: Option<aliases::AliasMatch>,
+    /// Active alerts that touch any leg, see `alerts`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alerts: Vec<alerts::ServiceAlert>,
+    /// Extra minutes from active delay alerts.
+    #[serde(default)]
+    pub delay_minutes: u32,
+    /// Exclusions the journey was planned under, see `find_route_excluding`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avoided: Option<Exclusions>,
+    /// Clock times when leaving at plan time, see `timing`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub itinerary: Option<timing::Itinerary>,
+    /// Legs close to or past their last departure, see `last_connection`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_connection: Option<last_connection::LastConnection>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -86,49 +137,41 @@ pub struct RouteLeg {
     pub origin_stop: String,
     pub dest_stop: String,
     pub price: f64,
+    /// Along-shape when the route has one, stop-to-stop otherwise.
+    #[serde(default)]
+    pub distance_m: f64,
+    /// Leg geometry as a Google encoded polyline, see `find_route_encoded`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub polyline: Option<String>,
 }
 
 // --- APP STATE ---
 
 struct AppState {
     routes_list: Vec<Route>,
+    stations: Vec<stations::Station>,
+    aliases: aliases::AliasTable,
+    alerts: Vec<alerts::ServiceAlert>,
+    vehicles: realtime::VehicleStore,
+    ferries: shared_types::ferry::FerryTimetable,
+    circuity: CircuityModel,
+    fares: FareTable,
 }
 
 static DB: Lazy<RwLock<AppState>> = Lazy::new(|| {
     RwLock::new(AppState {
         routes_list: Vec::new(),
+        stations: Vec::new(),
+        aliases: aliases::AliasTable::default(),
+        alerts: Vec::new(),
+        vehicles: realtime::VehicleStore::default(),
+        ferries: shared_types::ferry::FerryTimetable::default(),
+        circuity: CircuityModel::default(),
+        fares: FareTable::default(),
     })
 });
 
 
-// --- NORMALIZATION ---
-
-fn normalize_str(s: &str) -> String {
-    let mut result = s.trim().to_lowercase();
-    result = result.replace('á', "a");
-    result = result.replace('é', "e");
-    result = result.replace('í', "i");
-    result = result.replace('ó', "o");
-    result = result.replace('ú', "u");
-    result = result.replace('ü', "u");
-    result = result.replace('ñ', "n");
-
-    let mut collapsed = String::with_capacity(result.len());
-    let mut last_was_whitespace = false;
-    for c in result.chars() {
-        if c.is_whitespace() {
-            if !last_was_whitespace {
-                collapsed.push(' ');
-                last_was_whitespace = true;
-            }
-        } else {
-            collapsed.push(c);
-            last_was_whitespace = false;
-        }
-    }
-    collapsed.trim().to_string()
-}
-
 // --- HAVERSINE DISTANCE ---
 
 #[inline]
@@ -165,19 +208,90 @@ pub fn validate_catalog(catalog: &RouteCatalog) -> Result<(), String> {
     Ok(())
 }
 
+/// Fills the computed fields of a route.
+fn prepare_route(route: &mut Route) {
+    route.stops_normalized = route.stops.iter().map(|s| normalize_str(&s.name)).collect();
+    route.stop_name_to_index = route.stops_normalized.iter().enumerate()
+        .map(|(i, n)| (n.clone(), i)).collect();
+    route.stop_tokens = route.stops.iter().map(|s| normalize::token_set(&s.name)).collect();
+    project_route_stops(route);
+}
+
+/// Projects each stop onto the route shape. Stops are kept off the shape
+/// when the projection fails, and distances then fall back to haversine.
+fn project_route_stops(route: &mut Route) {
+    route.stop_offsets_m.clear();
+    let Some(shape) = route.shape.as_deref().filter(|s| s.len() >= 2) else { return };
+    let points: Vec<[f64; 2]> = route.stops.iter().map(|s| [s.lat, s.lng]).collect();
+    let proj = shape::project_stops(shape, &points);
+    if proj.len() == points.len() && proj.iter().all(|p| p.off_shape_m <= shape::MAX_STOP_OFF_SHAPE_M) {
+        route.stop_offsets_m = proj.iter().map(|p| p.offset_m).collect();
+    }
+}
+
+/// Distance travelled on `route` between two stop indices, meters.
+pub fn leg_distance_m(route: &Route, from: usize, to: usize) -> f64 {
+    if route.stop_offsets_m.len() == route.stops.len() {
+        return (route.stop_offsets_m[to] - route.stop_offsets_m[from]).abs();
+    }
+    let (lo, hi) = if from <= to { (from, to) } else { (to, from) };
+    route.stops[lo..=hi].windows(2)
+        .map(|w| haversine_distance_m(w[0].lat, w[0].lng, w[1].lat, w[1].lng))
+        .sum()
+}
+
+/// Coordinates `[lat, lng]` travelled on `route` between two stop indices:
+/// the slice of the shape when stops are projected on it, the stops otherwise.
+pub fn leg_coords(route: &Route, from: usize, to: usize) -> Vec<[f64; 2]> {
+    if let Some(shape) = route.shape.as_deref() {
+        if route.stop_offsets_m.len() == route.stops.len() {
+            return shape::slice_shape(shape, route.stop_offsets_m[from], route.stop_offsets_m[to]);
+        }
+    }
+    let pick = |s: &Stop| [s.lat, s.lng];
+    if from <= to {
+        route.stops[from..=to].iter().map(pick).collect()
+    } else {
+        route.stops[to..=from].iter().rev().map(pick).collect()
+    }
+}
+
+/// Attaches a traced shape (e.g. from `routes/POLYLINE_*.json`) to a loaded route.
+pub fn set_route_shape_core(route_id: &str, shape: Vec<[f64; 2]>) -> Result<(), String> {
+    if shape.len() > 20_000 { return Err("Shape too large".to_string()); }
+    let mut db = DB.write().map_err(|_| "Lock failed".to_string())?;
+    let route = db.routes_list.iter_mut().find(|r| r.id == route_id)
+        .ok_or_else(|| format!("Unknown route {}", route_id))?;
+    route.shape = Some(shape);
+    project_route_stops(route);
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn set_route_shape(route_id: &str, shape_js: JsValue) -> Result<(), JsValue> {
+    let shape: Vec<[f64; 2]> = serde_wasm_bindgen::from_value(shape_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    set_route_shape_core(route_id, shape).map_err(|e| JsValue::from_str(&e))
+}
+
 pub fn load_catalog_core(json_payload: &str) -> Result<(), String> {
     if json_payload.len() > 10 * 1024 * 1024 { return Err("Payload too large".to_string()); }
     let mut catalog: RouteCatalog = serde_json::from_str(json_payload).map_err(|e| e.to_string())?;
     validate_catalog(&catalog)?;
+    for alert in &catalog.service_alerts {
+        alert.validate()?;
+    }
 
     for route in &mut catalog.rutas {
-        route.stops_normalized = route.stops.iter().map(|s| normalize_str(&s.name)).collect();
-        route.stop_name_to_index = route.stops_normalized.iter().enumerate()
-            .map(|(i, n)| (n.clone(), i)).collect();
+        prepare_route(route);
     }
+    let station_list = stations::cluster_stations(&catalog.rutas);
+    stations::assign_stations(&mut catalog.rutas, &station_list);
 
     let mut db = DB.write().map_err(|_| "Lock failed".to_string())?;
     db.routes_list = catalog.rutas;
+    db.stations = station_list;
+    db.alerts = catalog.service_alerts;
     Ok(())
 }
 
@@ -186,30 +300,234 @@ pub fn load_catalog(json_payload: &str) -> Result<(), JsValue> {
     load_catalog_core(json_payload).map_err(|e| JsValue::from_str(&e))
 }
 
+pub fn load_circuity_core(json: &str) -> Result<usize, String> {
+    let model: CircuityModel = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    model.validate()?;
+    let n = model.zones.len();
+    DB.write().map_err(|_| "Lock failed".to_string())?.circuity = model;
+    Ok(n)
+}
+
+/// Replaces the circuity model used for transfer walks and ride-hailing
+/// fallbacks (same shape as in `eta-engine`). Returns how many zones it
+/// holds.
+#[wasm_bindgen]
+pub fn load_circuity(json: &str) -> Result<usize, JsValue> {
+    load_circuity_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+pub fn load_fares_core(json: &str) -> Result<usize, String> {
+    let table: FareTable = serde_json::from_str(json).map_err(|e| e.to_string())?;
+    table.validate()?;
+    let n = table.modes.len();
+    DB.write().map_err(|_| "Lock failed".to_string())?.fares = table;
+    Ok(n)
+}
+
+/// Replaces the fare table priced into ride-hailing fallbacks (same shape
+/// as in `eta-engine`). Returns how many rate cards it holds.
+#[wasm_bindgen]
+pub fn load_fares(json: &str) -> Result<usize, JsValue> {
+    load_fares_core(json).map_err(|e| JsValue::from_str(&e))
+}
+
+/// Non-fatal problems in the loaded catalog.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct CatalogLint {
+    pub unparseable_schedules: Vec<schedule::ScheduleIssue>,
+}
+
+pub fn lint_catalog_rs(routes: &[Route]) -> CatalogLint {
+    CatalogLint { unparseable_schedules: schedule::schedule_issues(routes) }
+}
+
+#[wasm_bindgen]
+pub fn lint_catalog() -> Result<JsValue, JsValue> {
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    serde_wasm_bindgen::to_value(&lint_catalog_rs(&db.routes_list)).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
 #[wasm_bindgen]
 pub fn find_route(origin: &str, dest: &str) -> Result<JsValue, JsValue> {
     let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
-    let journeys = find_route_rs(origin, dest, &db.routes_list);
+    let journeys = plan_journeys(&db, origin, dest, clock::now_unix_s(), &Exclusions::default());
+    serde_wasm_bindgen::to_value(&journeys).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn find_route_encoded(origin: &str, dest: &str, precision: u32) -> Result<JsValue, JsValue> {
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    let mut journeys = plan_journeys(&db, origin, dest, clock::now_unix_s(), &Exclusions::default());
+    encode_leg_polylines(&mut journeys, &db.routes_list, precision).map_err(|e| JsValue::from_str(&e))?;
+    serde_wasm_bindgen::to_value(&journeys).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Like `find_route`, leaving at `depart_at` (ISO 8601, Cancún time when it
+/// has no offset) instead of now.
+#[wasm_bindgen]
+pub fn find_route_at(origin: &str, dest: &str, depart_at: &str) -> Result<JsValue, JsValue> {
+    let depart = clock::parse_datetime(depart_at).map_err(|e| JsValue::from_str(&e))?;
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    let journeys = plan_journeys(&db, origin, dest, depart, &Exclusions::default());
     serde_wasm_bindgen::to_value(&journeys).map_err(|e| JsValue::from_str(&e.to_string()))
 }
 
+/// Like `find_route`, keeping the given stops, routes and area out of the plan.
+#[wasm_bindgen]
+pub fn find_route_excluding(origin: &str, dest: &str, exclusions_js: JsValue) -> Result<JsValue, JsValue> {
+    let exclusions: Exclusions = serde_wasm_bindgen::from_value(exclusions_js)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+    exclusions.validate().map_err(|e| JsValue::from_str(&e))?;
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    let journeys = plan_journeys(&db, origin, dest, clock::now_unix_s(), &exclusions);
+    serde_wasm_bindgen::to_value(&journeys).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Alias resolution, routing around closures and alert effects, as of `now_s`.
+fn plan_journeys(db: &AppState, origin: &str, dest: &str, now_s: i64, exclusions: &Exclusions) -> Vec<Journey> {
+    let active = alerts::active_alerts(&db.alerts, now_s);
+    let mut closures = alerts::closures(&active, &db.routes_list);
+    closures.exclude(exclusions, &db.routes_list);
+    let mut journeys = aliases::find_route_aliased(origin, dest, &db.routes_list, &db.stations, &db.aliases, &closures);
+    if !exclusions.is_empty() {
+        avoid_area(&mut journeys, exclusions, &db.routes_list);
+        for j in &mut journeys {
+            j.avoided = Some(exclusions.clone());
+        }
+    }
+    alerts::apply_alerts(&mut journeys, &active, &db.routes_list);
+    for j in &mut journeys {
+        j.itinerary = timing::schedule_forward(j, &db.routes_list, &db.circuity, now_s);
+        j.last_connection = last_connection::check_last_connection(j, &db.routes_list, &db.circuity, &db.fares);
+    }
+    journeys
+}
+
+/// Stop indices of a leg on its route.
+pub fn leg_stop_indices(route: &Route, leg: &RouteLeg) -> Option<(usize, usize)> {
+    let from = *route.stop_name_to_index.get(&normalize_str(&leg.origin_stop))?;
+    let to = *route.stop_name_to_index.get(&normalize_str(&leg.dest_stop))?;
+    Some((from, to))
+}
+
+/// Index of `stop` on `route`, by name (any word order) or station id.
+pub fn find_stop_on_route(route: &Route, stop: &str) -> Option<usize> {
+    if let Some(&i) = route.stop_name_to_index.get(&normalize_str(stop)) {
+        return Some(i);
+    }
+    let tokens = normalize::token_set(stop);
+    if !tokens.is_empty() {
+        if let Some(i) = route.stop_tokens.iter().position(|t| *t == tokens) {
+            return Some(i);
+        }
+    }
+    route.station_to_index.get(stop).copied()
+}
+
+/// Drops journeys with a leg that rides through the avoided area, even
+/// without stopping in it.
+fn avoid_area(journeys: &mut Vec<Journey>, exclusions: &Exclusions, routes: &[Route]) {
+    if exclusions.avoid_area.is_none() { return; }
+    let m = exclusions.matcher();
+    journeys.retain(|j| !j.legs.iter().any(|leg| {
+        let Some(route) = routes.iter().find(|r| r.id == leg.route_id) else { return false };
+        leg_stop_indices(route, leg).is_some_and(|(from, to)| m.path_crosses_area(&leg_coords(route, from, to)))
+    }));
+}
+
+/// Fills `RouteLeg.polyline` so the client gets leg geometry without
+/// shipping coordinate arrays across the WASM boundary.
+pub fn encode_leg_polylines(journeys: &mut [Journey], routes: &[Route], precision: u32) -> Result<(), String> {
+    for leg in journeys.iter_mut().flat_map(|j| j.legs.iter_mut()) {
+        let Some(route) = routes.iter().find(|r| r.id == leg.route_id) else { continue };
+        let from = route.stop_name_to_index.get(&normalize_str(&leg.origin_stop));
+        let to = route.stop_name_to_index.get(&normalize_str(&leg.dest_stop));
+        if let (Some(&from), Some(&to)) = (from, to) {
+            leg.polyline = Some(polyline::encode(&leg_coords(route, from, to), precision)?);
+        }
+    }
+    Ok(())
+}
+
 // --- ROUTING ENGINE ---
 
+/// Stops that may not be used to board, alight or transfer, and routes
+/// that may not be used at all. Filled from active alerts and from
+/// query-time exclusions; the catalog itself is never edited.
+#[derive(Default, Clone, Debug)]
+pub struct Closures {
+    pub routes: HashSet<String>,
+    pub stops: HashMap<String, HashSet<usize>>,
+}
+
+impl Closures {
+    pub fn close_route(&mut self, route_id: &str) {
+        self.routes.insert(route_id.to_string());
+    }
+
+    pub fn close_stop(&mut self, route_id: &str, idx: usize) {
+        self.stops.entry(route_id.to_string()).or_default().insert(idx);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty() && self.stops.values().all(|s| s.is_empty())
+    }
+
+    /// Adds query-time exclusions: listed routes, and stops listed by name,
+    /// id or station id or lying inside the avoided area.
+    pub fn exclude(&mut self, exclusions: &Exclusions, routes: &[Route]) {
+        if exclusions.is_empty() { return; }
+        let m = exclusions.matcher();
+        for route in routes {
+            if m.excludes_route(&route.id) {
+                self.close_route(&route.id);
+                continue;
+            }
+            for (i, stop) in route.stops.iter().enumerate() {
+                let ids = [stop.id.as_deref().unwrap_or(""), route.station_ids.get(i).map_or("", |s| s.as_str())];
+                if m.excludes_stop(&ids, &stop.name, stop.lat, stop.lng) {
+                    self.close_stop(&route.id, i);
+                }
+            }
+        }
+    }
+}
+
 struct RouteMatch<'a> {
     route: &'a Route,
     origin_idx: Option<usize>,
     dest_idx: Option<usize>,
+    closed: Option<&'a HashSet<usize>>,
+}
+
+impl RouteMatch<'_> {
+    fn is_open(&self, idx: usize) -> bool {
+        self.closed.is_none_or(|c| !c.contains(&idx))
+    }
 }
 
-fn find_route_rs(origin: &str, dest: &str, all_routes: &[Route]) -> Vec<Journey> {
+pub fn find_route_rs(origin: &str, dest: &str, all_routes: &[Route]) -> Vec<Journey> {
+    find_route_closed_rs(origin, dest, all_routes, &Closures::default())
+}
+
+pub fn find_route_closed_rs(origin: &str, dest: &str, all_routes: &[Route], closures: &Closures) -> Vec<Journey> {
     let origin_norm = normalize_str(origin);
     let dest_norm = normalize_str(dest);
+    let (origin_tokens, dest_tokens) = (normalize::token_set(origin), normalize::token_set(dest));
 
     let mut route_matches = Vec::with_capacity(all_routes.len());
     for route in all_routes {
-        let origin_idx = route.stop_name_to_index.get(&origin_norm).cloned();
-        let dest_idx = route.stop_name_to_index.get(&dest_norm).cloned();
-        route_matches.push(RouteMatch { route, origin_idx, dest_idx });
+        if closures.routes.contains(&route.id) { continue; }
+        let closed = closures.stops.get(&route.id);
+        let is_open = |i: &usize| closed.is_none_or(|c| !c.contains(i));
+        // Exact key first, then the same words in any order.
+        let lookup = |norm: &String, tokens: &BTreeSet<String>| {
+            route.stop_name_to_index.get(norm).cloned()
+                .or_else(|| route.stop_tokens.iter().position(|t| t == tokens))
+        };
+        let origin_idx = lookup(&origin_norm, &origin_tokens).filter(is_open);
+        let dest_idx = lookup(&dest_norm, &dest_tokens).filter(is_open);
+        route_matches.push(RouteMatch { route, origin_idx, dest_idx, closed });
     }
 
     let mut journeys = find_direct_routes(&route_matches);
@@ -255,11 +573,20 @@ fn find_direct_routes(matches: &[RouteMatch]) -> Vec<Journey> {
                     origin_stop: m.route.stops[oi].name.clone(),
                     dest_stop: m.route.stops[di].name.clone(),
                     price: m.route.price,
+                    distance_m: leg_distance_m(m.route, oi, di),
+                    polyline: None,
                 }],
                 total_price: m.route.price,
                 transfer_point: None,
                 geo_transfer: false,
                 is_forward: oi < di,
+                origin_alias: None,
+                dest_alias: None,
+                alerts: Vec::new(),
+                delay_minutes: 0,
+                avoided: None,
+                itinerary: None,
+                last_connection: None,
             });
         }
     }
@@ -281,22 +608,36 @@ fn find_transfer_routes(matches: &[RouteMatch], mut ops: usize, existing: usize)
             // 1. Exact Name Transfer
             for (idx_a, stop_norm_a) in ma.route.stops_normalized.iter().enumerate() {
                 ops += 1; if ops > MAX_OPS { break 'outer; }
-                if idx_a == oi_a { continue; }
+                if idx_a == oi_a || !ma.is_open(idx_a) { continue; }
 
                 if let Some(&idx_b) = mb.route.stop_name_to_index.get(stop_norm_a) {
-                    if idx_b == di_b { continue; }
+                    if idx_b == di_b || !mb.is_open(idx_b) { continue; }
                     journeys.push(create_transfer_journey(ma, mb, idx_a, idx_b, &ma.route.stops[idx_a].name, false));
                     if journeys.len() >= MAX_SEARCH_RESULTS { break 'outer; }
                     continue 'outer;
                 }
             }
 
-            // 2. Geo Proximity Transfer
+            // 2. Same Station Transfer (different names, same physical stop)
+            for (idx_a, station) in ma.route.station_ids.iter().enumerate() {
+                ops += 1; if ops > MAX_OPS { break 'outer; }
+                if idx_a == oi_a || station.is_empty() || !ma.is_open(idx_a) { continue; }
+
+                if let Some(&idx_b) = mb.route.station_to_index.get(station) {
+                    if idx_b == di_b || !mb.is_open(idx_b) { continue; }
+                    let tp = format!("{} / {}", ma.route.stops[idx_a].name, mb.route.stops[idx_b].name);
+                    journeys.push(create_transfer_journey(ma, mb, idx_a, idx_b, &tp, false));
+                    if journeys.len() >= MAX_SEARCH_RESULTS { break 'outer; }
+                    continue 'outer;
+                }
+            }
+
+            // 3. Geo Proximity Transfer
             for (idx_a, sa) in ma.route.stops.iter().enumerate() {
-                if idx_a == oi_a { continue; }
+                if idx_a == oi_a || !ma.is_open(idx_a) { continue; }
                 for (idx_b, sb) in mb.route.stops.iter().enumerate() {
                     ops += 1; if ops > MAX_OPS { break 'outer; }
-                    if idx_b == di_b { continue; }
+                    if idx_b == di_b || !mb.is_open(idx_b) { continue; }
 
                     if haversine_distance_m(sa.lat, sa.lng, sb.lat, sb.lng) <= GEO_TRANSFER_RADIUS_M {
                         journeys.push(create_transfer_journey(ma, mb, idx_a, idx_b, &format!("{} / {}", sa.name, sb.name), true));
@@ -321,6 +662,8 @@ fn create_transfer_journey(ma: &RouteMatch, mb: &RouteMatch, idx_a: usize, idx_b
                 origin_stop: ma.route.stops[ma.origin_idx.unwrap()].name.clone(),
                 dest_stop: ma.route.stops[idx_a].name.clone(),
                 price: ma.route.price,
+                distance_m: leg_distance_m(ma.route, ma.origin_idx.unwrap(), idx_a),
+                polyline: None,
             },
             RouteLeg {
                 route_id: mb.route.id.clone(),
@@ -328,12 +671,21 @@ fn create_transfer_journey(ma: &RouteMatch, mb: &RouteMatch, idx_a: usize, idx_b
                 origin_stop: mb.route.stops[idx_b].name.clone(),
                 dest_stop: mb.route.stops[mb.dest_idx.unwrap()].name.clone(),
                 price: mb.route.price,
+                distance_m: leg_distance_m(mb.route, idx_b, mb.dest_idx.unwrap()),
+                polyline: None,
             }
         ],
         total_price: ma.route.price + mb.route.price,
         transfer_point: Some(tp.to_string()),
         geo_transfer: geo,
         is_forward: true,
+        origin_alias: None,
+        dest_alias: None,
+        alerts: Vec::new(),
+        delay_minutes: 0,
+        avoided: None,
+        itinerary: None,
+        last_connection: None,
     }
 }
 
@@ -343,17 +695,19 @@ fn create_transfer_journey(ma: &RouteMatch, mb: &RouteMatch, idx_a: usize, idx_b
 mod tests {
     use super::*;
 
-    fn mock_route(id: &str, stops: Vec<(&str, f64, f64)>) -> Route {
+    pub(crate) fn mock_route(id: &str, stops: Vec<(&str, f64, f64)>) -> Route {
         let mut stop_objs = Vec::new();
         for (i, (name, lat, lng)) in stops.into_iter().enumerate() {
             stop_objs.push(Stop { id: None, name: name.to_string(), lat, lng, orden: i as u32, landmarks: String::new() });
         }
         let stops_normalized: Vec<String> = stop_objs.iter().map(|s| normalize_str(&s.name)).collect();
         let stop_name_to_index = stops_normalized.iter().enumerate().map(|(idx, name)| (name.clone(), idx)).collect();
+        let stop_tokens = stop_objs.iter().map(|s| normalize::token_set(&s.name)).collect();
         Route {
-            id: id.to_string(), name: id.to_string(), price: 10.0, transport_type: "Bus".to_string(),
-            empresa: None, frecuencia_minutos: None, horario: None, stops: stop_objs,
-            stops_normalized, stop_name_to_index, social_alerts: vec![], last_updated: "".to_string()
+            id: id.to_string(), name: id.to_string(), price: 10.0, transport_type: "Bus".to_string(), color: None,
+            empresa: None, frecuencia_minutos: None, horario: None, stops: stop_objs, shape: None,
+            stops_normalized, stop_name_to_index, stop_tokens, stop_offsets_m: vec![],
+            station_ids: vec![], station_to_index: HashMap::new(), social_alerts: vec![], last_updated: "".to_string()
         }
     }
 
@@ -365,6 +719,15 @@ mod tests {
         assert_eq!(normalize_str("Niño"), "nino");
         assert_eq!(normalize_str("ÁÉÍÓÚ Ü Ñ"), "aeiou u n");
         assert_eq!(normalize_str("Multiple   Spaces"), "multiple spaces");
+        assert_eq!(normalize_str("Av. Tulum"), normalize_str("av tulum"));
+    }
+
+    #[test]
+    fn test_find_route_ignores_word_order() {
+        let routes = vec![mock_route("R1", vec![("Terminal ADO Centro", 0.0, 0.0), ("Av. Kabah", 0.0, 0.01)])];
+        let res = find_route_rs("ADO Centro Terminal", "Kabah Av", &routes);
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].legs[0].origin_stop, "Terminal ADO Centro");
     }
 
     #[test]
@@ -379,7 +742,7 @@ mod tests {
     fn test_find_route_transfer() {
         let r1 = mock_route("R1", vec![("A", 0.0, 0.0), ("Hub", 0.1, 0.1)]);
         let r2 = mock_route("R2", vec![("Hub", 0.1, 0.1), ("B", 0.2, 0.2)]);
-        let res = find_route_rs("A", "B", &vec![r1, r2]);
+        let res = find_route_rs("A", "B", &[r1, r2]);
         assert_eq!(res.len(), 1);
         assert_eq!(res[0].type_, "Transfer");
     }
@@ -388,8 +751,66 @@ mod tests {
     fn test_geo_transfer() {
         let r1 = mock_route("R1", vec![("A", 21.1576, -86.8269), ("H1", 21.1580, -86.8269)]);
         let r2 = mock_route("R2", vec![("H2", 21.1600, -86.8269), ("B", 21.1620, -86.8269)]);
-        let res = find_route_rs("A", "B", &vec![r1, r2]);
+        let res = find_route_rs("A", "B", &[r1, r2]);
         assert_eq!(res.len(), 1);
         assert!(res[0].geo_transfer);
     }
+
+    #[test]
+    fn test_leg_distance_follows_shape() {
+        let mut r1 = mock_route("R1", vec![("A", 21.10, -86.77), ("B", 21.11, -86.76)]);
+        let straight = leg_distance_m(&r1, 0, 1);
+        r1.shape = Some(vec![[21.10, -86.77], [21.11, -86.77], [21.11, -86.76]]);
+        prepare_route(&mut r1);
+        assert_eq!(r1.stop_offsets_m.len(), 2);
+        let along = leg_distance_m(&r1, 0, 1);
+        assert!(along > straight * 1.3);
+        let res = find_route_rs("A", "B", &[r1]);
+        assert!((res[0].legs[0].distance_m - along).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_leg_polyline_round_trip() {
+        let routes = vec![mock_route("R1", vec![("A", 21.1714, -86.8219), ("B", 21.1619, -86.8515), ("C", 21.15, -86.84)])];
+        let mut res = find_route_rs("A", "C", &routes);
+        encode_leg_polylines(&mut res, &routes, 6).unwrap();
+        let decoded = polyline::decode(res[0].legs[0].polyline.as_ref().unwrap(), 6).unwrap();
+        assert_eq!(decoded, leg_coords(&routes[0], 0, 2));
+    }
+
+    #[test]
+    fn test_exclusions_reroute_and_are_reported() {
+        let db = AppState {
+            routes_list: vec![
+                mock_route("R1", vec![("A", 21.10, -86.80), ("Hub", 21.12, -86.82), ("B", 21.14, -86.84)]),
+                mock_route("R2", vec![("A", 21.10, -86.80), ("Mercado 28", 21.16, -86.83)]),
+                mock_route("R3", vec![("Mercado 28", 21.16, -86.83), ("B", 21.14, -86.84)]),
+            ],
+            stations: vec![],
+            aliases: aliases::AliasTable::default(),
+            alerts: vec![],
+            vehicles: realtime::VehicleStore::default(),
+            ferries: Default::default(),
+            circuity: Default::default(),
+            fares: Default::default(),
+        };
+        let plain = plan_journeys(&db, "A", "B", 0, &Exclusions::default());
+        assert_eq!(plain[0].legs[0].route_id, "R1");
+        assert!(plain[0].avoided.is_none());
+
+        let no_r1 = Exclusions { routes: vec!["R1".to_string()], ..Default::default() };
+        let res = plan_journeys(&db, "A", "B", 0, &no_r1);
+        assert!(res.iter().all(|j| j.legs.iter().all(|l| l.route_id != "R1")));
+        assert_eq!(res[0].avoided.as_ref(), Some(&no_r1));
+
+        let no_market = Exclusions { stops: vec!["mercado 28".to_string()], ..Default::default() };
+        let res = plan_journeys(&db, "A", "B", 0, &no_market);
+        assert!(res.iter().all(|j| j.transfer_point.is_none()));
+
+        // R1 rides through the area around Hub without stopping there.
+        let area = Exclusions { avoid_area: Some(vec![[21.115, -86.825], [21.125, -86.825], [21.125, -86.815], [21.115, -86.815]]), ..Default::default() };
+        let res = plan_journeys(&db, "A", "B", 0, &area);
+        assert!(!res.is_empty());
+        assert!(res.iter().all(|j| j.legs.iter().all(|l| l.route_id != "R1")));
+    }
 }
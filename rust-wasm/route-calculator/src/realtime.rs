@@ -0,0 +1,253 @@
+//! Live vehicle positions and arrival predictions.
+//!
+//! Positions come from `/api/tracking` (one unit per vehicle, tagged with a
+//! route id). Each is snapped onto its route — the traced shape when the
+//! stops are projected on it, the stop sequence otherwise — and arrivals at
+//! downstream stops are predicted from the `TransportType` speed slowed by
+//! `TrafficConditions`, plus a dwell at each intermediate stop.
+
+use serde::{Deserialize, Serialize};
+use shared_types::{shape, TrafficConditions, TransportType};
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+use crate::clock::{self, parse_datetime};
+use crate::{find_stop_on_route, Route, DB};
+
+/// Positions older than this are ignored, like `/api/tracking` does.
+pub const MAX_POSITION_AGE_S: i64 = 300;
+/// Vehicles farther than this from their route are not on it.
+const MAX_OFF_ROUTE_M: f64 = 300.0;
+/// A vehicle this close before a stop is arriving at it.
+const AT_STOP_M: f64 = 30.0;
+const DWELL_S: f64 = 20.0;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct VehiclePosition {
+    pub id: String,
+    pub route_id: String,
+    pub lat: f64,
+    pub lng: f64,
+    #[serde(default)]
+    pub speed_kmh: f64,
+    #[serde(default)]
+    pub heading: f64,
+    #[serde(default)]
+    pub stop_name: Option<String>,
+    /// ISO 8601 or Postgres `timestamptz::text`.
+    pub updated_at: String,
+}
+
+/// `/api/tracking` response, or a bare array of units.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TrackingFeed {
+    Payload { units: Vec<VehiclePosition> },
+    Units(Vec<VehiclePosition>),
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ArrivalPrediction {
+    pub vehicle_id: String,
+    pub route_id: String,
+    pub route_name: String,
+    pub stop: String,
+    pub stop_index: usize,
+    pub distance_m: f64,
+    pub eta_s: f64,
+    /// `eta_s` rounded up to whole minutes.
+    pub minutes: u32,
+    /// 0..=1, lower for stale positions, poor snaps and long horizons.
+    pub confidence: f64,
+}
+
+/// Latest position per vehicle.
+#[derive(Default, Clone, Debug)]
+pub struct VehicleStore {
+    vehicles: HashMap<String, (VehiclePosition, i64)>,
+}
+
+impl VehicleStore {
+    /// Adds positions, keeping the newest per vehicle. Returns how many were
+    /// accepted; units with unreadable timestamps are skipped.
+    pub fn ingest(&mut self, positions: Vec<VehiclePosition>) -> usize {
+        let mut accepted = 0;
+        for p in positions {
+            let Ok(t) = parse_datetime(&p.updated_at) else { continue };
+            if self.vehicles.get(&p.id).is_some_and(|(_, prev)| *prev > t) { continue; }
+            self.vehicles.insert(p.id.clone(), (p, t));
+            accepted += 1;
+        }
+        accepted
+    }
+
+    pub fn ingest_json(&mut self, json: &str) -> Result<usize, String> {
+        let feed: TrackingFeed = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let units = match feed {
+            TrackingFeed::Payload { units } | TrackingFeed::Units(units) => units,
+        };
+        Ok(self.ingest(units))
+    }
+
+    fn fresh(&self, now_s: i64) -> impl Iterator<Item = (&VehiclePosition, i64)> {
+        self.vehicles.values()
+            .map(move |(p, t)| (p, now_s - t))
+            .filter(|(_, age)| (0..=MAX_POSITION_AGE_S).contains(age))
+    }
+}
+
+/// Geometry the vehicle is snapped on and where each stop sits along it.
+fn route_line(route: &Route) -> (Vec<[f64; 2]>, Vec<f64>) {
+    if let Some(s) = route.shape.as_deref() {
+        if route.stop_offsets_m.len() == route.stops.len() {
+            return (s.to_vec(), route.stop_offsets_m.clone());
+        }
+    }
+    let line: Vec<[f64; 2]> = route.stops.iter().map(|s| [s.lat, s.lng]).collect();
+    let offsets = shape::cumulative_m(&line);
+    (line, offsets)
+}
+
+/// Predicted arrival of every fresh vehicle of `route` that has not yet
+/// passed stop `target`.
+pub fn predict_route(
+    route: &Route, target: usize, vehicles: &VehicleStore, now_s: i64, traffic: &TrafficConditions,
+) -> Vec<ArrivalPrediction> {
+    let (line, offsets) = route_line(route);
+    if line.len() < 2 || target >= offsets.len() { return Vec::new(); }
+    let speed_ms = TransportType::from_label(&route.transport_type).avg_speed_kmh() / 3.6
+        / traffic.total_delay_factor().max(1.0);
+
+    let mut out = Vec::new();
+    for (v, age_s) in vehicles.fresh(now_s).filter(|(v, _)| v.route_id == route.id) {
+        let Some(proj) = shape::project_point(&line, [v.lat, v.lng]) else { continue };
+        if proj.off_shape_m > MAX_OFF_ROUTE_M { continue; }
+        let distance_m = offsets[target] - proj.offset_m;
+        if distance_m < -AT_STOP_M { continue; }
+        let distance_m = distance_m.max(0.0);
+        let stops_between = offsets.iter().filter(|&&o| o > proj.offset_m + AT_STOP_M && o < offsets[target]).count();
+        let eta_s = (distance_m / speed_ms + stops_between as f64 * DWELL_S - age_s as f64).max(0.0);
+
+        let age_factor = 1.0 - 0.5 * age_s as f64 / MAX_POSITION_AGE_S as f64;
+        let snap_factor = 1.0 - 0.5 * proj.off_shape_m / MAX_OFF_ROUTE_M;
+        let horizon_factor = 1.0 / (1.0 + eta_s / 1800.0);
+        let confidence = (age_factor * snap_factor * horizon_factor * 100.0).round() / 100.0;
+
+        out.push(ArrivalPrediction {
+            vehicle_id: v.id.clone(),
+            route_id: route.id.clone(),
+            route_name: route.name.clone(),
+            stop: route.stops[target].name.clone(),
+            stop_index: target,
+            distance_m,
+            eta_s,
+            minutes: (eta_s / 60.0).ceil() as u32,
+            confidence,
+        });
+    }
+    out.sort_by(|a, b| a.eta_s.total_cmp(&b.eta_s));
+    out
+}
+
+/// Next vehicle of each route serving `stop`, soonest first.
+pub fn next_arrivals_rs(
+    stop: &str, routes: &[Route], vehicles: &VehicleStore, now_s: i64, traffic: &TrafficConditions,
+) -> Vec<ArrivalPrediction> {
+    let mut out: Vec<ArrivalPrediction> = routes.iter()
+        .filter_map(|r| {
+            let idx = find_stop_on_route(r, stop)?;
+            predict_route(r, idx, vehicles, now_s, traffic).into_iter().next()
+        })
+        .collect();
+    out.sort_by(|a, b| a.eta_s.total_cmp(&b.eta_s));
+    out
+}
+
+// --- WASM ---
+
+pub fn ingest_positions_core(json: &str) -> Result<usize, String> {
+    if json.len() > 2 * 1024 * 1024 { return Err("Payload too large".to_string()); }
+    let mut db = DB.write().map_err(|_| "Lock failed".to_string())?;
+    db.vehicles.ingest_json(json)
+}
+
+/// Accepts the `/api/tracking` response (or its `units` array).
+#[wasm_bindgen]
+pub fn ingest_positions(json_payload: &str) -> Result<usize, JsValue> {
+    ingest_positions_core(json_payload).map_err(|e| JsValue::from_str(&e))
+}
+
+/// "Next vehicle at stop X in N min", per route, with traffic for the
+/// current Cancún hour.
+#[wasm_bindgen]
+pub fn next_arrivals(stop: &str, limit: usize) -> Result<JsValue, JsValue> {
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    let now = clock::now_unix_s();
+    let traffic = TrafficConditions::from_hour(clock::cancun_hour(now));
+    let (stop, _) = db.aliases.resolve(stop, &db.routes_list, &db.stations);
+    let mut res = next_arrivals_rs(&stop, &db.routes_list, &db.vehicles, now, &traffic);
+    res.truncate(limit);
+    serde_wasm_bindgen::to_value(&res).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_route;
+
+    /// Recorded `/api/tracking` response, 2026-05-12 08:41 Cancún.
+    const FIXTURE: &str = include_str!("../fixtures/tracking_r1_zona_hotelera.json");
+
+    fn r1_zona_hotelera() -> Route {
+        let mut r = mock_route("R1_ZONA_HOTELERA_001", vec![
+            ("La Rehoyada / Villas Otoch (Origenes)", 21.1619, -86.8515),
+            ("El Crucero", 21.1714, -86.8219),
+            ("Av. Tulum Sur", 21.155, -86.825),
+            ("El Cebiche (Entrada Zona Hotelera)", 21.153, -86.815),
+            ("Boulevard Kukulcán (Zona Hotelera)", 21.135, -86.768),
+            ("Playa Delfines (El Mirador)", 21.085, -86.775),
+        ]);
+        r.transport_type = "Bus_Urban".to_string();
+        r
+    }
+
+    fn store() -> VehicleStore {
+        let mut s = VehicleStore::default();
+        assert_eq!(s.ingest_json(FIXTURE).unwrap(), 6);
+        s
+    }
+
+    #[test]
+    fn test_fixture_predictions_at_el_cebiche() {
+        let routes = vec![r1_zona_hotelera()];
+        let now = parse_datetime("2026-05-12T13:41:00Z").unwrap();
+        let traffic = TrafficConditions::from_hour(clock::cancun_hour(now));
+        assert!(traffic.is_rush_hour);
+
+        let preds = predict_route(&routes[0], 3, &store(), now, &traffic);
+        // R1ZH-02 is stale, R1ZH-15 is off the route, R1ZH-11 already passed.
+        let ids: Vec<&str> = preds.iter().map(|p| p.vehicle_id.as_str()).collect();
+        assert_eq!(ids, ["R1ZH-03", "R1ZH-07"]);
+        let next = &preds[0];
+        assert!(next.minutes >= 1 && next.minutes <= 5, "{:?}", next);
+        assert!(next.confidence > preds[1].confidence);
+        assert!(preds[1].eta_s > next.eta_s + 300.0);
+
+        let board = next_arrivals_rs("el cebiche entrada zona hotelera", &routes, &store(), now, &traffic);
+        assert_eq!(board.len(), 1);
+        assert_eq!(board[0].vehicle_id, "R1ZH-03");
+    }
+
+    #[test]
+    fn test_traffic_slows_predictions_and_old_positions_expire() {
+        let route = r1_zona_hotelera();
+        let now = parse_datetime("2026-05-12T13:41:00Z").unwrap();
+        let rush = predict_route(&route, 5, &store(), now, &TrafficConditions::from_hour(8));
+        let free = predict_route(&route, 5, &store(), now, &TrafficConditions::free_flow());
+        assert_eq!(rush.len(), free.len());
+        assert!(rush[0].eta_s > free[0].eta_s * 1.5);
+
+        let later = now + MAX_POSITION_AGE_S + 60;
+        assert!(predict_route(&route, 5, &store(), later, &TrafficConditions::free_flow()).is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Service hours of catalog routes. Parsing lives in
+//! `shared_types::schedule`.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+pub use shared_types::schedule::*;
+
+use crate::clock;
+use crate::{Route, DB};
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct ScheduleIssue {
+    pub route_id: String,
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+pub fn service_hours(route: &Route) -> ServiceHours {
+    route.horario.as_ref().map(|h| h.parse().0).unwrap_or_default()
+}
+
+/// (start, end) in minutes; `end` may exceed 24h for service past midnight.
+pub fn service_window(route: &Route) -> (u32, u32) {
+    service_hours(route).regular
+        .map_or((DEFAULT_SERVICE_START_MIN, DEFAULT_SERVICE_END_MIN), |w| (w.start_min, w.end_min))
+}
+
+pub fn guard_window(route: &Route) -> Option<(u32, u32)> {
+    service_hours(route).night_guard.map(|w| (w.start_min, w.end_min))
+}
+
+/// Service of `route` at `minute` (since midnight, Cancún).
+pub fn service_at(route: &Route, minute: u32) -> Service {
+    service_hours(route).service_at(minute)
+}
+
+/// Whether `route` runs at `unix_s`, night guard included.
+pub fn is_in_service_rs(route: &Route, unix_s: i64) -> bool {
+    service_at(route, clock::cancun_minute_of_day(unix_s)) != Service::NotRunning
+}
+
+/// Every schedule field in the catalog that does not parse.
+pub fn schedule_issues(routes: &[Route]) -> Vec<ScheduleIssue> {
+    routes.iter()
+        .filter_map(|r| r.horario.as_ref().map(|h| (r, h.parse().1)))
+        .flat_map(|(r, issues)| issues.into_iter().map(|(field, value, reason)| ScheduleIssue {
+            route_id: r.id.clone(), field, value, reason,
+        }))
+        .collect()
+}
+
+// --- WASM ---
+
+/// `time`: ISO 8601, Cancún time when it has no offset; empty for now.
+#[wasm_bindgen]
+pub fn is_in_service(route_id: &str, time: &str) -> Result<bool, JsValue> {
+    let t = if time.trim().is_empty() {
+        clock::now_unix_s()
+    } else {
+        clock::parse_datetime(time).map_err(|e| JsValue::from_str(&e))?
+    };
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    let route = db.routes_list.iter().find(|r| r.id == route_id)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown route: {}", route_id)))?;
+    Ok(is_in_service_rs(route, t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_route;
+    use serde::Deserialize;
+
+    fn sched(json: &str) -> Schedule {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_is_in_service_and_lint() {
+        let mut r = mock_route("R1", vec![("A", 21.10, -86.80), ("B", 21.12, -86.82)]);
+        r.horario = Some(sched(r#"{"inicio": "06:00 - 22:00"}"#));
+        assert!(is_in_service_rs(&r, clock::parse_datetime("2026-05-12T21:59").unwrap()));
+        assert!(!is_in_service_rs(&r, clock::parse_datetime("2026-05-12T22:00").unwrap()));
+
+        let mut bad = mock_route("R2", vec![("A", 21.10, -86.80), ("B", 21.12, -86.82)]);
+        bad.horario = Some(sched(r#"{"inicio": "6am", "fin": "22:00"}"#));
+        let issues = schedule_issues(&[r, bad]);
+        assert_eq!(issues.len(), 1);
+        assert_eq!((issues[0].route_id.as_str(), issues[0].field.as_str()), ("R2", "inicio"));
+    }
+
+    #[test]
+    fn test_catalog_schedules_parse() {
+        #[derive(Deserialize)]
+        struct Entry { id: String, #[serde(default)] horario: Option<Schedule> }
+        #[derive(Deserialize)]
+        struct Catalog { rutas: Vec<Entry> }
+        let catalog: Catalog = serde_json::from_str(include_str!("../../../public/data/master_routes.json")).unwrap();
+        for r in catalog.rutas {
+            let issues = r.horario.map(|h| h.parse().1).unwrap_or_default();
+            assert!(issues.is_empty(), "{}: {:?}", r.id, issues);
+        }
+    }
+}
@@ -0,0 +1,425 @@
+//! Station clustering: groups the same physical stop across routes.
+//!
+//! "ADO Centro", "Terminal ADO" and "ADO" on three routes are one station.
+//! Two stops are linked when they are practically on top of each other, or
+//! close and with similar names. Links are applied closest first, and never
+//! put two stops of the same route in one station or make a station wider
+//! than `MAX_STATION_SPREAD_M`, so dense traced routes do not chain into
+//! one huge cluster. The router uses stations for zero-walk transfers.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+use crate::gtfs::fnv1a;
+use crate::{haversine_distance_m, normalize_str, Route, DB};
+
+/// Stops this close are the same station unless their names clearly differ.
+const SAME_SPOT_RADIUS_M: f64 = 30.0;
+/// Stops this close are the same station when their names are similar.
+const NAME_MATCH_RADIUS_M: f64 = 250.0;
+const NAME_MATCH_THRESHOLD: f64 = 0.8;
+/// Stops on the same spot still need names at least this similar.
+const SAME_SPOT_THRESHOLD: f64 = 0.6;
+/// Pairs below the threshold but above this are reported for review.
+const NEAR_MISS_THRESHOLD: f64 = 0.5;
+/// Stations wider than this are reported for review.
+const AMBIGUOUS_SPREAD_M: f64 = 150.0;
+const MAX_STATION_SPREAD_M: f64 = 300.0;
+/// Grid cell for neighbour search, ~330 m at Cancún's latitude.
+const CELL_DEG: f64 = 0.003;
+
+/// Words that say nothing about which stop it is.
+const FILLER_WORDS: &[&str] = &[
+    "terminal", "parada", "av", "avenida", "blvd", "boulevard", "calle", "carr", "plaza",
+];
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct StationMember {
+    pub route_id: String,
+    pub stop_index: usize,
+    pub name: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Station {
+    pub id: String,
+    pub name: String,
+    pub lat: f64,
+    pub lng: f64,
+    pub members: Vec<StationMember>,
+    /// Largest distance between two members, meters.
+    pub spread_m: f64,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct AmbiguousCluster {
+    pub station_ids: Vec<String>,
+    pub names: Vec<String>,
+    pub distance_m: f64,
+    pub reason: String,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct StationReport {
+    pub station_count: usize,
+    pub stop_count: usize,
+    pub ambiguous: Vec<AmbiguousCluster>,
+}
+
+// --- NAME SIMILARITY ---
+
+/// Auto-generated names from traced routes ("Stop 12 - RUTA 5",
+/// "Parada RUTA 31 #4") only carry the route label, never the place.
+fn is_placeholder(norm: &str) -> bool {
+    norm.starts_with("stop ") || norm.starts_with("parada ruta")
+}
+
+fn key_tokens(norm: &str) -> HashSet<&str> {
+    norm.split(' ')
+        .filter(|t| !t.is_empty() && !FILLER_WORDS.contains(t))
+        .collect()
+}
+
+/// 0..=1. Token containment catches "ADO" vs "Terminal ADO";
+/// edit distance on the key words catches spelling variants. Different
+/// numbers ("Mercado 23" vs "Mercado 28") are never similar enough to merge
+/// on name alone. Placeholder names count as similar to anything, so they
+/// only merge on position.
+pub fn name_similarity(a_norm: &str, b_norm: &str) -> f64 {
+    if is_placeholder(a_norm) || is_placeholder(b_norm) {
+        return SAME_SPOT_THRESHOLD;
+    }
+    let (ta, tb) = (key_tokens(a_norm), key_tokens(b_norm));
+    let numbers = |t: &HashSet<&str>| -> HashSet<String> {
+        t.iter().filter(|w| w.chars().all(|c| c.is_ascii_digit())).map(|w| w.to_string()).collect()
+    };
+    let (na, nb) = (numbers(&ta), numbers(&tb));
+    if !na.is_empty() && !nb.is_empty() && na != nb {
+        return NEAR_MISS_THRESHOLD;
+    }
+    let containment = if ta.is_empty() || tb.is_empty() {
+        0.0
+    } else {
+        ta.intersection(&tb).count() as f64 / ta.len().min(tb.len()) as f64
+    };
+    let joined = |t: &HashSet<&str>| {
+        let mut words: Vec<&str> = t.iter().copied().collect();
+        words.sort_unstable();
+        words.join(" ")
+    };
+    containment.max(strsim::normalized_damerau_levenshtein(&joined(&ta), &joined(&tb)))
+}
+
+// --- CLUSTERING ---
+
+struct Flat<'a> {
+    route: &'a Route,
+    index: usize,
+    norm: String,
+}
+
+/// Union-find that keeps each root's members to check merge constraints.
+struct Clusters {
+    parent: Vec<usize>,
+    members: Vec<Vec<usize>>,
+}
+
+impl Clusters {
+    fn new(n: usize) -> Self {
+        Clusters { parent: (0..n).collect(), members: (0..n).map(|i| vec![i]).collect() }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    fn try_merge(&mut self, flat: &[Flat], a: usize, b: usize) {
+        let (ra, rb) = (self.find(a), self.find(b));
+        if ra == rb { return; }
+        for &x in &self.members[ra] {
+            for &y in &self.members[rb] {
+                let (sx, sy) = (&flat[x].route.stops[flat[x].index], &flat[y].route.stops[flat[y].index]);
+                if flat[x].route.id == flat[y].route.id
+                    || haversine_distance_m(sx.lat, sx.lng, sy.lat, sy.lng) > MAX_STATION_SPREAD_M
+                    || name_similarity(&flat[x].norm, &flat[y].norm) < SAME_SPOT_THRESHOLD
+                {
+                    return;
+                }
+            }
+        }
+        let (keep, gone) = (ra.min(rb), ra.max(rb));
+        let moved = std::mem::take(&mut self.members[gone]);
+        self.members[keep].extend(moved);
+        self.parent[gone] = keep;
+    }
+}
+
+/// Two stops within `NAME_MATCH_RADIUS_M` of each other.
+struct Pair {
+    a: usize,
+    b: usize,
+    distance_m: f64,
+    similarity: f64,
+}
+
+impl Pair {
+    fn is_same_station(&self) -> bool {
+        self.similarity >= NAME_MATCH_THRESHOLD
+            || (self.distance_m <= SAME_SPOT_RADIUS_M && self.similarity >= SAME_SPOT_THRESHOLD)
+    }
+}
+
+fn cell(lat: f64, lng: f64) -> (i64, i64) {
+    ((lat / CELL_DEG).floor() as i64, (lng / CELL_DEG).floor() as i64)
+}
+
+fn candidate_pairs(flat: &[Flat]) -> Vec<Pair> {
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (i, f) in flat.iter().enumerate() {
+        let s = &f.route.stops[f.index];
+        grid.entry(cell(s.lat, s.lng)).or_default().push(i);
+    }
+    let mut pairs = Vec::new();
+    for (i, f) in flat.iter().enumerate() {
+        let s = &f.route.stops[f.index];
+        let (cy, cx) = cell(s.lat, s.lng);
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let Some(bucket) = grid.get(&(cy + dy, cx + dx)) else { continue };
+                for &j in bucket.iter().filter(|&&j| j > i) {
+                    let o = &flat[j].route.stops[flat[j].index];
+                    let d = haversine_distance_m(s.lat, s.lng, o.lat, o.lng);
+                    if d <= NAME_MATCH_RADIUS_M {
+                        let similarity = name_similarity(&f.norm, &flat[j].norm);
+                        pairs.push(Pair { a: i, b: j, distance_m: d, similarity });
+                    }
+                }
+            }
+        }
+    }
+    pairs
+}
+
+pub fn cluster_stations(routes: &[Route]) -> Vec<Station> {
+    cluster(routes).stations
+}
+
+struct Clustering {
+    stations: Vec<Station>,
+    /// Close pairs with similar-ish names that ended up in different stations.
+    near_misses: Vec<Pair>,
+    /// Station index of each stop, in catalog order.
+    station_of: Vec<usize>,
+}
+
+fn cluster(routes: &[Route]) -> Clustering {
+    let flat: Vec<Flat> = routes.iter()
+        .flat_map(|route| (0..route.stops.len()).map(move |index| (route, index)))
+        .map(|(route, index)| Flat {
+            route,
+            index,
+            norm: route.stops_normalized.get(index).cloned()
+                .unwrap_or_else(|| normalize_str(&route.stops[index].name)),
+        })
+        .collect();
+
+    let mut pairs = candidate_pairs(&flat);
+    pairs.sort_by(|x, y| x.distance_m.total_cmp(&y.distance_m).then(x.a.cmp(&y.a)).then(x.b.cmp(&y.b)));
+    let mut uf = Clusters::new(flat.len());
+    for p in pairs.iter().filter(|p| p.is_same_station()) {
+        uf.try_merge(&flat, p.a, p.b);
+    }
+
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut root_to_group: HashMap<usize, usize> = HashMap::new();
+    let station_of: Vec<usize> = (0..flat.len()).map(|i| {
+        let g = *root_to_group.entry(uf.find(i)).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[g].push(i);
+        g
+    }).collect();
+
+    let mut used_ids: HashSet<String> = HashSet::new();
+    let stations = groups.iter().map(|members| build_station(&flat, members, &mut used_ids)).collect();
+    let near_misses = pairs.into_iter()
+        .filter(|p| station_of[p.a] != station_of[p.b])
+        .filter(|p| p.similarity >= NEAR_MISS_THRESHOLD)
+        .filter(|p| !is_placeholder(&flat[p.a].norm) && !is_placeholder(&flat[p.b].norm))
+        .collect();
+    Clustering { stations, near_misses, station_of }
+}
+
+fn build_station(flat: &[Flat], members: &[usize], used_ids: &mut HashSet<String>) -> Station {
+    // Canonical member: most common name, then earliest in catalog order.
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for &m in members { *counts.entry(flat[m].norm.as_str()).or_default() += 1; }
+    let canonical = *members.iter()
+        .max_by(|&&a, &&b| counts[flat[a].norm.as_str()].cmp(&counts[flat[b].norm.as_str()]).then(b.cmp(&a)))
+        .expect("station has members");
+    let c = &flat[canonical].route.stops[flat[canonical].index];
+
+    // Stable as long as the canonical stop does not change.
+    let key = format!("{}|{:.4}|{:.4}", flat[canonical].norm, c.lat, c.lng);
+    let mut id = format!("ST{:012x}", fnv1a(key.as_bytes()) & 0xffff_ffff_ffff);
+    let mut n = 2;
+    while !used_ids.insert(id.clone()) {
+        id = format!("ST{:012x}-{}", fnv1a(key.as_bytes()) & 0xffff_ffff_ffff, n);
+        n += 1;
+    }
+
+    let stops: Vec<_> = members.iter().map(|&m| &flat[m].route.stops[flat[m].index]).collect();
+    let count = stops.len() as f64;
+    let mut spread_m: f64 = 0.0;
+    for (i, a) in stops.iter().enumerate() {
+        for b in &stops[i + 1..] {
+            spread_m = spread_m.max(haversine_distance_m(a.lat, a.lng, b.lat, b.lng));
+        }
+    }
+    Station {
+        id,
+        name: c.name.clone(),
+        lat: stops.iter().map(|s| s.lat).sum::<f64>() / count,
+        lng: stops.iter().map(|s| s.lng).sum::<f64>() / count,
+        members: members.iter().map(|&m| StationMember {
+            route_id: flat[m].route.id.clone(),
+            stop_index: flat[m].index,
+            name: flat[m].route.stops[flat[m].index].name.clone(),
+        }).collect(),
+        spread_m,
+    }
+}
+
+/// Stations too wide to trust, and close pairs with similar-ish names that
+/// were kept apart. Both need a human to decide.
+pub fn station_report(routes: &[Route]) -> StationReport {
+    let Clustering { stations, near_misses, station_of } = cluster(routes);
+    let mut ambiguous: Vec<AmbiguousCluster> = stations.iter()
+        .filter(|s| s.spread_m > AMBIGUOUS_SPREAD_M)
+        .map(|s| {
+            let mut names: Vec<String> = s.members.iter().map(|m| m.name.clone()).collect();
+            names.sort();
+            names.dedup();
+            AmbiguousCluster {
+                station_ids: vec![s.id.clone()],
+                names,
+                distance_m: s.spread_m,
+                reason: format!("Members up to {:.0} m apart", s.spread_m),
+            }
+        })
+        .collect();
+
+    let flat_names: Vec<&str> = routes.iter().flat_map(|r| r.stops.iter().map(|s| s.name.as_str())).collect();
+    let mut seen: HashSet<(usize, usize)> = HashSet::new();
+    for p in near_misses {
+        let (sa, sb) = (station_of[p.a], station_of[p.b]);
+        let key = (sa.min(sb), sa.max(sb));
+        if !seen.insert(key) { continue; }
+        ambiguous.push(AmbiguousCluster {
+            station_ids: vec![stations[key.0].id.clone(), stations[key.1].id.clone()],
+            names: vec![flat_names[p.a].to_string(), flat_names[p.b].to_string()],
+            distance_m: p.distance_m,
+            reason: format!("Kept apart: {:.0} m, name similarity {:.2}", p.distance_m, p.similarity),
+        });
+    }
+
+    StationReport {
+        station_count: stations.len(),
+        stop_count: flat_names.len(),
+        ambiguous,
+    }
+}
+
+/// Writes each stop's station id into its route, for the router.
+pub(crate) fn assign_stations(routes: &mut [Route], stations: &[Station]) {
+    let by_route: HashMap<&str, usize> = routes.iter().enumerate().map(|(i, r)| (r.id.as_str(), i)).collect();
+    let mut ids: Vec<Vec<String>> = routes.iter().map(|r| vec![String::new(); r.stops.len()]).collect();
+    for st in stations {
+        for m in &st.members {
+            if let Some(&ri) = by_route.get(m.route_id.as_str()) {
+                ids[ri][m.stop_index] = st.id.clone();
+            }
+        }
+    }
+    for (route, station_ids) in routes.iter_mut().zip(ids) {
+        route.station_to_index = station_ids.iter().enumerate()
+            .filter(|(_, id)| !id.is_empty())
+            .map(|(i, id)| (id.clone(), i))
+            .collect();
+        route.station_ids = station_ids;
+    }
+}
+
+// --- WASM ---
+
+#[wasm_bindgen]
+pub fn get_stations() -> Result<JsValue, JsValue> {
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    serde_wasm_bindgen::to_value(&db.stations).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[wasm_bindgen]
+pub fn get_station_report() -> Result<JsValue, JsValue> {
+    let db = DB.read().map_err(|_| JsValue::from_str("Lock failed"))?;
+    serde_wasm_bindgen::to_value(&station_report(&db.routes_list)).map_err(|e| JsValue::from_str(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::find_route_rs;
+    use crate::tests::mock_route;
+
+    fn ado_network() -> Vec<Route> {
+        vec![
+            mock_route("R1", vec![("Villas Otoch", 21.1685, -86.885), ("ADO Centro", 21.16100, -86.82600)]),
+            mock_route("R2", vec![("Terminal ADO", 21.16120, -86.82620), ("Puerto Juárez", 21.1860, -86.8070)]),
+            mock_route("R3", vec![("ADO", 21.16090, -86.82580), ("El Crucero", 21.1714, -86.8219)]),
+        ]
+    }
+
+    #[test]
+    fn test_ado_variants_form_one_station() {
+        let stations = cluster_stations(&ado_network());
+        let ado: Vec<&Station> = stations.iter().filter(|s| s.members.len() > 1).collect();
+        assert_eq!(ado.len(), 1);
+        assert_eq!(ado[0].members.len(), 3);
+        // Ids do not depend on run or on unrelated routes.
+        let mut more = ado_network();
+        more.push(mock_route("R9", vec![("Chedraui Lakin", 21.165, -86.879), ("Playa Delfines", 21.06, -86.78)]));
+        let again = cluster_stations(&more);
+        assert!(again.iter().any(|s| s.id == ado[0].id && s.members.len() == 3));
+    }
+
+    #[test]
+    fn test_same_station_is_zero_walk_transfer() {
+        let mut routes = ado_network();
+        let stations = cluster_stations(&routes);
+        assign_stations(&mut routes, &stations);
+        let res = find_route_rs("Villas Otoch", "Puerto Juárez", &routes);
+        assert_eq!(res[0].type_, "Transfer");
+        assert!(!res[0].geo_transfer);
+        assert_eq!(res[0].legs[1].origin_stop, "Terminal ADO");
+    }
+
+    #[test]
+    fn test_report_lists_wide_clusters_and_near_misses() {
+        let routes = vec![
+            mock_route("R1", vec![("Plaza Las Américas", 21.1472, -86.8234), ("Mercado 23", 21.1620, -86.8300)]),
+            mock_route("R2", vec![("Plaza Américas Kabah", 21.1480, -86.8250), ("Mercado 28", 21.1635, -86.8310)]),
+        ];
+        let report = station_report(&routes);
+        assert_eq!(report.stop_count, 4);
+        assert_eq!(report.station_count, 3);
+        let wide = report.ambiguous.iter().find(|a| a.station_ids.len() == 1).unwrap();
+        assert_eq!(wide.names, vec!["Plaza Américas Kabah", "Plaza Las Américas"]);
+        let miss = report.ambiguous.iter().find(|a| a.station_ids.len() == 2).unwrap();
+        assert_eq!(miss.names, vec!["Mercado 23", "Mercado 28"]);
+    }
+}
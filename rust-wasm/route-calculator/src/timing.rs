@@ -0,0 +1,189 @@
+//! Time model for planned journeys, shared by arrive-by and timed
+//! itineraries.
+//!
+//! Ride times use the same formula as `eta-engine` (`shared_types::ride`)
+//! under the default traffic calendar's conditions at boarding. Service
+//! hours come from `schedule`. All clock times are Cancún local (UTC-5, no
+//! DST).
+
+use serde::{Deserialize, Serialize};
+use shared_types::calendar::TrafficCalendar;
+use shared_types::circuity::CircuityModel;
+use shared_types::{ride, TrafficConditions, TransportType};
+
+use crate::alerts::AlertEffect;
+use crate::clock::{self, format_hhmm};
+use crate::schedule::{service_at, service_window, Service, DEFAULT_HEADWAY_MIN};
+use crate::{leg_stop_indices, Journey, Route, RouteLeg};
+
+/// Minutes between departures; night guard departures are half as frequent.
+pub fn headway_min(route: &Route, service: Service) -> u32 {
+    let h = route.frecuencia_minutos.filter(|&f| f > 0).unwrap_or(DEFAULT_HEADWAY_MIN);
+    if service == Service::NightGuard { h * 2 } else { h }
+}
+
+/// In-vehicle minutes for `leg` when riding at `unix_s`.
+pub fn ride_min(route: &Route, leg: &RouteLeg, unix_s: i64) -> f64 {
+    let traffic = TrafficConditions::at(unix_s, &TrafficCalendar::default());
+    let stops = leg_stop_indices(route, leg).map_or(0, |(a, b)| a.abs_diff(b));
+    ride::ride(&TransportType::from_label(&route.transport_type), leg.distance_m, stops as u32, &traffic, None).minutes
+}
+
+/// Walking minutes between legs `i` and `i + 1` (zero unless the transfer
+/// is geographic), on streets as `circuity` bends them.
+pub fn walk_min(journey: &Journey, i: usize, routes: &[Route], circuity: &CircuityModel) -> f64 {
+    if !journey.geo_transfer { return 0.0; }
+    let stop = |leg: &RouteLeg, first: bool| {
+        let route = routes.iter().find(|r| r.id == leg.route_id)?;
+        let (a, b) = leg_stop_indices(route, leg)?;
+        route.stops.get(if first { a } else { b })
+    };
+    let (Some(from), Some(to)) = (stop(&journey.legs[i], false), journey.legs.get(i + 1).and_then(|l| stop(l, true))) else {
+        return 0.0;
+    };
+    let m = circuity.road_distance_m(&TransportType::Caminata, [from.lat, from.lng], [to.lat, to.lng]);
+    m / 1000.0 / TransportType::Caminata.avg_speed_kmh() * 60.0
+}
+
+/// One leg placed on the clock.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimedLeg {
+    pub route_id: String,
+    pub origin_stop: String,
+    pub dest_stop: String,
+    /// At the stop, ready to board.
+    pub at_stop: String,
+    pub board: String,
+    pub alight: String,
+    pub at_stop_s: i64,
+    pub board_s: i64,
+    pub alight_s: i64,
+    /// Walk from the previous leg's stop, geographic transfers only.
+    pub walk_minutes: u32,
+    pub wait_minutes: u32,
+    pub ride_minutes: u32,
+    pub service: Service,
+}
+
+impl TimedLeg {
+    pub fn new(leg: &RouteLeg, walk_s: i64, at_stop_s: i64, board_s: i64, alight_s: i64, service: Service) -> Self {
+        let hhmm = |t: i64| format_hhmm(clock::cancun_minute_of_day(t));
+        TimedLeg {
+            route_id: leg.route_id.clone(),
+            origin_stop: leg.origin_stop.clone(),
+            dest_stop: leg.dest_stop.clone(),
+            at_stop: hhmm(at_stop_s),
+            board: hhmm(board_s),
+            alight: hhmm(alight_s),
+            at_stop_s,
+            board_s,
+            alight_s,
+            walk_minutes: ((walk_s + 59) / 60) as u32,
+            wait_minutes: ((board_s - at_stop_s) / 60) as u32,
+            ride_minutes: ((alight_s - board_s + 59) / 60) as u32,
+            service,
+        }
+    }
+}
+
+/// Clock times of a journey leaving now: "board 07:12, transfer 07:40,
+/// arrive 08:05".
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Itinerary {
+    pub depart: String,
+    pub arrive: String,
+    pub depart_s: i64,
+    pub arrive_s: i64,
+    pub legs: Vec<TimedLeg>,
+}
+
+/// Places `journey` on the clock forward from `depart_s`. Waits are the
+/// expected half headway (the night guard's when only it runs, or until
+/// service starts, leaving the leg `NotRunning`); delay alerts on a leg's
+/// route lengthen its wait. `None` when a leg's route is unknown.
+pub fn schedule_forward(journey: &Journey, routes: &[Route], circuity: &CircuityModel, depart_s: i64) -> Option<Itinerary> {
+    let mut t = depart_s;
+    let mut legs = Vec::with_capacity(journey.legs.len());
+    for (i, leg) in journey.legs.iter().enumerate() {
+        let route = routes.iter().find(|r| r.id == leg.route_id)?;
+        let walk_s = if i > 0 { (walk_min(journey, i - 1, routes, circuity) * 60.0).round() as i64 } else { 0 };
+        let at_stop_s = t + walk_s;
+        let minute = clock::cancun_minute_of_day(at_stop_s);
+        let service = service_at(route, minute);
+        let mut wait_min = 0;
+        let mut runs = service;
+        if service == Service::NotRunning {
+            wait_min = (service_window(route).0 + 1440 - minute) % 1440;
+            runs = Service::Regular;
+        }
+        wait_min += headway_min(route, runs).div_ceil(2);
+        wait_min += journey.alerts.iter()
+            .filter(|a| a.route_ids.is_empty() || a.route_ids.contains(&route.id))
+            .map(|a| if let AlertEffect::Delay { minutes } = a.effect { minutes } else { 0 })
+            .sum::<u32>();
+        let board_s = at_stop_s + wait_min as i64 * 60;
+        let alight_s = board_s + (ride_min(route, leg, board_s) * 60.0).round() as i64;
+        legs.push(TimedLeg::new(leg, walk_s, at_stop_s, board_s, alight_s, service));
+        t = alight_s;
+    }
+    let hhmm = |t: i64| format_hhmm(clock::cancun_minute_of_day(t));
+    Some(Itinerary { depart: hhmm(depart_s), arrive: hhmm(t), depart_s, arrive_s: t, legs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::parse_datetime;
+    use crate::tests::mock_route;
+    use crate::{find_route_rs, Schedule};
+
+    #[test]
+    fn test_service_windows() {
+        let mut r = mock_route("R", vec![("A", 21.10, -86.80), ("B", 21.12, -86.82)]);
+        r.horario = Some(Schedule {
+            inicio_oficial: Some("05:00".into()), fin_oficial: Some("22:30".into()),
+            guardia_nocturna: Some("03:00 - 05:00 (Sistema de llenado en OXXO)".into()), ..Default::default()
+        });
+        r.frecuencia_minutos = Some(12);
+        assert_eq!(service_at(&r, 6 * 60), Service::Regular);
+        assert_eq!(service_at(&r, 4 * 60), Service::NightGuard);
+        assert_eq!(service_at(&r, 23 * 60), Service::NotRunning);
+        assert_eq!(headway_min(&r, Service::NightGuard), 24);
+    }
+
+    #[test]
+    fn test_forward_itinerary_with_walk_transfer() {
+        let mut r1 = mock_route("R1", vec![("Villas Otoch", 21.1619, -86.8515), ("El Crucero", 21.1714, -86.8219)]);
+        r1.transport_type = "Bus_Urban".to_string();
+        r1.frecuencia_minutos = Some(10);
+        // ~200 m from El Crucero.
+        let mut r2 = mock_route("R2", vec![("Plaza Las Américas", 21.1730, -86.8210), ("Zona Hotelera", 21.135, -86.768)]);
+        r2.transport_type = "Bus_HotelZone".to_string();
+        r2.frecuencia_minutos = Some(8);
+        r2.horario = Some(Schedule { inicio: Some("06:00 - 22:00".into()), ..Default::default() });
+        let routes = vec![r1, r2];
+        let journey = find_route_rs("Villas Otoch", "Zona Hotelera", &routes).remove(0);
+        assert!(journey.geo_transfer);
+
+        let depart = parse_datetime("2026-05-12T07:00").unwrap();
+        let it = schedule_forward(&journey, &routes, &CircuityModel::default(), depart).unwrap();
+        assert_eq!(it.depart, "07:00");
+        assert_eq!(it.legs[0].board, "07:05");
+        assert_eq!(it.legs[0].wait_minutes, 5);
+        assert!(it.legs[1].walk_minutes >= 2);
+        assert!(it.legs[1].at_stop_s > it.legs[0].alight_s);
+        assert_eq!(it.legs[1].wait_minutes, 4);
+        assert_eq!(it.arrive_s, it.legs[1].alight_s);
+
+        // A loaded walking factor lengthens the transfer walk.
+        let winding = CircuityModel { modes: [("Caminata".to_string(), 3.0)].into(), ..Default::default() };
+        let slow = schedule_forward(&journey, &routes, &winding, depart).unwrap();
+        assert!(slow.legs[1].walk_minutes > it.legs[1].walk_minutes);
+        assert!(slow.arrive_s >= it.arrive_s);
+
+        // Before R2 starts, the wait runs until 06:00 plus half a headway.
+        let early = parse_datetime("2026-05-12T05:00").unwrap();
+        let it = schedule_forward(&journey, &routes, &CircuityModel::default(), early).unwrap();
+        assert_eq!(it.legs[1].board, "06:04");
+    }
+}
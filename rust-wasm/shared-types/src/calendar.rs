@@ -0,0 +1,218 @@
+//! Traffic calendar for Cancún.
+//!
+//! Congestion depends on the day as much as on the hour: weekends and
+//! federal holidays have no school or office rush, and the tourist high
+//! season loads every road at once. `TrafficConditions::at` picks the day
+//! profile for a local datetime and scales it by the active season.
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{self, days_from_civil, weekday_from_days};
+use crate::TrafficConditions;
+
+/// Congestion never goes above this, season included.
+const MAX_CONGESTION: f64 = 3.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DayType {
+    Weekday,
+    Saturday,
+    Sunday,
+    /// Federal holiday or configured extra date; runs the Sunday profile.
+    Holiday,
+}
+
+/// A yearly date range, `MM-DD` inclusive. Ranges may wrap the new year.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Season {
+    pub name: String,
+    pub start: String,
+    pub end: String,
+    /// Multiplies the day profile's congestion.
+    pub factor: f64,
+}
+
+impl Season {
+    fn new(name: &str, start: &str, end: &str, factor: f64) -> Self {
+        Season { name: name.to_string(), start: start.to_string(), end: end.to_string(), factor }
+    }
+
+    fn contains(&self, month: u32, day: u32) -> bool {
+        let (Some(start), Some(end)) = (parse_month_day(&self.start), parse_month_day(&self.end)) else {
+            return false;
+        };
+        let md = (month, day);
+        if start <= end { (start..=end).contains(&md) } else { md >= start || md <= end }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TrafficCalendar {
+    /// First match wins.
+    #[serde(default)]
+    pub high_season: Vec<Season>,
+    /// Extra non-working dates, `YYYY-MM-DD` (local festivities, bridges).
+    #[serde(default)]
+    pub holidays: Vec<String>,
+}
+
+impl Default for TrafficCalendar {
+    fn default() -> Self {
+        TrafficCalendar {
+            high_season: vec![
+                Season::new("spring_break", "03-01", "04-15", 1.15),
+                Season::new("verano", "07-01", "08-31", 1.1),
+                Season::new("diciembre", "12-15", "01-06", 1.2),
+            ],
+            holidays: Vec::new(),
+        }
+    }
+}
+
+fn parse_month_day(s: &str) -> Option<(u32, u32)> {
+    let (m, d) = s.trim().split_once('-')?;
+    let (m, d): (u32, u32) = (m.parse().ok()?, d.parse().ok()?);
+    ((1..=12).contains(&m) && (1..=31).contains(&d)).then_some((m, d))
+}
+
+/// Day of month of the `n`th Monday (1-based) of `month`.
+fn nth_monday(year: i64, month: u32, n: u32) -> u32 {
+    let first = weekday_from_days(days_from_civil(year, month as i64, 1)) as u32;
+    1 + (7 - first) % 7 + (n - 1) * 7
+}
+
+/// Mandatory rest days of the Ley Federal del Trabajo (art. 74) as
+/// `(month, day)`, Mondays resolved for `year`.
+pub fn federal_holidays(year: i64) -> Vec<(u32, u32)> {
+    let mut days = vec![
+        (1, 1),
+        (2, nth_monday(year, 2, 1)),
+        (3, nth_monday(year, 3, 3)),
+        (5, 1),
+        (9, 16),
+        (11, nth_monday(year, 11, 3)),
+        (12, 25),
+    ];
+    // Presidential inauguration, every six years.
+    if year >= 2024 && (year - 2024) % 6 == 0 {
+        days.push((10, 1));
+    } else if year < 2024 && (year - 2024) % 6 == 0 {
+        days.push((12, 1));
+    }
+    days.sort();
+    days
+}
+
+impl TrafficCalendar {
+    pub fn validate(&self) -> Result<(), String> {
+        for s in &self.high_season {
+            if parse_month_day(&s.start).is_none() || parse_month_day(&s.end).is_none() {
+                return Err(format!("Season {}: dates must be MM-DD", s.name));
+            }
+            if !(1.0..=2.0).contains(&s.factor) {
+                return Err(format!("Season {}: factor must be between 1 and 2", s.name));
+            }
+        }
+        for h in &self.holidays {
+            clock::parse_datetime(h).map_err(|e| format!("Holiday {}: {}", h, e))?;
+        }
+        Ok(())
+    }
+
+    pub fn day_type(&self, unix_s: i64) -> DayType {
+        let (y, m, d) = clock::cancun_date(unix_s);
+        let extra = self.holidays.iter()
+            .filter_map(|h| clock::parse_datetime(h).ok())
+            .any(|t| clock::cancun_date(t) == (y, m, d));
+        if extra || federal_holidays(y).contains(&(m, d)) {
+            return DayType::Holiday;
+        }
+        match clock::cancun_weekday(unix_s) {
+            5 => DayType::Saturday,
+            6 => DayType::Sunday,
+            _ => DayType::Weekday,
+        }
+    }
+
+    pub fn season(&self, unix_s: i64) -> Option<&Season> {
+        let (_, m, d) = clock::cancun_date(unix_s);
+        self.high_season.iter().find(|s| s.contains(m, d))
+    }
+}
+
+impl TrafficConditions {
+    /// Conditions at `unix_s`, Cancún local time, under `calendar`.
+    pub fn at(unix_s: i64, calendar: &TrafficCalendar) -> Self {
+        let hour = clock::cancun_hour(unix_s);
+        let mut t = match calendar.day_type(unix_s) {
+            DayType::Weekday => Self::from_hour(hour),
+            DayType::Saturday => {
+                let congestion = match hour {
+                    10..=14 => 1.5, // errands and shopping
+                    17..=20 => 1.6,
+                    7..=9 | 15..=16 | 21..=23 => 1.2,
+                    _ => 1.0,
+                };
+                Self { congestion_factor: congestion, rain_factor: 1.0, is_rush_hour: false, hour }
+            }
+            DayType::Sunday | DayType::Holiday => {
+                let congestion = match hour {
+                    13..=19 => 1.3, // beach and family outings
+                    9..=12 | 20..=21 => 1.15,
+                    _ => 1.0,
+                };
+                Self { congestion_factor: congestion, rain_factor: 1.0, is_rush_hour: false, hour }
+            }
+        };
+        if let Some(s) = calendar.season(unix_s) {
+            t.congestion_factor = (t.congestion_factor * s.factor).min(MAX_CONGESTION);
+        }
+        t
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::parse_datetime;
+
+    fn at(s: &str) -> i64 {
+        parse_datetime(s).unwrap()
+    }
+
+    #[test]
+    fn test_federal_holidays() {
+        let h = federal_holidays(2026);
+        assert!(h.contains(&(2, 2)) && h.contains(&(3, 16)) && h.contains(&(11, 16)));
+        assert!(!h.contains(&(10, 1)));
+        assert!(federal_holidays(2030).contains(&(10, 1)));
+        assert!(federal_holidays(2018).contains(&(12, 1)));
+
+        let cal = TrafficCalendar::default();
+        assert_eq!(cal.day_type(at("2026-09-16T08:00")), DayType::Holiday);
+        assert_eq!(cal.day_type(at("2026-05-16T08:00")), DayType::Saturday);
+        assert_eq!(cal.day_type(at("2026-05-18T08:00")), DayType::Weekday);
+        let cal = TrafficCalendar { holidays: vec!["2026-05-18".into()], ..Default::default() };
+        assert_eq!(cal.day_type(at("2026-05-18T08:00")), DayType::Holiday);
+    }
+
+    #[test]
+    fn test_traffic_by_day_and_season() {
+        let cal = TrafficCalendar::default();
+        let monday = TrafficConditions::at(at("2026-05-18T08:00"), &cal);
+        let sunday = TrafficConditions::at(at("2026-05-17T08:00"), &cal);
+        assert_eq!(monday.congestion_factor, 2.2);
+        assert!(monday.is_rush_hour);
+        assert!(sunday.congestion_factor < monday.congestion_factor && !sunday.is_rush_hour);
+
+        // December wraps the new year and stays under the cap.
+        assert_eq!(cal.season(at("2027-01-03T12:00")).map(|s| s.name.as_str()), Some("diciembre"));
+        let peak = TrafficConditions::at(at("2026-12-17T18:00"), &cal);
+        assert_eq!(peak.congestion_factor, MAX_CONGESTION);
+        assert!(cal.season(at("2026-10-20T12:00")).is_none());
+
+        let bad = TrafficCalendar { high_season: vec![Season::new("x", "13-01", "01-05", 1.1)], holidays: vec![] };
+        assert!(bad.validate().is_err());
+    }
+}
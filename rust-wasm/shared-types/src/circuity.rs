@@ -0,0 +1,199 @@
+//! Road circuity: how much longer the street route is than the straight
+//! line between two points.
+//!
+//! Cancún's supermanzanas are closed blocks ringed by avenues with
+//! retornos, so a straight-line distance underestimates what a car or a
+//! pedestrian actually covers. Each mode has a default factor; zones (a
+//! polygon with its own factor) override it where the street grid differs,
+//! and can be calibrated from traced route shapes, whose along-shape
+//! distances are real road distances.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::geo::{Contains, Coord, LineString, Point, Polygon};
+use crate::{haversine_distance, shape, TransportType};
+
+/// Stretch of a shape compared with its chord when calibrating.
+const CALIBRATION_SPAN_M: f64 = 2500.0;
+/// Fewer spans than this leave the zone uncalibrated.
+const MIN_CALIBRATION_SPANS: usize = 3;
+
+/// Straight-line factor when nothing more specific is known.
+pub fn default_factor(mode: &TransportType) -> f64 {
+    match mode {
+        // On foot or by bike there are shortcuts through plazas and parks
+        TransportType::Caminata | TransportType::Bicicleta => 1.2,
+        // Intercity and airport services run on highways
+        TransportType::ADO | TransportType::AdoAirport | TransportType::BusForaneo
+        | TransportType::Van | TransportType::VanForanea | TransportType::PlayaExpress => 1.15,
+        TransportType::Ferry => 1.0,
+        _ => 1.25,
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CircuityZone {
+    pub name: String,
+    /// Polygon `[lat, lng]`.
+    pub area: Vec<[f64; 2]>,
+    pub factor: f64,
+}
+
+impl CircuityZone {
+    fn polygon(&self) -> Polygon<f64> {
+        Polygon::new(self.area.iter().map(|p| Coord { x: p[1], y: p[0] }).collect::<LineString<f64>>(), vec![])
+    }
+
+    fn contains(&self, p: [f64; 2]) -> bool {
+        self.polygon().contains(&Point::new(p[1], p[0]))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct CircuityModel {
+    /// Mode label ("Caminata", "Uber", ...) → factor, over the defaults.
+    #[serde(default)]
+    pub modes: BTreeMap<String, f64>,
+    /// First match wins; the midpoint of the trip picks the zone.
+    #[serde(default)]
+    pub zones: Vec<CircuityZone>,
+}
+
+impl CircuityModel {
+    pub fn validate(&self) -> Result<(), String> {
+        for (mode, f) in &self.modes {
+            if TransportType::parse(mode).is_none() {
+                return Err(format!("Mode {}: unknown transport type", mode));
+            }
+            if !(1.0..=3.0).contains(f) {
+                return Err(format!("Mode {}: factor must be between 1 and 3", mode));
+            }
+        }
+        for z in &self.zones {
+            if z.area.len() < 3 {
+                return Err(format!("Zone {}: area needs at least 3 points", z.name));
+            }
+            if !(1.0..=3.0).contains(&z.factor) {
+                return Err(format!("Zone {}: factor must be between 1 and 3", z.name));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn factor(&self, mode: &TransportType, from: [f64; 2], to: [f64; 2]) -> f64 {
+        // Water has no streets
+        if *mode == TransportType::Ferry { return 1.0; }
+        let mid = [(from[0] + to[0]) / 2.0, (from[1] + to[1]) / 2.0];
+        if let Some(z) = self.zones.iter().find(|z| z.contains(mid)) {
+            return z.factor;
+        }
+        self.modes.iter()
+            .find(|(label, _)| TransportType::parse(label).as_ref() == Some(mode))
+            .map_or_else(|| default_factor(mode), |(_, f)| *f)
+    }
+
+    /// Estimated street distance between two points, meters.
+    pub fn road_distance_m(&self, mode: &TransportType, from: [f64; 2], to: [f64; 2]) -> f64 {
+        haversine_distance(from[0], from[1], to[0], to[1]) * self.factor(mode, from, to)
+    }
+}
+
+/// Zone factor from traced `shapes` (`[lat, lng]`): the median ratio of
+/// along-shape distance to straight line over spans of about 2.5 km lying
+/// inside `area`. `None` without enough spans.
+pub fn calibrate_zone(name: &str, area: Vec<[f64; 2]>, shapes: &[Vec<[f64; 2]>]) -> Option<CircuityZone> {
+    let mut zone = CircuityZone { name: name.to_string(), area, factor: 1.0 };
+    let mut ratios = Vec::new();
+    for s in shapes {
+        let cum = shape::cumulative_m(s);
+        let mut i = 0;
+        while let Some(j) = (i + 1..s.len()).find(|&j| cum[j] - cum[i] >= CALIBRATION_SPAN_M) {
+            let chord = haversine_distance(s[i][0], s[i][1], s[j][0], s[j][1]);
+            if chord > 0.0 && zone.contains(s[i]) && zone.contains(s[j]) {
+                ratios.push((cum[j] - cum[i]) / chord);
+            }
+            i = j;
+        }
+    }
+    if ratios.len() < MIN_CALIBRATION_SPANS { return None; }
+    ratios.sort_by(f64::total_cmp);
+    zone.factor = ((ratios[ratios.len() / 2] * 100.0).round() / 100.0).max(1.0);
+    Some(zone)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pairs of points 2.5 km apart along traced bus routes
+    /// (`public/data/routes/POLYLINE_*.json`) with the road distance between
+    /// them.
+    #[derive(Deserialize)]
+    struct RoadDistance {
+        from: [f64; 2],
+        to: [f64; 2],
+        road_m: f64,
+    }
+
+    fn road_distances() -> Vec<RoadDistance> {
+        serde_json::from_str(include_str!("../fixtures/road_distances.json")).unwrap()
+    }
+
+    fn median_error(pairs: &[RoadDistance], estimate: impl Fn(&RoadDistance) -> f64) -> f64 {
+        let mut e: Vec<f64> = pairs.iter().map(|p| (estimate(p) - p.road_m).abs() / p.road_m).collect();
+        e.sort_by(f64::total_cmp);
+        e[e.len() / 2]
+    }
+
+    #[test]
+    fn test_default_factor_beats_straight_line() {
+        let pairs = road_distances();
+        let model = CircuityModel::default();
+        let straight = median_error(&pairs, |p| haversine_distance(p.from[0], p.from[1], p.to[0], p.to[1]));
+        let corrected = median_error(&pairs, |p| model.road_distance_m(&TransportType::Bus, p.from, p.to));
+        assert!(corrected < straight, "{} vs {}", corrected, straight);
+        assert!(corrected < 0.15, "{}", corrected);
+    }
+
+    #[test]
+    fn test_calibrate_zone_from_shapes() {
+        #[derive(Deserialize)]
+        struct Vertex { lat: f64, lng: f64 }
+        #[derive(Deserialize)]
+        struct Traced { paradas: Vec<Vertex> }
+        let shapes: Vec<Vec<[f64; 2]>> = [
+            include_str!("../../../public/data/routes/POLYLINE_1464274794329.json"),
+            include_str!("../../../public/data/routes/POLYLINE_1760333218631.json"),
+            include_str!("../../../public/data/routes/POLYLINE_1761277331648.json"),
+        ].iter().map(|json| {
+            let t: Traced = serde_json::from_str(json).unwrap();
+            t.paradas.iter().map(|p| [p.lat, p.lng]).collect()
+        }).collect();
+
+        let city = vec![[21.0, -87.0], [21.3, -87.0], [21.3, -86.7], [21.0, -86.7]];
+        let zone = calibrate_zone("Cancún", city, &shapes).unwrap();
+        assert!((1.05..1.5).contains(&zone.factor), "{}", zone.factor);
+        let model = CircuityModel { zones: vec![zone], ..Default::default() };
+        let pairs = road_distances();
+        let straight = median_error(&pairs, |p| haversine_distance(p.from[0], p.from[1], p.to[0], p.to[1]));
+        assert!(median_error(&pairs, |p| model.road_distance_m(&TransportType::Bus, p.from, p.to)) < straight);
+
+        assert!(calibrate_zone("Mar", vec![[20.0, -86.0], [20.1, -86.0], [20.1, -85.9]], &shapes).is_none());
+    }
+
+    #[test]
+    fn test_zones_and_overrides() {
+        let model = CircuityModel {
+            modes: BTreeMap::from([("Caminata".to_string(), 1.4)]),
+            zones: vec![CircuityZone { name: "Centro".into(), area: vec![[21.15, -86.84], [21.17, -86.84], [21.17, -86.82], [21.15, -86.82]], factor: 1.5 }],
+        };
+        assert!(model.validate().is_ok());
+        assert_eq!(model.factor(&TransportType::Caminata, [21.10, -86.90], [21.11, -86.90]), 1.4);
+        assert_eq!(model.factor(&TransportType::Uber, [21.10, -86.90], [21.11, -86.90]), 1.25);
+        assert_eq!(model.factor(&TransportType::Uber, [21.155, -86.83], [21.165, -86.83]), 1.5);
+        assert_eq!(model.factor(&TransportType::Ferry, [21.155, -86.83], [21.165, -86.83]), 1.0);
+        let typo = CircuityModel { modes: BTreeMap::from([("Caminta".to_string(), 1.4)]), ..Default::default() };
+        assert!(typo.validate().unwrap_err().contains("Caminta"));
+    }
+}
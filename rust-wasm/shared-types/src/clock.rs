@@ -0,0 +1,159 @@
+//! Cancún local time. Cancún is UTC-5 all year (no DST since 2015).
+
+/// Cancún offset from UTC, seconds.
+pub const CANCUN_UTC_OFFSET_S: i64 = -5 * 3600;
+
+/// Current time, unix seconds.
+#[cfg(target_arch = "wasm32")]
+pub fn now_unix_s() -> i64 {
+    (js_sys::Date::now() / 1000.0) as i64
+}
+
+/// Current time, unix seconds.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now_unix_s() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Hour of day (0..=23) in Cancún.
+pub fn cancun_hour(unix_s: i64) -> u8 {
+    ((unix_s + CANCUN_UTC_OFFSET_S).rem_euclid(86_400) / 3600) as u8
+}
+
+/// Minutes since midnight (0..1440) in Cancún.
+pub fn cancun_minute_of_day(unix_s: i64) -> u32 {
+    ((unix_s + CANCUN_UTC_OFFSET_S).rem_euclid(86_400) / 60) as u32
+}
+
+/// `HH:MM` for minutes since midnight; wraps past 24h.
+pub fn format_hhmm(minutes: u32) -> String {
+    format!("{:02}:{:02}", (minutes / 60) % 24, minutes % 60)
+}
+
+/// Days since 1970-01-01 for a proleptic Gregorian date.
+pub(crate) fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (m + if m > 2 { -3 } else { 9 }) + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of `days_from_civil`: `(year, month, day)`.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if m <= 2 { era * 400 + yoe + 1 } else { era * 400 + yoe }, m, d)
+}
+
+/// Day of week for days since 1970-01-01, 0 = Monday.
+pub(crate) fn weekday_from_days(days: i64) -> u8 {
+    (days + 3).rem_euclid(7) as u8
+}
+
+/// Days since 1970-01-01 of the Cancún calendar day.
+fn cancun_days(unix_s: i64) -> i64 {
+    (unix_s + CANCUN_UTC_OFFSET_S).div_euclid(86_400)
+}
+
+/// `(year, month, day)` in Cancún.
+pub fn cancun_date(unix_s: i64) -> (i64, u32, u32) {
+    civil_from_days(cancun_days(unix_s))
+}
+
+/// Day of week in Cancún, 0 = Monday … 6 = Sunday.
+pub fn cancun_weekday(unix_s: i64) -> u8 {
+    weekday_from_days(cancun_days(unix_s))
+}
+
+/// `hour:00` Cancún time on the day of `unix_s`.
+pub fn at_hour(unix_s: i64, hour: u8) -> i64 {
+    cancun_days(unix_s) * 86_400 - CANCUN_UTC_OFFSET_S + hour as i64 * 3600
+}
+
+fn num(s: &str, what: &str) -> Result<i64, String> {
+    s.parse::<i64>().map_err(|_| format!("Invalid {} in date: {:?}", what, s))
+}
+
+/// Parses `YYYY-MM-DD`, `YYYY-MM-DDTHH:MM[:SS[.fff]]` with an optional `Z`
+/// or `±HH:MM` suffix. Without a suffix the time is Cancún local.
+pub fn parse_datetime(s: &str) -> Result<i64, String> {
+    let s = s.trim();
+    let (date, rest) = s.split_at(s.find(['T', ' ']).unwrap_or(s.len()));
+    let mut ymd = date.split('-');
+    let (Some(y), Some(m), Some(d), None) = (ymd.next(), ymd.next(), ymd.next(), ymd.next()) else {
+        return Err(format!("Invalid date: {:?}", s));
+    };
+    let (y, m, d) = (num(y, "year")?, num(m, "month")?, num(d, "day")?);
+    if !(1..=12).contains(&m) || !(1..=31).contains(&d) {
+        return Err(format!("Invalid date: {:?}", s));
+    }
+
+    let rest = rest.get(1..).unwrap_or("");
+    let (time, offset_s) = if let Some(t) = rest.strip_suffix('Z') {
+        (t, 0)
+    } else if let Some(pos) = rest.rfind(['+', '-']) {
+        let (t, off) = rest.split_at(pos);
+        let sign = if off.starts_with('-') { -1 } else { 1 };
+        let (oh, om) = off[1..].split_once(':').unwrap_or((&off[1..], "0"));
+        (t, sign * (num(oh, "offset")? * 3600 + num(om, "offset")? * 60))
+    } else {
+        (rest, CANCUN_UTC_OFFSET_S)
+    };
+
+    let mut secs = 0;
+    if !time.is_empty() {
+        let mut hms = time.split(':');
+        let h = num(hms.next().unwrap_or(""), "hour")?;
+        let mi = num(hms.next().ok_or_else(|| format!("Invalid time: {:?}", s))?, "minute")?;
+        let sec = match hms.next() {
+            Some(v) => num(v.split('.').next().unwrap_or(v), "second")?,
+            None => 0,
+        };
+        if h > 23 || mi > 59 || sec > 60 {
+            return Err(format!("Invalid time: {:?}", s));
+        }
+        secs = h * 3600 + mi * 60 + sec;
+    }
+    Ok(days_from_civil(y, m, d) * 86_400 + secs - offset_s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_datetime() {
+        assert_eq!(parse_datetime("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(parse_datetime("2026-04-15T08:33:33.284Z").unwrap(), 1_776_242_013);
+        // Local Cancún time is five hours behind UTC.
+        assert_eq!(parse_datetime("2026-04-15T03:33:33").unwrap(), 1_776_242_013);
+        assert_eq!(parse_datetime("2026-04-15T03:33:33-05:00").unwrap(), 1_776_242_013);
+        assert_eq!(parse_datetime("2026-04-15").unwrap() + 5 * 3600, parse_datetime("2026-04-15T10:00Z").unwrap());
+        assert!(parse_datetime("2026-13-01").is_err());
+        assert!(parse_datetime("ayer").is_err());
+        assert_eq!(cancun_hour(parse_datetime("2026-04-15T03:33:33Z").unwrap()), 22);
+        assert_eq!(cancun_minute_of_day(parse_datetime("2026-04-15T07:12").unwrap()), 7 * 60 + 12);
+        assert_eq!(format_hhmm(24 * 60 + 30), "00:30");
+    }
+
+    #[test]
+    fn test_cancun_calendar_day() {
+        // 23:30 in Cancún is already the next day in UTC.
+        let t = parse_datetime("2026-12-31T23:30").unwrap();
+        assert_eq!(cancun_date(t), (2026, 12, 31));
+        assert_eq!(cancun_weekday(t), 3);
+        assert_eq!(cancun_date(parse_datetime("2024-02-29").unwrap()), (2024, 2, 29));
+        assert_eq!(at_hour(t, 8), parse_datetime("2026-12-31T08:00").unwrap());
+    }
+}
@@ -0,0 +1,111 @@
+//! Travel times as distributions rather than single numbers.
+//!
+//! Every component (riding, waiting for the next vehicle, the chance of
+//! missing a connection) is a mean and a variance; independent components
+//! add both. Percentiles come from a lognormal with the same mean and
+//! variance (Fenton–Wilkinson), which keeps the long right tail of real
+//! travel times: a bus is rarely much early but often very late.
+
+use serde::{Deserialize, Serialize};
+use std::iter::Sum;
+use std::ops::Add;
+
+/// Standard normal quantile at 0.9.
+const Z90: f64 = 1.281_551_565_545;
+
+/// Minutes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct TravelTime {
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl TravelTime {
+    /// `mean` with a standard deviation of `cv · mean`.
+    pub fn with_cv(mean: f64, cv: f64) -> Self {
+        TravelTime { mean, variance: (cv * mean).powi(2) }
+    }
+
+    /// Waiting at a stop reached at a random moment: uniform over a headway.
+    pub fn wait(headway_min: f64) -> Self {
+        TravelTime { mean: headway_min / 2.0, variance: headway_min.powi(2) / 12.0 }
+    }
+
+    /// `penalty` minutes lost with probability `p`.
+    pub fn risk(p: f64, penalty: f64) -> Self {
+        TravelTime { mean: p * penalty, variance: p * (1.0 - p) * penalty.powi(2) }
+    }
+
+    fn quantile(&self, z: f64) -> f64 {
+        if self.mean <= 0.0 { return 0.0; }
+        let s2 = (1.0 + self.variance / self.mean.powi(2)).ln();
+        (self.mean.ln() - s2 / 2.0 + z * s2.sqrt()).exp()
+    }
+
+    pub fn p50(&self) -> f64 {
+        self.quantile(0.0)
+    }
+
+    pub fn p90(&self) -> f64 {
+        self.quantile(Z90)
+    }
+
+    /// "12–18 min", "~5 min" when the range rounds to one value,
+    /// "1h 05–1h 20" past the hour.
+    pub fn range_label(&self) -> String {
+        let (lo, hi) = (self.p50().round() as u32, self.p90().round() as u32);
+        let hm = |m: u32| format!("{}h {:02}", m / 60, m % 60);
+        match (lo, hi) {
+            (_, 0) => "< 1 min".to_string(),
+            (lo, hi) if lo == hi => format!("~{} min", lo),
+            (lo, hi) if hi < 60 => format!("{}–{} min", lo, hi),
+            (lo, hi) if lo < 60 => format!("{} min–{}", lo, hm(hi)),
+            (lo, hi) => format!("{}–{}", hm(lo), hm(hi)),
+        }
+    }
+}
+
+impl Add for TravelTime {
+    type Output = TravelTime;
+
+    fn add(self, o: TravelTime) -> TravelTime {
+        TravelTime { mean: self.mean + o.mean, variance: self.variance + o.variance }
+    }
+}
+
+impl Sum for TravelTime {
+    fn sum<I: Iterator<Item = TravelTime>>(iter: I) -> TravelTime {
+        iter.fold(TravelTime::default(), Add::add)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percentiles_and_label() {
+        let ride = TravelTime::with_cv(15.0, 0.2);
+        assert!(ride.p50() < 15.0 && ride.p90() > 15.0);
+        assert_eq!(ride.range_label(), "15–19 min");
+        assert_eq!(TravelTime::with_cv(4.2, 0.0).range_label(), "~4 min");
+        assert_eq!(TravelTime::with_cv(75.0, 0.2).range_label(), "1h 14–1h 35");
+        assert_eq!(TravelTime::default().range_label(), "< 1 min");
+    }
+
+    #[test]
+    fn test_legs_combine() {
+        let legs = [TravelTime::with_cv(10.0, 0.3), TravelTime::wait(12.0), TravelTime::with_cv(20.0, 0.3)];
+        let total: TravelTime = legs.iter().copied().sum();
+        assert_eq!(total.mean, 36.0);
+        // Independent spreads partly cancel: the p90 of the whole is below
+        // the sum of the legs' p90s.
+        let sum_p90: f64 = legs.iter().map(|l| l.p90()).sum();
+        assert!(total.p90() < sum_p90);
+        assert!(total.p90() > total.p50());
+
+        let miss = TravelTime::risk(0.2, 15.0);
+        assert_eq!(miss.mean, 3.0);
+        assert!((miss.variance - 36.0).abs() < 1e-9);
+    }
+}
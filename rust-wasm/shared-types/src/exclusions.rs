@@ -0,0 +1,95 @@
+//! Query-time exclusions: stops, routes and an area to keep out of a plan
+//! (road closures, events in the Zona Hotelera) without editing the catalog.
+
+use crate::geo::{Contains, Coord, Intersects, LineString, Point, Polygon};
+use crate::normalize::normalize_str;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Exclusions {
+    /// Stop names, stop ids or station ids.
+    #[serde(default)]
+    pub stops: Vec<String>,
+    #[serde(default)]
+    pub routes: Vec<String>,
+    /// Polygon `[lat, lng]` to stay out of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avoid_area: Option<Vec<[f64; 2]>>,
+}
+
+impl Exclusions {
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty() && self.routes.is_empty() && self.avoid_area.is_none()
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.avoid_area.as_ref().is_some_and(|a| a.len() < 3) {
+            return Err("avoid_area needs at least 3 points".to_string());
+        }
+        Ok(())
+    }
+
+    /// Prepared form for repeated tests.
+    pub fn matcher(&self) -> ExclusionMatcher<'_> {
+        let area = self.avoid_area.as_ref().map(|pts| {
+            Polygon::new(pts.iter().map(|p| Coord { x: p[1], y: p[0] }).collect::<LineString<f64>>(), vec![])
+        });
+        ExclusionMatcher {
+            exclusions: self,
+            stops_norm: self.stops.iter().map(|s| normalize_str(s)).collect(),
+            area,
+        }
+    }
+}
+
+pub struct ExclusionMatcher<'a> {
+    exclusions: &'a Exclusions,
+    stops_norm: Vec<String>,
+    area: Option<Polygon<f64>>,
+}
+
+impl ExclusionMatcher<'_> {
+    pub fn excludes_route(&self, route_id: &str) -> bool {
+        self.exclusions.routes.iter().any(|r| r == route_id)
+    }
+
+    /// `ids` are the stop's own id and station id, when it has them.
+    pub fn excludes_stop(&self, ids: &[&str], name: &str, lat: f64, lng: f64) -> bool {
+        ids.iter().any(|id| !id.is_empty() && self.exclusions.stops.iter().any(|s| s == id))
+            || self.stops_norm.contains(&normalize_str(name))
+            || self.in_area(lat, lng)
+    }
+
+    pub fn in_area(&self, lat: f64, lng: f64) -> bool {
+        self.area.as_ref().is_some_and(|a| a.contains(&Point::new(lng, lat)))
+    }
+
+    /// True when a `[lat, lng]` path enters the avoided area.
+    pub fn path_crosses_area(&self, path: &[[f64; 2]]) -> bool {
+        let Some(area) = &self.area else { return false };
+        let line: LineString<f64> = path.iter().map(|p| Coord { x: p[1], y: p[0] }).collect();
+        line.intersects(area)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matcher() {
+        let ex = Exclusions {
+            stops: vec!["Zona Hotelera".to_string(), "S1".to_string()],
+            routes: vec!["R1".to_string()],
+            avoid_area: Some(vec![[21.0, -86.8], [21.1, -86.8], [21.1, -86.7], [21.0, -86.7]]),
+        };
+        let m = ex.matcher();
+        assert!(m.excludes_route("R1") && !m.excludes_route("R2"));
+        assert!(m.excludes_stop(&[], "ZONA HOTELERA", 0.0, 0.0));
+        assert!(m.excludes_stop(&["S1"], "Otra", 0.0, 0.0));
+        assert!(m.excludes_stop(&[], "Km 9", 21.05, -86.75));
+        assert!(!m.excludes_stop(&[""], "Km 0", 21.15, -86.75));
+        assert!(m.path_crosses_area(&[[21.05, -86.9], [21.05, -86.6]]));
+        assert!(!m.path_crosses_area(&[[21.2, -86.9], [21.2, -86.6]]));
+    }
+}
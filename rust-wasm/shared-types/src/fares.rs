@@ -0,0 +1,264 @@
+//! Fares for on-demand modes: ride-hailing, motortaxis and street taxis.
+//!
+//! Apps charge a base plus distance and time, never less than a minimum,
+//! and add a surcharge for airport trips. Cancún's street taxis charge a
+//! fixed fare between zones instead; outside the zones, or to the airport,
+//! they fall back to their rate card. Fixed-fare modes (buses, vans, the
+//! ferry) keep `TransportType::base_fare`.
+
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+use crate::geo::{Contains, Coord, LineString, Point, Polygon};
+use crate::{haversine_distance, TransportType};
+
+/// MXN.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RateCard {
+    pub base: f64,
+    pub per_km: f64,
+    #[serde(default)]
+    pub per_min: f64,
+    #[serde(default)]
+    pub minimum: f64,
+    #[serde(default)]
+    pub airport_surcharge: f64,
+}
+
+impl RateCard {
+    fn price(&self, distance_m: f64, minutes: f64) -> f64 {
+        (self.base + self.per_km * distance_m / 1000.0 + self.per_min * minutes).max(self.minimum)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TaxiZone {
+    pub name: String,
+    /// Polygon `[lat, lng]`.
+    pub area: Vec<[f64; 2]>,
+}
+
+impl TaxiZone {
+    fn contains(&self, p: [f64; 2]) -> bool {
+        let ring: LineString<f64> = self.area.iter().map(|q| Coord { x: q[1], y: q[0] }).collect();
+        Polygon::new(ring, vec![]).contains(&Point::new(p[1], p[0]))
+    }
+}
+
+/// Taxi fare between two zones, either direction.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ZoneFare {
+    pub from: String,
+    pub to: String,
+    pub fare: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Airport {
+    pub name: String,
+    pub lat: f64,
+    pub lng: f64,
+    #[serde(default = "default_airport_radius_m")]
+    pub radius_m: f64,
+}
+
+fn default_airport_radius_m() -> f64 {
+    2500.0
+}
+
+/// Missing fields keep the Cancún defaults.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct FareTable {
+    /// Mode label ("Uber", "Taxi", ...) → rate card. Loaded cards replace
+    /// the default for their mode only.
+    #[serde(deserialize_with = "over_default_modes")]
+    pub modes: BTreeMap<String, RateCard>,
+    /// First match wins.
+    pub taxi_zones: Vec<TaxiZone>,
+    pub zone_fares: Vec<ZoneFare>,
+    pub airports: Vec<Airport>,
+}
+
+fn over_default_modes<'de, D: Deserializer<'de>>(d: D) -> Result<BTreeMap<String, RateCard>, D::Error> {
+    let loaded = BTreeMap::<String, RateCard>::deserialize(d)?;
+    let mut modes = FareTable::default().modes;
+    modes.retain(|label, _| !loaded.keys().any(|l| TransportType::parse(l) == TransportType::parse(label)));
+    modes.extend(loaded);
+    Ok(modes)
+}
+
+fn card(base: f64, per_km: f64, per_min: f64, minimum: f64, airport_surcharge: f64) -> RateCard {
+    RateCard { base, per_km, per_min, minimum, airport_surcharge }
+}
+
+fn rect(name: &str, south: f64, west: f64, north: f64, east: f64) -> TaxiZone {
+    TaxiZone { name: name.to_string(), area: vec![[south, west], [north, west], [north, east], [south, east]] }
+}
+
+impl Default for FareTable {
+    fn default() -> Self {
+        let modes = BTreeMap::from([
+            ("Uber".to_string(), card(25.0, 7.0, 1.8, 55.0, 80.0)),
+            ("Indriver".to_string(), card(20.0, 6.0, 1.2, 45.0, 80.0)),
+            ("MotorTaxi".to_string(), card(15.0, 5.0, 0.0, 20.0, 0.0)),
+            ("Taxi".to_string(), card(40.0, 12.0, 0.0, 60.0, 150.0)),
+        ]);
+        let taxi_zones = vec![
+            rect("Centro", 21.13, -86.88, 21.20, -86.81),
+            rect("Zona Hotelera Norte", 21.125, -86.81, 21.145, -86.74),
+            rect("Zona Hotelera Sur", 21.03, -86.80, 21.125, -86.74),
+        ];
+        let zone_fares = [
+            ("Centro", "Centro", 60.0),
+            ("Centro", "Zona Hotelera Norte", 150.0),
+            ("Centro", "Zona Hotelera Sur", 250.0),
+            ("Zona Hotelera Norte", "Zona Hotelera Norte", 100.0),
+            ("Zona Hotelera Norte", "Zona Hotelera Sur", 180.0),
+            ("Zona Hotelera Sur", "Zona Hotelera Sur", 120.0),
+        ].into_iter().map(|(from, to, fare)| ZoneFare { from: from.into(), to: to.into(), fare }).collect();
+        let airports = vec![Airport {
+            name: "Aeropuerto Internacional de Cancún".to_string(),
+            lat: 21.0365,
+            lng: -86.8771,
+            radius_m: default_airport_radius_m(),
+        }];
+        FareTable { modes, taxi_zones, zone_fares, airports }
+    }
+}
+
+impl FareTable {
+    pub fn validate(&self) -> Result<(), String> {
+        for (mode, c) in &self.modes {
+            if TransportType::parse(mode).is_none() {
+                return Err(format!("Mode {}: unknown transport type", mode));
+            }
+            let values = [c.base, c.per_km, c.per_min, c.minimum, c.airport_surcharge];
+            if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+                return Err(format!("Mode {}: rates must be zero or more", mode));
+            }
+        }
+        for z in &self.taxi_zones {
+            if z.area.len() < 3 {
+                return Err(format!("Zone {}: area needs at least 3 points", z.name));
+            }
+        }
+        for f in &self.zone_fares {
+            for name in [&f.from, &f.to] {
+                if !self.taxi_zones.iter().any(|z| &z.name == name) {
+                    return Err(format!("Zone fare {} - {}: unknown zone {}", f.from, f.to, name));
+                }
+            }
+            if !f.fare.is_finite() || f.fare < 0.0 {
+                return Err(format!("Zone fare {} - {}: fare must be zero or more", f.from, f.to));
+            }
+        }
+        for a in &self.airports {
+            if !(a.radius_m.is_finite() && a.radius_m > 0.0) {
+                return Err(format!("Airport {}: radius_m must be positive", a.name));
+            }
+        }
+        Ok(())
+    }
+
+    fn rate_card(&self, mode: &TransportType) -> Option<&RateCard> {
+        self.modes.iter().find(|(label, _)| TransportType::parse(label).as_ref() == Some(mode)).map(|(_, c)| c)
+    }
+
+    fn zone_of(&self, p: [f64; 2]) -> Option<&str> {
+        self.taxi_zones.iter().find(|z| z.contains(p)).map(|z| z.name.as_str())
+    }
+
+    fn zone_fare(&self, from: [f64; 2], to: [f64; 2]) -> Option<f64> {
+        let (a, b) = (self.zone_of(from)?, self.zone_of(to)?);
+        self.zone_fares.iter()
+            .find(|f| (f.from == a && f.to == b) || (f.from == b && f.to == a))
+            .map(|f| f.fare)
+    }
+
+    /// Whether either end is at an airport.
+    pub fn is_airport_trip(&self, from: [f64; 2], to: [f64; 2]) -> bool {
+        self.airports.iter().any(|a| {
+            [from, to].iter().any(|p| haversine_distance(p[0], p[1], a.lat, a.lng) <= a.radius_m)
+        })
+    }
+
+    /// Whole-peso fare for `mode` from `from` to `to` over `distance_m` of
+    /// street in `minutes`. `None` for modes without a rate card.
+    pub fn fare(&self, mode: &TransportType, from: [f64; 2], to: [f64; 2], distance_m: f64, minutes: f64) -> Option<f64> {
+        let airport = self.is_airport_trip(from, to);
+        if *mode == TransportType::Taxi && !airport {
+            if let Some(f) = self.zone_fare(from, to) {
+                return Some(f.round());
+            }
+        }
+        let card = self.rate_card(mode)?;
+        let surcharge = if airport { card.airport_surcharge } else { 0.0 };
+        Some((card.price(distance_m, minutes) + surcharge).round())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CRUCERO: [f64; 2] = [21.1714, -86.8219];
+    const PLAZA_LAS_AMERICAS: [f64; 2] = [21.1460, -86.8230];
+    const KM_9: [f64; 2] = [21.135, -86.768];
+    const KM_20: [f64; 2] = [21.075, -86.775];
+    const AIRPORT: [f64; 2] = [21.0400, -86.8740];
+
+    #[test]
+    fn test_ride_hail_scales_with_distance() {
+        let table = FareTable::default();
+        assert!(table.validate().is_ok());
+        // Short trips pay the minimum
+        assert_eq!(table.fare(&TransportType::Uber, CRUCERO, PLAZA_LAS_AMERICAS, 1500.0, 4.0), Some(55.0));
+        let long = table.fare(&TransportType::Uber, CRUCERO, KM_20, 22_000.0, 35.0).unwrap();
+        // Far more than the two buses it replaces
+        assert!(long > 4.0 * 2.0 * TransportType::Bus.base_fare(), "{}", long);
+        assert!(table.fare(&TransportType::Indriver, CRUCERO, KM_20, 22_000.0, 35.0).unwrap() < long);
+        assert_eq!(table.fare(&TransportType::Bus, CRUCERO, KM_20, 22_000.0, 35.0), None);
+    }
+
+    #[test]
+    fn test_airport_surcharge() {
+        let table = FareTable::default();
+        assert!(table.is_airport_trip(AIRPORT, KM_20));
+        let to_airport = table.fare(&TransportType::Uber, KM_20, AIRPORT, 12_000.0, 20.0).unwrap();
+        let plain = table.fare(&TransportType::Uber, KM_20, CRUCERO, 12_000.0, 20.0).unwrap();
+        assert_eq!(to_airport - plain, 80.0);
+    }
+
+    #[test]
+    fn test_taxi_zones() {
+        let table = FareTable::default();
+        // Fixed by zone, whatever the distance, either direction
+        assert_eq!(table.fare(&TransportType::Taxi, CRUCERO, KM_9, 9_000.0, 20.0), Some(150.0));
+        assert_eq!(table.fare(&TransportType::Taxi, KM_20, CRUCERO, 25_000.0, 40.0), Some(250.0));
+        assert_eq!(table.fare(&TransportType::Taxi, CRUCERO, PLAZA_LAS_AMERICAS, 2_000.0, 6.0), Some(60.0));
+        // The airport is off the zone table: rate card plus surcharge
+        assert_eq!(table.fare(&TransportType::Taxi, AIRPORT, KM_20, 15_000.0, 20.0), Some(40.0 + 180.0 + 150.0));
+
+        let bad = FareTable {
+            zone_fares: vec![ZoneFare { from: "Centro".into(), to: "Isla".into(), fare: 300.0 }],
+            ..Default::default()
+        };
+        assert!(bad.validate().is_err());
+        let loaded: FareTable = serde_json::from_str(r#"{"modes": {"Uber": {"base": 30, "per_km": 8}}}"#).unwrap();
+        assert_eq!(loaded.taxi_zones.len(), 3);
+        assert_eq!(loaded.fare(&TransportType::Uber, CRUCERO, KM_9, 10_000.0, 15.0), Some(110.0));
+    }
+
+    #[test]
+    fn test_loaded_cards_keep_other_defaults() {
+        let loaded: FareTable = serde_json::from_str(r#"{"modes": {"Uber": {"base": 30, "per_km": 8}}}"#).unwrap();
+        let defaults = FareTable::default();
+        assert_eq!(loaded.modes.len(), defaults.modes.len());
+        assert_eq!(loaded.modes["Indriver"], defaults.modes["Indriver"]);
+        assert_eq!(loaded.modes["Uber"].base, 30.0);
+
+        let typo: FareTable = serde_json::from_str(r#"{"modes": {"Ubr": {"base": 30, "per_km": 8}}}"#).unwrap();
+        assert!(typo.validate().unwrap_err().contains("Ubr"));
+    }
+}
@@ -0,0 +1,270 @@
+//! Ferry timetables for the Isla Mujeres and Cozumel crossings.
+//!
+//! Ferries leave at fixed times rather than at a headway, so a ferry leg is
+//! the wait until the next listed departure plus the crossing. Each line
+//! runs one way between two terminals and charges a tourist or a resident
+//! fare. Mainland terminals name the catalog stop (`hub`) where bus routes
+//! meet them. The defaults follow the operators' published timetables;
+//! load the current ones when they change.
+
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{self, CANCUN_UTC_OFFSET_S};
+use crate::normalize::normalize_str;
+use crate::schedule::parse_clock;
+use crate::haversine_distance;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FareClass {
+    #[default]
+    Tourist,
+    /// Quintana Roo residents with ID
+    Resident,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FerryFares {
+    pub tourist: f64,
+    pub resident: f64,
+}
+
+impl FerryFares {
+    pub fn fare(&self, class: FareClass) -> f64 {
+        match class {
+            FareClass::Tourist => self.tourist,
+            FareClass::Resident => self.resident,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FerryTerminal {
+    pub id: String,
+    pub name: String,
+    pub lat: f64,
+    pub lng: f64,
+    /// Catalog stop served by bus routes, mainland terminals only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hub: Option<String>,
+}
+
+/// One direction of a crossing.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FerryLine {
+    pub id: String,
+    pub operator: String,
+    /// Terminal ids.
+    pub from: String,
+    pub to: String,
+    pub crossing_min: u32,
+    /// `HH:MM`, Cancún time, every day.
+    pub departures: Vec<String>,
+    pub fares: FerryFares,
+}
+
+impl FerryLine {
+    /// Departures in minutes since midnight, sorted.
+    fn departure_minutes(&self) -> Vec<u32> {
+        let mut m: Vec<u32> = self.departures.iter().filter_map(|d| parse_clock(d.trim())).map(|m| m % 1440).collect();
+        m.sort_unstable();
+        m.dedup();
+        m
+    }
+}
+
+/// A departure placed on the clock.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Crossing {
+    pub line_id: String,
+    pub operator: String,
+    pub from: String,
+    pub to: String,
+    /// `HH:MM`
+    pub depart: String,
+    pub arrive: String,
+    pub depart_s: i64,
+    pub arrive_s: i64,
+    pub wait_min: u32,
+    pub crossing_min: u32,
+    pub fare_mxn: f64,
+    /// Until the line's following departure: the cost of missing this one.
+    pub next_gap_min: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FerryTimetable {
+    pub terminals: Vec<FerryTerminal>,
+    pub lines: Vec<FerryLine>,
+}
+
+/// `HH:MM` every `step` minutes from `first` to `last`, inclusive.
+fn every(first: u32, last: u32, step: u32) -> Vec<String> {
+    (first..=last).step_by(step as usize).map(clock::format_hhmm).collect()
+}
+
+impl Default for FerryTimetable {
+    fn default() -> Self {
+        let terminal = |id: &str, name: &str, lat: f64, lng: f64, hub: Option<&str>| FerryTerminal {
+            id: id.into(), name: name.into(), lat, lng, hub: hub.map(String::from),
+        };
+        let line = |id: &str, operator: &str, from: &str, to: &str, crossing_min: u32, departures: Vec<String>, tourist: f64, resident: f64| FerryLine {
+            id: id.into(), operator: operator.into(), from: from.into(), to: to.into(),
+            crossing_min, departures, fares: FerryFares { tourist, resident },
+        };
+        FerryTimetable {
+            terminals: vec![
+                terminal("ultramar", "Muelle Ultramar (Gran Puerto)", 21.1819, -86.8028, Some("Muelle Ultramar (Puerto Juárez)")),
+                terminal("puerto_juarez", "Puerto Juárez", 21.1862, -86.8036, Some("Muelle Ultramar (Puerto Juárez)")),
+                terminal("isla_mujeres", "Isla Mujeres", 21.2580, -86.7490, None),
+                terminal("playa_del_carmen", "Muelle Playa del Carmen", 20.6237, -87.0735, Some("Playa del Carmen Centro (Calle 2 x 5ta Av.)")),
+                terminal("cozumel", "Cozumel (Muelle Fiscal)", 20.5108, -86.9497, None),
+            ],
+            lines: vec![
+                line("ULTRAMAR_CUN_ISLA", "Ultramar", "ultramar", "isla_mujeres", 20, every(5 * 60, 23 * 60 + 30, 30), 300.0, 120.0),
+                line("ULTRAMAR_ISLA_CUN", "Ultramar", "isla_mujeres", "ultramar", 20, every(5 * 60 + 30, 23 * 60 + 30, 30), 300.0, 120.0),
+                line("MARITIMA_PJ_ISLA", "Marítima Isla Mujeres", "puerto_juarez", "isla_mujeres", 25, every(6 * 60, 23 * 60, 60), 200.0, 60.0),
+                line("MARITIMA_ISLA_PJ", "Marítima Isla Mujeres", "isla_mujeres", "puerto_juarez", 25, every(6 * 60 + 30, 23 * 60 + 30, 60), 200.0, 60.0),
+                line("ULTRAMAR_PDC_CZM", "Ultramar", "playa_del_carmen", "cozumel", 40, every(7 * 60, 22 * 60, 60), 420.0, 150.0),
+                line("ULTRAMAR_CZM_PDC", "Ultramar", "cozumel", "playa_del_carmen", 40, every(6 * 60, 21 * 60, 60), 420.0, 150.0),
+            ],
+        }
+    }
+}
+
+impl FerryTimetable {
+    pub fn validate(&self) -> Result<(), String> {
+        for t in &self.terminals {
+            if !(-90.0..=90.0).contains(&t.lat) || !(-180.0..=180.0).contains(&t.lng) {
+                return Err(format!("Terminal {}: invalid coordinates", t.id));
+            }
+        }
+        for l in &self.lines {
+            for id in [&l.from, &l.to] {
+                if self.terminal(id).is_none() {
+                    return Err(format!("Line {}: unknown terminal {}", l.id, id));
+                }
+            }
+            if l.from == l.to {
+                return Err(format!("Line {}: from and to are the same terminal", l.id));
+            }
+            if l.crossing_min == 0 {
+                return Err(format!("Line {}: crossing_min must be positive", l.id));
+            }
+            if l.departures.is_empty() {
+                return Err(format!("Line {}: no departures", l.id));
+            }
+            if let Some(d) = l.departures.iter().find(|d| parse_clock(d.trim()).is_none()) {
+                return Err(format!("Line {}: departure {:?} is not HH:MM", l.id, d));
+            }
+            if [l.fares.tourist, l.fares.resident].iter().any(|f| !f.is_finite() || *f < 0.0) {
+                return Err(format!("Line {}: fares must be zero or more", l.id));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn terminal(&self, id: &str) -> Option<&FerryTerminal> {
+        self.terminals.iter().find(|t| t.id == id)
+    }
+
+    /// Terminal by id, name or hub stop name.
+    pub fn terminal_named(&self, name: &str) -> Option<&FerryTerminal> {
+        let n = normalize_str(name);
+        self.terminals.iter().find(|t| t.id == name)
+            .or_else(|| self.terminals.iter().find(|t| normalize_str(&t.name) == n))
+            .or_else(|| self.terminals.iter().find(|t| t.hub.as_deref().is_some_and(|h| normalize_str(h) == n)))
+    }
+
+    /// Closest terminal within `max_m` of `p` (`[lat, lng]`).
+    pub fn terminal_near(&self, p: [f64; 2], max_m: f64) -> Option<&FerryTerminal> {
+        self.terminals.iter()
+            .map(|t| (t, haversine_distance(p[0], p[1], t.lat, t.lng)))
+            .filter(|(_, d)| *d <= max_m)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(t, _)| t)
+    }
+
+    /// Lines from terminal `from` to terminal `to`.
+    pub fn lines_between<'a>(&'a self, from: &'a str, to: &'a str) -> impl Iterator<Item = &'a FerryLine> + 'a {
+        self.lines.iter().filter(move |l| l.from == from && l.to == to)
+    }
+
+    /// Up to `limit` crossings from `from` to `to` leaving at or after
+    /// `unix_s`, earliest arrival first, over today and tomorrow.
+    pub fn next_crossings(&self, from: &str, to: &str, unix_s: i64, class: FareClass, limit: usize) -> Vec<Crossing> {
+        let midnight = unix_s - (unix_s + CANCUN_UTC_OFFSET_S).rem_euclid(86_400);
+        let hhmm = |t: i64| clock::format_hhmm(clock::cancun_minute_of_day(t));
+        let mut out = Vec::new();
+        for line in self.lines_between(from, to) {
+            let minutes = line.departure_minutes();
+            let times: Vec<i64> = [0, 1440, 2880].iter()
+                .flat_map(|day| minutes.iter().map(move |m| midnight + (day + *m as i64) * 60))
+                .collect();
+            for (i, &depart_s) in times.iter().enumerate().filter(|(_, &t)| t >= unix_s).take(limit) {
+                let Some(&following) = times.get(i + 1) else { break };
+                let arrive_s = depart_s + line.crossing_min as i64 * 60;
+                out.push(Crossing {
+                    line_id: line.id.clone(),
+                    operator: line.operator.clone(),
+                    from: line.from.clone(),
+                    to: line.to.clone(),
+                    depart: hhmm(depart_s),
+                    arrive: hhmm(arrive_s),
+                    depart_s,
+                    arrive_s,
+                    wait_min: ((depart_s - unix_s) / 60) as u32,
+                    crossing_min: line.crossing_min,
+                    fare_mxn: line.fares.fare(class),
+                    next_gap_min: ((following - depart_s) / 60) as u32,
+                });
+            }
+        }
+        out.sort_by(|a, b| a.arrive_s.cmp(&b.arrive_s).then(a.fare_mxn.total_cmp(&b.fare_mxn)));
+        out.truncate(limit);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::parse_datetime;
+
+    #[test]
+    fn test_next_crossings() {
+        let table = FerryTimetable::default();
+        assert!(table.validate().is_ok());
+        let t = parse_datetime("2026-05-12T10:10").unwrap();
+        let c = table.next_crossings("ultramar", "isla_mujeres", t, FareClass::Tourist, 2);
+        assert_eq!(c.len(), 2);
+        assert_eq!((c[0].depart.as_str(), c[0].arrive.as_str(), c[0].wait_min), ("10:30", "10:50", 20));
+        assert_eq!(c[0].next_gap_min, 30);
+        assert_eq!(c[1].depart, "11:00");
+        let resident = table.next_crossings("ultramar", "isla_mujeres", t, FareClass::Resident, 1);
+        assert!(resident[0].fare_mxn < c[0].fare_mxn);
+
+        // After the last boat the first one tomorrow
+        let late = parse_datetime("2026-05-12T22:30").unwrap();
+        let c = table.next_crossings("playa_del_carmen", "cozumel", late, FareClass::Tourist, 1);
+        assert_eq!(c[0].depart, "07:00");
+        assert_eq!(c[0].depart_s - late, (8 * 60 + 30) * 60);
+        assert!(table.next_crossings("isla_mujeres", "cozumel", t, FareClass::Tourist, 1).is_empty());
+    }
+
+    #[test]
+    fn test_terminal_lookup_and_validation() {
+        let table = FerryTimetable::default();
+        assert_eq!(table.terminal_named("Isla Mujeres").unwrap().id, "isla_mujeres");
+        assert_eq!(table.terminal_named("muelle ultramar puerto juarez").unwrap().id, "ultramar");
+        assert_eq!(table.terminal_near([21.2575, -86.7495], 1000.0).unwrap().id, "isla_mujeres");
+        assert!(table.terminal_near([21.16, -86.85], 1000.0).is_none());
+
+        let mut bad = table.clone();
+        bad.lines[0].departures.push("25:00".into());
+        assert!(bad.validate().is_err());
+        bad.lines[0].departures.pop();
+        bad.lines[0].to = "holbox".into();
+        assert!(bad.validate().is_err());
+    }
+}
@@ -1,5 +1,22 @@
 use serde::{Deserialize, Serialize};
 
+/// Re-exported so engines share one version of the geometry types.
+pub use geo;
+
+pub mod calendar;
+pub mod circuity;
+pub mod clock;
+pub mod distribution;
+pub mod exclusions;
+pub mod fares;
+pub mod ferry;
+pub mod normalize;
+pub mod polyline;
+pub mod ride;
+pub mod schedule;
+pub mod shape;
+pub mod weather;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
 pub enum TransportType {
     // Legacy Variants (Keep for compatibility)
@@ -42,9 +59,41 @@ pub enum TransportType {
     Uber,
     #[serde(rename = "Ferry")]
     Ferry,
+    /// Cancún street taxi, priced by zone
+    #[serde(rename = "Taxi")]
+    Taxi,
 }
 
 impl TransportType {
+    /// Mode from a catalog / UI label ("Bus_Urbano", "ADO_Airport", ...),
+    /// `None` when unknown.
+    pub fn parse(s: &str) -> Option<Self> {
+        Some(match s {
+            "Bus" | "Bus_Urban" | "BusUrban" | "Bus_Urbano" | "BusUrbano" => TransportType::Bus,
+            "Bus_HotelZone" | "BusHotelZone" => TransportType::BusHotelZone,
+            "Combi" | "Combi_Municipal" | "CombiMunicipal" => TransportType::Combi,
+            "Van" | "Van_Foranea" | "VanForanea" => TransportType::Van,
+            "ADO" => TransportType::ADO,
+            "ADO_Airport" | "AdoAirport" => TransportType::AdoAirport,
+            "Bus_Foraneo" | "BusForaneo" => TransportType::BusForaneo,
+            "PlayaExpress" | "Playa_Express" => TransportType::PlayaExpress,
+            "MotorTaxi" => TransportType::MotorTaxi,
+            "Bicicleta" => TransportType::Bicicleta,
+            "Caminata" => TransportType::Caminata,
+            "Indriver" => TransportType::Indriver,
+            "Uber" => TransportType::Uber,
+            "Ferry" => TransportType::Ferry,
+            "Taxi" => TransportType::Taxi,
+            _ => return None,
+        })
+    }
+
+    /// Like `parse`, but unknown labels fall back to `Bus`, like the engines
+    /// always did.
+    pub fn from_label(s: &str) -> Self {
+        Self::parse(s).unwrap_or(TransportType::Bus)
+    }
+
     /// Average speed in km/h for ETA estimation
     pub fn avg_speed_kmh(&self) -> f64 {
         match self {
@@ -57,7 +106,7 @@ impl TransportType {
             TransportType::MotorTaxi => 30.0,
             TransportType::Bicicleta => 15.0,
             TransportType::Caminata => 5.0,
-            TransportType::Indriver | TransportType::Uber => 40.0,
+            TransportType::Indriver | TransportType::Uber | TransportType::Taxi => 40.0,
             TransportType::Ferry => 25.0,
         }
     }
@@ -75,6 +124,7 @@ impl TransportType {
             TransportType::Bicicleta => 0.0,
             TransportType::Caminata => 0.0,
             TransportType::Indriver | TransportType::Uber => 130.0,
+            TransportType::Taxi => 150.0,
             TransportType::Ferry => 50.0,
         }
     }
@@ -95,6 +145,7 @@ impl TransportType {
             TransportType::Caminata => 0.0,
             TransportType::Indriver => 45.0,
             TransportType::Uber => 55.0,
+            TransportType::Taxi => 60.0,
             TransportType::Ferry => 35.0,
         }
     }
@@ -175,3 +226,21 @@ impl TrafficConditions {
         Self { congestion_factor: congestion, rain_factor: 1.0, is_rush_hour: is_rush, hour }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_labels() {
+        assert_eq!(TransportType::parse("Bus_Foraneo"), Some(TransportType::BusForaneo));
+        assert_eq!(TransportType::parse("BusForaneo"), Some(TransportType::BusForaneo));
+        assert_eq!(TransportType::parse("CombiMunicipal"), Some(TransportType::Combi));
+        assert_eq!(TransportType::parse("VanForanea"), Some(TransportType::Van));
+        assert_eq!(TransportType::parse("Bus_Urbano"), Some(TransportType::Bus));
+        assert_eq!(TransportType::parse("Playa_Express"), Some(TransportType::PlayaExpress));
+        assert_eq!(TransportType::parse("Tren Maya"), None);
+        assert_eq!(TransportType::from_label("Tren Maya"), TransportType::Bus);
+        assert_eq!(TransportType::from_label("AdoAirport"), TransportType::AdoAirport);
+    }
+}
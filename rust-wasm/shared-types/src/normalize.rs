@@ -0,0 +1,111 @@
+//! Stop name normalization.
+//!
+//! Pipeline: Unicode canonical decomposition (NFD) with combining marks
+//! dropped, lowercase, punctuation to spaces, whitespace split, Spanish
+//! stop words removed. "Av. Tulum", "AV TULUM" and "Av Tulum" all give
+//! `av tulum`; "Plaza Las Américas" gives `plaza americas`.
+
+use std::collections::BTreeSet;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Articles and prepositions that never tell two stops apart.
+const STOP_WORDS: &[&str] = &["de", "del", "la", "las", "el", "los", "y", "en", "al"];
+
+pub fn is_stop_word(w: &str) -> bool {
+    STOP_WORDS.contains(&w)
+}
+
+/// Accent-free, lowercase text with punctuation turned into single spaces.
+/// Keeps every word; see `tokens` for the matching form.
+pub fn fold(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut pending_space = false;
+    for c in s.nfd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() {
+            if pending_space && !out.is_empty() {
+                out.push(' ');
+            }
+            pending_space = false;
+            out.extend(c.to_lowercase());
+        } else {
+            pending_space = true;
+        }
+    }
+    out
+}
+
+/// Matching tokens in text order. A name made only of stop words
+/// ("La", "El") keeps them rather than vanishing.
+pub fn tokens(s: &str) -> Vec<String> {
+    let folded = fold(s);
+    let all: Vec<&str> = folded.split(' ').filter(|t| !t.is_empty()).collect();
+    let kept: Vec<&str> = all.iter().copied().filter(|t| !is_stop_word(t)).collect();
+    let chosen = if kept.is_empty() { all } else { kept };
+    chosen.into_iter().map(str::to_string).collect()
+}
+
+/// Order-insensitive form of `tokens`, for "ADO Centro Terminal" vs
+/// "Terminal ADO Centro".
+pub fn token_set(s: &str) -> BTreeSet<String> {
+    tokens(s).into_iter().collect()
+}
+
+/// Lookup key used by the router: `tokens` joined by single spaces.
+pub fn normalize_str(s: &str) -> String {
+    tokens(s).join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const MASTER_ROUTES: &str = include_str!("../../../public/data/master_routes.json");
+
+    fn catalog_stop_names() -> Vec<String> {
+        let v: serde_json::Value = serde_json::from_str(MASTER_ROUTES).unwrap();
+        v["rutas"].as_array().unwrap().iter()
+            .flat_map(|r| r["paradas"].as_array().unwrap().iter())
+            .filter_map(|s| s["nombre"].as_str().or(s["parada"].as_str()).map(str::to_string))
+            .collect()
+    }
+
+    #[test]
+    fn test_punctuation_case_and_accents() {
+        assert_eq!(normalize_str("Av. Tulum"), normalize_str("Av Tulum"));
+        assert_eq!(normalize_str("  AV.   TULUM "), "av tulum");
+        assert_eq!(normalize_str("Plaza Las Américas"), "plaza americas");
+        assert_eq!(normalize_str("PLAZA LAS AMÉRICAS"), normalize_str("plaza las americas"));
+        assert_eq!(normalize_str("Muelle Ultramar (Puerto Juárez)"), "muelle ultramar puerto juarez");
+        assert_eq!(normalize_str("Chedraui Lakin / Av. Talleres"), "chedraui lakin av talleres");
+        assert_eq!(normalize_str("Parada RUTA 18-A #9"), "parada ruta 18 a 9");
+        assert_eq!(normalize_str("Ñichupté"), "nichupte");
+        assert_eq!(normalize_str("El"), "el");
+        // Precomposed and decomposed accents agree.
+        assert_eq!(normalize_str("Cancu\u{0301}n"), normalize_str("Cancún"));
+    }
+
+    #[test]
+    fn test_real_catalog_names() {
+        let names = catalog_stop_names();
+        assert!(names.len() > 1000);
+        let mut by_key: HashMap<String, Vec<&str>> = HashMap::new();
+        for name in &names {
+            let key = normalize_str(name);
+            assert!(!key.is_empty(), "{:?} normalizes to nothing", name);
+            assert!(key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == ' '), "{:?} -> {:?}", name, key);
+            assert_eq!(normalize_str(&key), key, "not idempotent for {:?}", name);
+            by_key.entry(key).or_default().push(name);
+        }
+        // Catalog spellings that used to be distinct keys now meet.
+        assert_eq!(normalize_str("Carr. Federal 307"), "carr federal 307");
+        assert_eq!(normalize_str("Parque Las Palapas"), normalize_str("Parque de las Palapas"));
+        assert_eq!(normalize_str("Plaza Las Américas (Kabah)"), "plaza americas kabah");
+        assert!(by_key["crucero"].contains(&"El Crucero"));
+        assert!(by_key["puerto cancun"].contains(&"Puerto Cancun"));
+        // Same words, different order: only the token set matches.
+        assert_ne!(normalize_str("Terminal ADO Centro Cancún"), normalize_str("Terminal ADO Cancún Centro"));
+        assert_eq!(token_set("Terminal ADO Centro Cancún"), token_set("Terminal ADO Cancún Centro"));
+    }
+}
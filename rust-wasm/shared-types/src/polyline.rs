@@ -0,0 +1,98 @@
+//! Google encoded polyline format with configurable precision.
+//!
+//! Points are `[lat, lng]`. Precision 5 is Google's default; 6 is what
+//! OSRM/Valhalla call "polyline6".
+
+pub const DEFAULT_PRECISION: u32 = 5;
+const MAX_PRECISION: u32 = 10;
+
+fn factor(precision: u32) -> Result<f64, String> {
+    if precision == 0 || precision > MAX_PRECISION {
+        return Err(format!("Polyline precision must be 1..={}, got {}", MAX_PRECISION, precision));
+    }
+    Ok(10f64.powi(precision as i32))
+}
+
+fn encode_value(mut v: i64, out: &mut String) {
+    v = if v < 0 { !(v << 1) } else { v << 1 };
+    while v >= 0x20 {
+        out.push((((v & 0x1f) | 0x20) as u8 + 63) as char);
+        v >>= 5;
+    }
+    out.push((v as u8 + 63) as char);
+}
+
+pub fn encode(points: &[[f64; 2]], precision: u32) -> Result<String, String> {
+    let f = factor(precision)?;
+    let mut out = String::with_capacity(points.len() * 8);
+    let (mut prev_lat, mut prev_lng) = (0i64, 0i64);
+    for p in points {
+        let lat = (p[0] * f).round() as i64;
+        let lng = (p[1] * f).round() as i64;
+        encode_value(lat - prev_lat, &mut out);
+        encode_value(lng - prev_lng, &mut out);
+        prev_lat = lat;
+        prev_lng = lng;
+    }
+    Ok(out)
+}
+
+pub fn decode(encoded: &str, precision: u32) -> Result<Vec<[f64; 2]>, String> {
+    let f = factor(precision)?;
+    let bytes = encoded.as_bytes();
+    let mut i = 0;
+    let mut next = || -> Result<Option<i64>, String> {
+        if i >= bytes.len() { return Ok(None); }
+        let (mut result, mut shift) = (0i64, 0u32);
+        loop {
+            let b = *bytes.get(i).ok_or("Truncated polyline")? as i64 - 63;
+            if !(0..64).contains(&b) || shift > 60 {
+                return Err(format!("Invalid polyline byte at {}", i));
+            }
+            i += 1;
+            result |= (b & 0x1f) << shift;
+            shift += 5;
+            if b < 0x20 { break; }
+        }
+        Ok(Some(if result & 1 == 1 { !(result >> 1) } else { result >> 1 }))
+    };
+
+    let mut points = Vec::new();
+    let (mut lat, mut lng) = (0i64, 0i64);
+    while let Some(d_lat) = next()? {
+        let d_lng = next()?.ok_or("Polyline ends between latitude and longitude")?;
+        lat += d_lat;
+        lng += d_lng;
+        points.push([lat as f64 / f, lng as f64 / f]);
+    }
+    Ok(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reference example from Google's polyline algorithm documentation.
+    const GOOGLE_POINTS: [[f64; 2]; 3] = [[38.5, -120.2], [40.7, -120.95], [43.252, -126.453]];
+    const GOOGLE_ENCODED: &str = "_p~iF~ps|U_ulLnnqC_mqNvxq`@";
+
+    #[test]
+    fn test_known_vector() {
+        assert_eq!(encode(&GOOGLE_POINTS, 5).unwrap(), GOOGLE_ENCODED);
+        assert_eq!(decode(GOOGLE_ENCODED, 5).unwrap(), GOOGLE_POINTS.to_vec());
+        // Same points at precision 6 are scaled by 10 before encoding.
+        assert_eq!(encode(&GOOGLE_POINTS, 6).unwrap(), "_izlhA~rlgdF_{geC~ywl@_kwzCn`{nI");
+    }
+
+    #[test]
+    fn test_round_trip_cancun_shape() {
+        let shape = [[21.1714, -86.8219], [21.1619, -86.8515], [21.1367, -86.7464], [21.0362, -86.7791]];
+        for precision in [5, 6] {
+            let decoded = decode(&encode(&shape, precision).unwrap(), precision).unwrap();
+            let tol = 0.6 / 10f64.powi(precision as i32);
+            assert!(decoded.iter().zip(&shape).all(|(a, b)| (a[0] - b[0]).abs() < tol && (a[1] - b[1]).abs() < tol));
+        }
+        assert!(decode("_p~iF", 5).is_err());
+        assert!(encode(&shape, 0).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! In-vehicle time, the same for `eta-engine` ETAs and `route-calculator`
+//! itineraries.
+//!
+//! A ride covers its distance at the mode's average speed slowed by the
+//! traffic (congestion times rain), plus a dwell at every stop. A speed
+//! observed on the route (a calibrated profile) already includes congestion
+//! and dwell, so only rain slows it further.
+
+use crate::{TrafficConditions, TransportType};
+
+/// Dwell per stop, minutes.
+pub const DWELL_MIN: f64 = 0.5;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ride {
+    pub minutes: f64,
+    /// After traffic.
+    pub speed_kmh: f64,
+    /// Applied to the mode's average speed; for an observed speed, how much
+    /// slower than the average it is.
+    pub congestion_factor: f64,
+}
+
+/// Speed at `base_speed_kmh` under `congestion_factor` and the rain in
+/// `traffic`.
+pub fn speed_kmh(base_speed_kmh: f64, congestion_factor: f64, traffic: &TrafficConditions) -> f64 {
+    base_speed_kmh / (congestion_factor * traffic.rain_factor)
+}
+
+/// Minutes over `distance_km` at `speed_kmh` stopping `stops` times.
+pub fn minutes(distance_km: f64, speed_kmh: f64, stops: u32) -> f64 {
+    distance_km / speed_kmh * 60.0 + stops as f64 * DWELL_MIN
+}
+
+/// `transport` over `distance_m` with `stops` stops under `traffic`, or at
+/// `observed_kmh` when the route has a calibrated speed.
+pub fn ride(transport: &TransportType, distance_m: f64, stops: u32, traffic: &TrafficConditions, observed_kmh: Option<f64>) -> Ride {
+    let base = transport.avg_speed_kmh();
+    let distance_km = distance_m / 1000.0;
+    match observed_kmh {
+        Some(observed) => {
+            let speed = observed / traffic.rain_factor;
+            Ride { minutes: minutes(distance_km, speed, 0), speed_kmh: speed, congestion_factor: base / observed }
+        }
+        None => {
+            let speed = speed_kmh(base, traffic.congestion_factor, traffic);
+            Ride { minutes: minutes(distance_km, speed, stops), speed_kmh: speed, congestion_factor: traffic.congestion_factor }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_traffic_and_dwell() {
+        // 22 km/h
+        let free = ride(&TransportType::Bus, 11_000.0, 4, &TrafficConditions::free_flow(), None);
+        assert_eq!(free.speed_kmh, 22.0);
+        assert!((free.minutes - (30.0 + 2.0)).abs() < 1e-9);
+
+        let rush = TrafficConditions::from_hour(18);
+        let slow = ride(&TransportType::Bus, 11_000.0, 4, &rush, None);
+        assert!((slow.minutes - (30.0 * 2.5 + 2.0)).abs() < 1e-9);
+        assert_eq!(slow.congestion_factor, 2.5);
+    }
+
+    #[test]
+    fn test_observed_speed_only_takes_rain() {
+        let rain = TrafficConditions { rain_factor: 1.25, ..TrafficConditions::from_hour(18) };
+        let r = ride(&TransportType::Bus, 6000.0, 4, &rain, Some(12.0));
+        assert_eq!(r.speed_kmh, 12.0 / 1.25);
+        assert!((r.minutes - 6.0 / (12.0 / 1.25) * 60.0).abs() < 1e-9);
+        assert!((r.congestion_factor - 22.0 / 12.0).abs() < 1e-9);
+    }
+}
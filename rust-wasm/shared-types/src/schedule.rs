@@ -0,0 +1,195 @@
+//! Typed service windows parsed from the free-text `horario` fields.
+//!
+//! Fields hold a time ("05:00"), a range ("04:00-23:00", "06:00 - 22:30")
+//! or a range with a trailing note ("03:00 - 05:00 (Sistema de llenado en
+//! OXXO)"). An end at or before the start runs past midnight. Official hours
+//! win over `inicio`/`fin` when both are given.
+
+use serde::{Deserialize, Serialize};
+
+/// `horario` of a catalog route, as written.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct Schedule {
+    pub inicio: Option<String>,
+    pub fin: Option<String>,
+    pub inicio_oficial: Option<String>,
+    pub fin_oficial: Option<String>,
+    pub guardia_nocturna: Option<String>,
+}
+
+/// Used when a route has no `frecuencia_minutos`.
+pub const DEFAULT_HEADWAY_MIN: u32 = 15;
+/// Used when a route has no parseable `horario`.
+pub const DEFAULT_SERVICE_START_MIN: u32 = 5 * 60;
+pub const DEFAULT_SERVICE_END_MIN: u32 = 23 * 60;
+
+/// Minutes since midnight, Cancún. `end_min` exceeds 1440 past midnight.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TimeWindow {
+    pub start_min: u32,
+    pub end_min: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl TimeWindow {
+    fn new(start_min: u32, end_min: u32, note: Option<String>) -> Self {
+        let end_min = if end_min <= start_min { end_min + 1440 } else { end_min };
+        TimeWindow { start_min, end_min, note }
+    }
+
+    pub fn contains(&self, minute: u32) -> bool {
+        let r = self.start_min..self.end_min;
+        r.contains(&minute) || r.contains(&(minute + 1440))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ServiceHours {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regular: Option<TimeWindow>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub night_guard: Option<TimeWindow>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Service {
+    Regular,
+    /// Only the `guardia_nocturna` service runs; it leaves when full.
+    NightGuard,
+    NotRunning,
+}
+
+/// `H:MM` or `HH:MM`, up to `24:00`.
+pub(crate) fn parse_clock(s: &str) -> Option<u32> {
+    let (h, m) = s.split_once(':')?;
+    if h.is_empty() || h.len() > 2 || m.len() != 2 { return None; }
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    (m < 60 && h * 60 + m <= 1440).then_some(h * 60 + m)
+}
+
+/// Leading clock time of `s` and the rest.
+fn take_clock(s: &str) -> Option<(u32, &str)> {
+    let s = s.trim_start();
+    let end = s.find(|c: char| !(c.is_ascii_digit() || c == ':')).unwrap_or(s.len());
+    Some((parse_clock(&s[..end])?, &s[end..]))
+}
+
+/// One field: start, optional end, optional note.
+fn parse_field(s: &str) -> Result<(u32, Option<u32>, Option<String>), String> {
+    let (start, rest) = take_clock(s).ok_or("expected HH:MM")?;
+    let rest = rest.trim_start();
+    let (end, rest) = match rest.strip_prefix(['-', '–']).or_else(|| rest.strip_prefix("a ")) {
+        Some(r) => {
+            let (end, r) = take_clock(r).ok_or("expected HH:MM after range separator")?;
+            (Some(end), r)
+        }
+        None => (None, rest),
+    };
+    let note = rest.trim().trim_start_matches('(').trim_end_matches(')').trim();
+    if note.chars().next().is_some_and(|c| c.is_ascii_digit()) {
+        return Err(format!("unexpected {:?} after time", note));
+    }
+    Ok((start, end, (!note.is_empty()).then(|| note.to_string())))
+}
+
+/// A window from a start field and an optional end field.
+fn parse_pair(
+    start: (&str, &Option<String>), end: (&str, &Option<String>), issues: &mut Vec<(String, String, String)>,
+) -> Option<TimeWindow> {
+    let mut fail = |field: &str, value: &str, reason: String| {
+        issues.push((field.to_string(), value.to_string(), reason));
+        None
+    };
+    let parsed_end = match end.1.as_deref() {
+        Some(v) => match parse_field(v) {
+            Ok((t, None, _)) => Some(t),
+            Ok(_) => return fail(end.0, v, "a range is not allowed here".to_string()),
+            Err(e) => return fail(end.0, v, e),
+        },
+        None => None,
+    };
+    let Some(v) = start.1.as_deref() else {
+        return parsed_end.and_then(|_| fail(end.0, end.1.as_deref().unwrap_or_default(), format!("{} is missing", start.0)));
+    };
+    match (parse_field(v), parsed_end) {
+        (Err(e), _) => fail(start.0, v, e),
+        (Ok((s, Some(e), note)), None) => Some(TimeWindow::new(s, e, note)),
+        (Ok((_, Some(_), _)), Some(_)) => fail(start.0, v, format!("range and {} both given", end.0)),
+        (Ok((s, None, note)), Some(e)) => Some(TimeWindow::new(s, e, note)),
+        (Ok((_, None, _)), None) => fail(start.0, v, format!("no end time and no {}", end.0)),
+    }
+}
+
+impl Schedule {
+    /// Typed windows plus `(field, value, reason)` for what did not parse.
+    pub fn parse(&self) -> (ServiceHours, Vec<(String, String, String)>) {
+        let mut issues = Vec::new();
+        let official = parse_pair(("inicio_oficial", &self.inicio_oficial), ("fin_oficial", &self.fin_oficial), &mut issues);
+        let plain = parse_pair(("inicio", &self.inicio), ("fin", &self.fin), &mut issues);
+        let night_guard = self.guardia_nocturna.as_deref().and_then(|v| match parse_field(v) {
+            Ok((s, Some(e), note)) => Some(TimeWindow::new(s, e, note)),
+            Ok(_) => {
+                issues.push(("guardia_nocturna".into(), v.into(), "expected a range".into()));
+                None
+            }
+            Err(e) => {
+                issues.push(("guardia_nocturna".into(), v.into(), e));
+                None
+            }
+        });
+        (ServiceHours { regular: official.or(plain), night_guard }, issues)
+    }
+}
+
+impl ServiceHours {
+    /// Service at `minute` (since midnight, Cancún); the default window when
+    /// no regular hours parsed.
+    pub fn service_at(&self, minute: u32) -> Service {
+        let regular = self.regular.clone()
+            .unwrap_or(TimeWindow::new(DEFAULT_SERVICE_START_MIN, DEFAULT_SERVICE_END_MIN, None));
+        if regular.contains(minute) {
+            Service::Regular
+        } else if self.night_guard.as_ref().is_some_and(|w| w.contains(minute)) {
+            Service::NightGuard
+        } else {
+            Service::NotRunning
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sched(json: &str) -> Schedule {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_parse_windows() {
+        let (h, issues) = sched(r#"{"inicio_oficial": "05:00", "fin_oficial": "22:30",
+            "guardia_nocturna": "03:00 - 05:00 (Sistema de llenado en OXXO)"}"#).parse();
+        assert!(issues.is_empty());
+        assert_eq!(h.regular, Some(TimeWindow { start_min: 300, end_min: 1350, note: None }));
+        let guard = h.night_guard.unwrap();
+        assert_eq!((guard.start_min, guard.end_min), (180, 300));
+        assert_eq!(guard.note.as_deref(), Some("Sistema de llenado en OXXO"));
+
+        // Past midnight, compact ranges, 00:00 as end of day.
+        let (h, _) = sched(r#"{"inicio": "05:30", "fin": "00:30"}"#).parse();
+        assert_eq!(h.regular.as_ref().map(|w| w.end_min), Some(1470));
+        assert!(h.regular.unwrap().contains(10));
+        let (h, _) = sched(r#"{"inicio_oficial": "04:00-23:00"}"#).parse();
+        assert_eq!(h.regular.map(|w| (w.start_min, w.end_min)), Some((240, 1380)));
+        let (h, _) = sched(r#"{"inicio": "05:00", "fin": "00:00"}"#).parse();
+        assert_eq!(h.regular.map(|w| w.end_min), Some(1440));
+
+        let (h, issues) = sched(r#"{"inicio": "24 horas", "guardia_nocturna": "madrugada"}"#).parse();
+        assert_eq!(h, ServiceHours::default());
+        let fields: Vec<&str> = issues.iter().map(|i| i.0.as_str()).collect();
+        assert_eq!(fields, ["inicio", "guardia_nocturna"]);
+        assert_eq!(sched(r#"{"inicio": "06:00"}"#).parse().1.len(), 1);
+    }
+}
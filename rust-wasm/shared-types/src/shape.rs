@@ -0,0 +1,154 @@
+//! Route shapes: traced polylines that are independent from the stop list.
+//!
+//! Points are `[lat, lng]`, like `EtaInput.stops`. Projection uses a local
+//! equirectangular approximation per segment, which is well below GPS noise
+//! at Cancún's scale; lengths are haversine.
+
+use crate::haversine_distance;
+use serde::{Deserialize, Serialize};
+
+pub type LatLng = [f64; 2];
+
+/// Stops farther than this from a shape mean the shape does not belong to them.
+pub const MAX_STOP_OFF_SHAPE_M: f64 = 250.0;
+
+/// Where a point lands on a shape.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Projection {
+    /// Distance from the start of the shape to the projected point, meters.
+    pub offset_m: f64,
+    /// Distance from the point to the shape, meters.
+    pub off_shape_m: f64,
+    /// Index of the segment (`shape[i]..shape[i + 1]`) hit.
+    pub segment: usize,
+}
+
+/// Distance from the first vertex to each vertex, meters.
+pub fn cumulative_m(shape: &[LatLng]) -> Vec<f64> {
+    let mut acc = 0.0;
+    let mut out = Vec::with_capacity(shape.len());
+    for (i, p) in shape.iter().enumerate() {
+        if i > 0 {
+            let q = shape[i - 1];
+            acc += haversine_distance(q[0], q[1], p[0], p[1]);
+        }
+        out.push(acc);
+    }
+    out
+}
+
+pub fn polyline_length_m(shape: &[LatLng]) -> f64 {
+    cumulative_m(shape).last().copied().unwrap_or(0.0)
+}
+
+/// Fraction (0..=1) along segment `a→b` closest to `p`.
+fn segment_t(a: LatLng, b: LatLng, p: LatLng) -> f64 {
+    let k = a[0].to_radians().cos();
+    let (bx, by) = ((b[1] - a[1]) * k, b[0] - a[0]);
+    let (px, py) = ((p[1] - a[1]) * k, p[0] - a[0]);
+    let len2 = bx * bx + by * by;
+    if len2 == 0.0 { return 0.0; }
+    ((px * bx + py * by) / len2).clamp(0.0, 1.0)
+}
+
+fn project_from(shape: &[LatLng], cum: &[f64], p: LatLng, min_offset_m: f64) -> Option<Projection> {
+    let mut best: Option<Projection> = None;
+    let first = cum.partition_point(|&c| c <= min_offset_m).saturating_sub(1);
+    for i in first..shape.len().saturating_sub(1) {
+        let (a, b) = (shape[i], shape[i + 1]);
+        let len = cum[i + 1] - cum[i];
+        let t_min = if len > 0.0 { ((min_offset_m - cum[i]) / len).clamp(0.0, 1.0) } else { 0.0 };
+        let t = segment_t(a, b, p).max(t_min);
+        let hit = [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t];
+        let off_shape_m = haversine_distance(p[0], p[1], hit[0], hit[1]);
+        // Ties (within 1 m) keep the earlier segment so loops stay in order.
+        if best.is_none_or(|b| off_shape_m + 1.0 < b.off_shape_m) {
+            best = Some(Projection { offset_m: cum[i] + len * t, off_shape_m, segment: i });
+        }
+    }
+    best
+}
+
+/// Closest point of the whole shape to `p`.
+pub fn project_point(shape: &[LatLng], p: LatLng) -> Option<Projection> {
+    project_from(shape, &cumulative_m(shape), p, 0.0)
+}
+
+/// Projects an ordered stop list so offsets never go backwards: each stop is
+/// searched from the previous stop's offset on. Needed for out-and-back
+/// shapes (Zona Hotelera) where both directions run side by side.
+pub fn project_stops(shape: &[LatLng], stops: &[LatLng]) -> Vec<Projection> {
+    if shape.len() < 2 { return Vec::new(); }
+    let cum = cumulative_m(shape);
+    let mut from = 0.0;
+    let mut out = Vec::with_capacity(stops.len());
+    for p in stops {
+        let Some(proj) = project_from(shape, &cum, *p, from) else { break };
+        from = proj.offset_m;
+        out.push(proj);
+    }
+    out
+}
+
+/// Along-shape distance covered by an ordered stop sequence, meters.
+/// `None` when the shape is unusable or a stop is off it, so callers can
+/// fall back to haversine.
+pub fn along_shape_distance_m(shape: &[LatLng], stops: &[LatLng]) -> Option<f64> {
+    if stops.len() < 2 { return None; }
+    let proj = project_stops(shape, stops);
+    if proj.len() != stops.len() || proj.iter().any(|p| p.off_shape_m > MAX_STOP_OFF_SHAPE_M) {
+        return None;
+    }
+    Some(proj[proj.len() - 1].offset_m - proj[0].offset_m)
+}
+
+/// The part of the shape between two offsets, endpoints interpolated.
+pub fn slice_shape(shape: &[LatLng], from_m: f64, to_m: f64) -> Vec<LatLng> {
+    if shape.len() < 2 { return shape.to_vec(); }
+    let cum = cumulative_m(shape);
+    let (lo, hi) = if from_m <= to_m { (from_m, to_m) } else { (to_m, from_m) };
+    let at = |d: f64| -> LatLng {
+        let i = cum.partition_point(|&c| c < d).clamp(1, shape.len() - 1);
+        let span = cum[i] - cum[i - 1];
+        let t = if span > 0.0 { ((d - cum[i - 1]) / span).clamp(0.0, 1.0) } else { 0.0 };
+        let (a, b) = (shape[i - 1], shape[i]);
+        [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+    };
+    let mut out = vec![at(lo)];
+    out.extend(shape.iter().zip(&cum).filter(|(_, &c)| c > lo && c < hi).map(|(p, _)| *p));
+    out.push(at(hi));
+    if from_m > to_m { out.reverse(); }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An L along Av. Kukulcán: 0.01° north then 0.01° east.
+    const L: [LatLng; 3] = [[21.10, -86.77], [21.11, -86.77], [21.11, -86.76]];
+
+    #[test]
+    fn test_along_shape_longer_than_straight_line() {
+        let stops = [[21.10, -86.77], [21.11, -86.76]];
+        let along = along_shape_distance_m(&L, &stops).unwrap();
+        let straight = haversine_distance(21.10, -86.77, 21.11, -86.76);
+        assert!((along - polyline_length_m(&L)).abs() < 1.0);
+        assert!(along > straight * 1.3);
+    }
+
+    #[test]
+    fn test_out_and_back_projection_stays_ordered() {
+        let shape = [[21.10, -86.77], [21.12, -86.77], [21.10, -86.77]];
+        // Same spot visited going out and coming back.
+        let stops = [[21.10, -86.77], [21.11, -86.77], [21.12, -86.77], [21.11, -86.77]];
+        let proj = project_stops(&shape, &stops);
+        assert_eq!(proj.len(), 4);
+        assert!(proj.windows(2).all(|w| w[0].offset_m <= w[1].offset_m));
+        assert_eq!(proj[3].segment, 1);
+
+        let part = slice_shape(&L, 500.0, polyline_length_m(&L) - 500.0);
+        assert_eq!(part.len(), 3);
+        assert_eq!(part[1], L[1]);
+    }
+}
@@ -0,0 +1,129 @@
+//! Local weather observations and what they do to travel times.
+//!
+//! Rain slows every vehicle, heavy rain and tropical storms also jam the
+//! roads, and flooded streets (common in Cancún after a downpour) make
+//! walking and cycling through them much slower.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{haversine_distance, shape, TrafficConditions};
+
+/// Congestion never goes above this, weather included.
+const MAX_CONGESTION: f64 = 3.0;
+/// Walking or cycling through a flooded street.
+const FLOOD_WALK_FACTOR: f64 = 1.6;
+/// Walking or cycling during a tropical storm, floods or not.
+const STORM_WALK_FACTOR: f64 = 1.3;
+
+fn default_flood_radius_m() -> f64 {
+    300.0
+}
+
+/// A reported flooded street.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FloodReport {
+    pub lat: f64,
+    pub lng: f64,
+    #[serde(default = "default_flood_radius_m")]
+    pub radius_m: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct WeatherObservation {
+    /// Precipitation rate, mm/h.
+    #[serde(default)]
+    pub precipitation_mm_h: f64,
+    #[serde(default)]
+    pub tropical_storm: bool,
+    #[serde(default)]
+    pub flooding: Vec<FloodReport>,
+}
+
+impl WeatherObservation {
+    /// Speed penalty for any vehicle, 1.0 to 1.5.
+    pub fn rain_factor(&self) -> f64 {
+        if self.tropical_storm { return 1.5; }
+        match self.precipitation_mm_h {
+            p if p <= 0.0 => 1.0,
+            p if p < 2.5 => 1.1,  // light
+            p if p < 7.6 => 1.25, // moderate
+            _ => 1.4,             // heavy
+        }
+    }
+
+    /// Extra congestion on top of the traffic calendar.
+    pub fn congestion_multiplier(&self) -> f64 {
+        let mut m = 1.0;
+        if self.tropical_storm {
+            m *= 1.4;
+        } else if self.precipitation_mm_h >= 7.6 {
+            m *= 1.15;
+        }
+        if !self.flooding.is_empty() {
+            m *= 1.2;
+        }
+        m
+    }
+
+    /// Whether `path` (`[lat, lng]`) passes through a reported flood.
+    pub fn floods_on(&self, path: &[[f64; 2]]) -> bool {
+        self.flooding.iter().any(|f| {
+            let off_m = match path {
+                [p] => Some(haversine_distance(p[0], p[1], f.lat, f.lng)),
+                _ => shape::project_point(path, [f.lat, f.lng]).map(|p| p.off_shape_m),
+            };
+            off_m.is_some_and(|d| d <= f.radius_m)
+        })
+    }
+
+    /// Penalty for walking or cycling along `path`.
+    pub fn walk_factor(&self, path: &[[f64; 2]]) -> f64 {
+        let flood = if self.floods_on(path) { FLOOD_WALK_FACTOR } else { 1.0 };
+        let storm = if self.tropical_storm { STORM_WALK_FACTOR } else { 1.0 };
+        flood * storm
+    }
+}
+
+impl TrafficConditions {
+    /// These conditions under `weather`: rain factor replaced, congestion
+    /// raised.
+    pub fn with_weather(mut self, weather: &WeatherObservation) -> Self {
+        self.rain_factor = weather.rain_factor();
+        self.congestion_factor = (self.congestion_factor * weather.congestion_multiplier()).min(MAX_CONGESTION);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weather_factors() {
+        let dry = WeatherObservation::default();
+        let t = TrafficConditions::from_hour(8).with_weather(&dry);
+        assert_eq!((t.congestion_factor, t.rain_factor), (2.2, 1.0));
+
+        let heavy = WeatherObservation { precipitation_mm_h: 12.0, ..Default::default() };
+        let t = TrafficConditions::from_hour(12).with_weather(&heavy);
+        assert_eq!(t.rain_factor, 1.4);
+        assert!(t.congestion_factor > 1.1);
+
+        let storm = WeatherObservation { tropical_storm: true, ..Default::default() };
+        let t = TrafficConditions::from_hour(18).with_weather(&storm);
+        assert_eq!((t.congestion_factor, t.rain_factor), (MAX_CONGESTION, 1.5));
+    }
+
+    #[test]
+    fn test_flooded_walk() {
+        let w: WeatherObservation = serde_json::from_str(
+            r#"{"precipitation_mm_h": 5, "flooding": [{"lat": 21.1605, "lng": -86.8475}]}"#,
+        ).unwrap();
+        assert_eq!(w.flooding[0].radius_m, 300.0);
+        // Neither end is near the flood, the street between them is.
+        let through = [[21.1619, -86.8600], [21.1600, -86.8350]];
+        let away = [[21.1350, -86.7680], [21.1400, -86.7700]];
+        assert_eq!(w.walk_factor(&through), FLOOD_WALK_FACTOR);
+        assert_eq!(w.walk_factor(&away), 1.0);
+    }
+}
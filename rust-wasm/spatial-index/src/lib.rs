@@ -2,6 +2,7 @@ use wasm_bindgen::prelude::*;
 use serde::{Deserialize, Serialize};
 use rstar::{RTree, PointDistance, RTreeObject, AABB};
 use shared_types::{Stop, haversine_distance};
+use shared_types::exclusions::Exclusions;
 use once_cell::sync::Lazy;
 use std::sync::RwLock;
 use std::hash::{Hash, Hasher};
@@ -61,6 +62,20 @@ pub fn find_nearest_stop(user_lat: f64, user_lng: f64, stops_val: JsValue) -> Js
     }
 }
 
+/// Nearest stop that is not excluded (by name, id or avoided area), or
+/// `null`. Throws on malformed exclusions.
+#[wasm_bindgen]
+pub fn find_nearest_stop_excluding(user_lat: f64, user_lng: f64, stops_val: JsValue, exclusions_val: JsValue) -> Result<JsValue, JsValue> {
+    let stops: Vec<Stop> = serde_wasm_bindgen::from_value(stops_val).unwrap_or_default();
+    let exclusions: Exclusions = serde_wasm_bindgen::from_value(exclusions_val)
+        .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+    match find_nearest_stop_excluding_native(user_lat, user_lng, &stops, &exclusions).map_err(|e| JsValue::from_str(&e))? {
+        Some(result) => serde_wasm_bindgen::to_value(&result).map_err(|e| JsValue::from_str(&e.to_string())),
+        None => Ok(JsValue::NULL),
+    }
+}
+
 fn calculate_stops_hash(stops: &[Stop]) -> u64 {
     let mut hasher = DefaultHasher::new();
     for stop in stops {
@@ -74,6 +89,16 @@ fn calculate_stops_hash(stops: &[Stop]) -> u64 {
 }
 
 pub fn find_nearest_stop_native(user_lat: f64, user_lng: f64, stops: &[Stop]) -> Option<NearestStopResult> {
+    find_nearest_matching(user_lat, user_lng, stops, |_| true)
+}
+
+pub fn find_nearest_stop_excluding_native(user_lat: f64, user_lng: f64, stops: &[Stop], exclusions: &Exclusions) -> Result<Option<NearestStopResult>, String> {
+    exclusions.validate()?;
+    let m = exclusions.matcher();
+    Ok(find_nearest_matching(user_lat, user_lng, stops, |s| !m.excludes_stop(&[&s.id], &s.name, s.lat, s.lng)))
+}
+
+fn find_nearest_matching(user_lat: f64, user_lng: f64, stops: &[Stop], keep: impl Fn(&Stop) -> bool) -> Option<NearestStopResult> {
     let scaled_point = [user_lat, user_lng * LNG_SCALE];
 
     if stops.is_empty() {
@@ -86,7 +111,7 @@ pub fn find_nearest_stop_native(user_lat: f64, user_lng: f64, stops: &[Stop]) ->
     if let Ok(index_guard) = SPATIAL_INDEX.read() {
         if let Some(cached) = &*index_guard {
             if cached.hash == current_hash {
-                if let Some(nearest) = cached.rtree.nearest_neighbor(scaled_point) {
+                if let Some(nearest) = cached.rtree.nearest_neighbor_iter(scaled_point).find(|w| keep(&w.0)) {
                     return Some(create_result(user_lat, user_lng, &nearest.0));
                 }
             }
@@ -97,11 +122,9 @@ pub fn find_nearest_stop_native(user_lat: f64, user_lng: f64, stops: &[Stop]) ->
     let wrappers: Vec<StopWrapper> = stops.iter().cloned().map(StopWrapper).collect();
     let rtree = RTree::bulk_load(wrappers);
     
-    let result = if let Some(nearest) = rtree.nearest_neighbor(scaled_point) {
-        Some(create_result(user_lat, user_lng, &nearest.0))
-    } else {
-        None
-    };
+    let result = rtree.nearest_neighbor_iter(scaled_point)
+        .find(|w| keep(&w.0))
+        .map(|nearest| create_result(user_lat, user_lng, &nearest.0));
 
     // Cache the built tree for future use
     if let Ok(mut index_guard) = SPATIAL_INDEX.write() {
@@ -196,4 +219,22 @@ mod tests {
         // which includes the stop it found, so we can assert on the ID.
         assert_eq!(res_b.stop.id, "B1", "Second query should return stop from dataset B, not use cached A");
     }
+
+    #[test]
+    fn test_nearest_skips_excluded_stops() {
+        let stop = |id: &str, name: &str, lat: f64| Stop { id: id.to_string(), name: name.to_string(), lat, lng: -86.77, order: 0 };
+        let stops = vec![stop("K9", "Km 9", 21.135), stop("K12", "Km 12", 21.125), stop("K15", "Km 15", 21.11)];
+
+        let ex = Exclusions { stops: vec!["km 9".to_string()], ..Default::default() };
+        let res = find_nearest_stop_excluding_native(21.136, -86.77, &stops, &ex).unwrap().unwrap();
+        assert_eq!(res.stop.id, "K12");
+
+        let ex = Exclusions { avoid_area: Some(vec![[21.12, -86.78], [21.14, -86.78], [21.14, -86.76], [21.12, -86.76]]), ..Default::default() };
+        let res = find_nearest_stop_excluding_native(21.136, -86.77, &stops, &ex).unwrap().unwrap();
+        assert_eq!(res.stop.id, "K15");
+        assert_eq!(find_nearest_stop_native(21.136, -86.77, &stops).unwrap().stop.id, "K9");
+
+        let ex = Exclusions { avoid_area: Some(vec![[21.12, -86.78], [21.14, -86.78]]), ..Default::default() };
+        assert!(find_nearest_stop_excluding_native(21.136, -86.77, &stops, &ex).is_err());
+    }
 }